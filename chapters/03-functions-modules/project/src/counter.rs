@@ -3,6 +3,8 @@ pub struct CountResult {
     pub lines: usize,
     pub words: usize,
     pub chars: usize,
+    /// 最长一行的字符数（不含换行符）
+    pub max_line_len: usize,
 }
 
 /// 统计文本的行数、单词数、字符数
@@ -10,6 +12,36 @@ pub fn count_text(text: &str) -> CountResult {
     let lines = text.lines().count();
     let words = text.split_whitespace().count();
     let chars = text.chars().count();
+    // 按字符而不是字节计算，中文等多字节字符也算 1 个；
+    // 制表符和控制字符同样简单地算作 1 个字符
+    let max_line_len = text
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
 
-    CountResult { lines, words, chars }
+    CountResult {
+        lines,
+        words,
+        chars,
+        max_line_len,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_line_len() {
+        let result = count_text("short\na much longer line\n\nmid line\n");
+        assert_eq!(result.lines, 4);
+        assert_eq!(result.max_line_len, 18);
+
+        // 多字节字符、制表符都按 1 个字符计算，\r\n 不计入长度
+        let result = count_text("你好世界\r\na\tb\n");
+        assert_eq!(result.max_line_len, 4);
+
+        assert_eq!(count_text("").max_line_len, 0);
+    }
 }
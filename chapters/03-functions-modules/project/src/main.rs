@@ -6,21 +6,25 @@ use std::fs;
 use std::io::{self, Read};
 
 fn main() {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // -L: 额外输出最长行的长度（与 wc -L 相同）
+    let show_max_line = args.iter().any(|a| a == "-L");
+    args.retain(|a| a != "-L");
 
     if args.is_empty() {
         // 从标准输入读取
         let mut text = String::new();
         io::stdin().read_to_string(&mut text).unwrap();
         let result = counter::count_text(&text);
-        output::print_result(&result, None);
+        output::print_result(&result, None, show_max_line);
     } else {
         // 从文件读取
         for filename in &args {
             match fs::read_to_string(filename) {
                 Ok(text) => {
                     let result = counter::count_text(&text);
-                    output::print_result(&result, Some(filename));
+                    output::print_result(&result, Some(filename), show_max_line);
                 }
                 Err(e) => {
                     eprintln!("word-count: {}: {}", filename, e);
@@ -1,19 +1,16 @@
 use crate::counter::CountResult;
 
 /// 格式化输出统计结果
-pub fn print_result(result: &CountResult, filename: Option<&str>) {
+///
+/// `show_max_line` 为 true 时（-L）在最后追加一列最长行的长度
+pub fn print_result(result: &CountResult, filename: Option<&str>, show_max_line: bool) {
+    let mut line = format!("{:>8}{:>8}{:>8}", result.lines, result.words, result.chars);
+    if show_max_line {
+        line.push_str(&format!("{:>8}", result.max_line_len));
+    }
+
     match filename {
-        Some(name) => {
-            println!(
-                "{:>8}{:>8}{:>8} {}",
-                result.lines, result.words, result.chars, name
-            );
-        }
-        None => {
-            println!(
-                "{:>8}{:>8}{:>8}",
-                result.lines, result.words, result.chars
-            );
-        }
+        Some(name) => println!("{} {}", line, name),
+        None => println!("{}", line),
     }
 }
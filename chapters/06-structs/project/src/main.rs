@@ -1,4 +1,20 @@
 use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// 默认的数据文件
+const DEFAULT_DATA_FILE: &str = "tasks.txt";
+
+/// 数据文件路径：设置了环境变量 TASK_FILE 时使用它，否则用默认文件
+///
+/// 这一版的任务只保存在内存里，路径先用于提示，后面章节加入文件读写时直接沿用。
+/// 测试或者想维护多份清单时，改环境变量就能换一个文件
+fn data_path() -> PathBuf {
+    match env::var_os("TASK_FILE") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(DEFAULT_DATA_FILE),
+    }
+}
 
 struct Task {
     id: u32,
@@ -38,17 +54,43 @@ fn list_tasks(tasks: &[Task]) {
     }
 }
 
-fn print_help() {
+/// 清空任务列表，返回删除的任务数
+fn clear_tasks(tasks: &mut Vec<Task>) -> usize {
+    let count = tasks.len();
+    tasks.clear();
+    count
+}
+
+/// 询问用户确认，只有输入 y/yes 才返回 true
+fn confirm(prompt: &str) -> bool {
+    print!("{} (y/N) ", prompt);
+    // print! 不会自动刷新缓冲区，需要手动 flush 让提示立即显示
+    io::stdout().flush().ok();
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).ok();
+
+    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn print_help(data_file: &Path) {
     println!("task-cli - 命令行待办事项管理器");
+    println!(
+        "数据文件: {} (可用环境变量 TASK_FILE 修改)",
+        data_file.display()
+    );
     println!();
     println!("用法:");
     println!("  task add <任务内容>  添加任务");
     println!("  task list            列出任务");
     println!("  task done <ID>       标记完成");
+    println!("  task clear           清空所有任务");
 }
 
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
+    // 只在启动时读取一次环境变量
+    let data_file = data_path();
     let mut tasks: Vec<Task> = Vec::new();
     let mut next_id: u32 = 1;
 
@@ -59,7 +101,7 @@ fn main() {
     next_id += 1;
 
     if args.is_empty() {
-        print_help();
+        print_help(&data_file);
         return;
     }
 
@@ -104,9 +146,43 @@ fn main() {
                 }
             }
         }
+        "clear" => {
+            if tasks.is_empty() {
+                println!("没有需要清空的任务");
+                return;
+            }
+
+            let prompt = format!("确定要删除全部 {} 个任务吗？", tasks.len());
+            if !confirm(&prompt) {
+                println!("已取消");
+                return;
+            }
+
+            let removed = clear_tasks(&mut tasks);
+            println!("✓ 已清空 {} 个任务", removed);
+        }
         _ => {
             println!("未知命令: {}", command);
-            print_help();
+            print_help(&data_file);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_path_env_override() {
+        // 环境变量是整个进程共享的，相关断言放在同一个测试里，避免并行测试互相干扰
+        env::set_var("TASK_FILE", "/tmp/work-tasks.txt");
+        assert_eq!(data_path(), PathBuf::from("/tmp/work-tasks.txt"));
+
+        // 空字符串视为没有设置
+        env::set_var("TASK_FILE", "");
+        assert_eq!(data_path(), PathBuf::from(DEFAULT_DATA_FILE));
+
+        env::remove_var("TASK_FILE");
+        assert_eq!(data_path(), PathBuf::from("tasks.txt"));
+    }
+}
@@ -61,51 +61,190 @@ impl Task {
         self.status = Status::Done;
     }
 
-    fn display(&self) {
+    fn reset(&mut self) {
+        self.status = Status::Pending;
+    }
+
+    /// 表格中的一行：ID、优先级、状态、截止、任务
+    fn row(&self) -> [String; 5] {
         let due = match &self.due_date {
             Some(date) => date.as_str(),
             None => "-",
         };
 
-        println!(
-            "{:>3}  {:>4}  {:>6}  {:>10}  {}",
-            self.id,
-            self.priority.as_str(),
-            self.status.as_str(),
-            due,
-            self.title
-        );
+        [
+            self.id.to_string(),
+            self.priority.as_str().to_string(),
+            self.status.as_str().to_string(),
+            due.to_string(),
+            self.title.clone(),
+        ]
     }
 }
 
+/// 单个字符在终端中占几列
+///
+/// 中日韩文字和全角符号占 2 列，控制字符不占位置，其余按 1 列计算。
+/// 完整的规则见 Unicode 的 East Asian Width，这里只覆盖常见的宽字符区间
+///
+/// 第 17 章 common 库的 char_width 用的是同一份区间表，修改时两处要一起改
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x00..=0x1F | 0x7F => 0,
+        0x1100..=0x115F          // 韩文字母
+        | 0x2E80..=0x303E        // CJK 部首、标点
+        | 0x3041..=0x33FF        // 日文假名、CJK 符号
+        | 0x3400..=0x4DBF        // CJK 扩展 A
+        | 0x4E00..=0x9FFF        // CJK 统一汉字
+        | 0xA000..=0xA4CF        // 彝文
+        | 0xAC00..=0xD7A3        // 韩文音节
+        | 0xF900..=0xFAFF        // CJK 兼容汉字
+        | 0xFE30..=0xFE4F        // CJK 兼容标点
+        | 0xFF00..=0xFF60        // 全角 ASCII、全角标点
+        | 0xFFE0..=0xFFE6        // 全角符号
+        | 0x1F300..=0x1F64F      // 表情符号
+        | 0x20000..=0x3FFFD => 2, // CJK 扩展 B 及以后
+        _ => 1,
+    }
+}
+
+/// 字符串的显示宽度
+///
+/// `{:<10}` 按字符个数补齐，一个汉字算 1 个字符却占 2 列，
+/// 所以中文标题用 format! 对齐会错位，需要按显示宽度自己补空格
+fn display_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+/// 在右侧补空格，使显示宽度达到 width
+fn pad(s: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(s));
+    format!("{}{}", s, " ".repeat(fill))
+}
+
 fn list_tasks(tasks: &[Task]) {
     if tasks.is_empty() {
         println!("没有任务");
         return;
     }
 
-    println!(
-        "{:>3}  {:>4}  {:>6}  {:>10}  任务",
-        "ID", "优先级", "状态", "截止"
-    );
-    println!("{}", "-".repeat(60));
+    print!("{}", format_table(tasks));
+}
+
+/// 把任务渲染成带边框的表格，每列宽度取该列最宽的内容
+fn format_table(tasks: &[Task]) -> String {
+    let header = ["ID", "优先级", "状态", "截止", "任务"].map(String::from);
+    let rows: Vec<[String; 5]> = tasks.iter().map(Task::row).collect();
+
+    let mut widths = header.clone().map(|h| display_width(&h));
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    // 边框线：每列两侧各留一个空格
+    let border = |left: &str, mid: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{}{}{}\n", left, segments.join(mid), right)
+    };
+    let line = |cells: &[String; 5]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &w)| format!(" {} ", pad(cell, w)))
+            .collect();
+        format!("│{}│\n", cells.join("│"))
+    };
+
+    let mut table = border("┌", "┬", "┐");
+    table.push_str(&line(&header));
+    table.push_str(&border("├", "┼", "┤"));
+    for row in &rows {
+        table.push_str(&line(row));
+    }
+    table.push_str(&border("└", "┴", "┘"));
+    table
+}
+
+/// 按状态统计任务个数，顺序为 [待办, 进行中, 完成]
+///
+/// 只遍历一次；match 必须覆盖 Status 的每个变体，以后新增状态时编译器会提醒这里
+fn count_by_status(tasks: &[Task]) -> [usize; 3] {
+    let mut counts = [0; 3];
     for task in tasks {
-        task.display();
+        let index = match task.status {
+            Status::Pending => 0,
+            Status::InProgress => 1,
+            Status::Done => 2,
+        };
+        counts[index] += 1;
     }
+    counts
+}
+
+fn print_stats(tasks: &[Task]) {
+    let [pending, in_progress, done] = count_by_status(tasks);
+    println!("{}: {}", Status::Pending.as_str(), pending);
+    println!("{}: {}", Status::InProgress.as_str(), in_progress);
+    println!("{}: {}", Status::Done.as_str(), done);
+    println!("总计: {}", tasks.len());
 }
 
 fn find_task_mut(tasks: &mut [Task], id: u32) -> Option<&mut Task> {
     tasks.iter_mut().find(|t| t.id == id)
 }
 
+/// 批量操作中单个 ID 的处理结果
+#[derive(Debug, PartialEq)]
+enum BatchResult {
+    /// 操作成功，附带任务 ID
+    Applied(u32),
+    /// ID 合法但任务不存在
+    NotFound(u32),
+    /// 参数不是合法的数字
+    InvalidId(String),
+}
+
+/// 对每个 ID 执行同一个操作
+///
+/// 某个参数出错不会中断整个批次，剩下的 ID 照常处理
+fn apply_batch(tasks: &mut [Task], ids: &[String], action: fn(&mut Task)) -> Vec<BatchResult> {
+    ids.iter()
+        .map(|arg| match arg.parse::<u32>() {
+            Ok(id) => match find_task_mut(tasks, id) {
+                Some(task) => {
+                    action(task);
+                    BatchResult::Applied(id)
+                }
+                None => BatchResult::NotFound(id),
+            },
+            Err(_) => BatchResult::InvalidId(arg.clone()),
+        })
+        .collect()
+}
+
+/// 打印批量操作的结果，verb 是成功时的动作描述（如「已开始」）
+fn report_batch(results: &[BatchResult], verb: &str) {
+    for result in results {
+        match result {
+            BatchResult::Applied(id) => println!("✓ 任务 #{} {}", id, verb),
+            BatchResult::NotFound(id) => println!("找不到任务 #{}", id),
+            BatchResult::InvalidId(arg) => println!("无效的 ID: {}", arg),
+        }
+    }
+}
+
 fn print_help() {
     println!("task-cli - 命令行待办事项管理器");
     println!();
     println!("用法:");
     println!("  task add <任务内容>  添加任务");
     println!("  task list            列出任务");
-    println!("  task start <ID>      开始任务");
-    println!("  task done <ID>       完成任务");
+    println!("  task stats           按状态统计任务");
+    println!("  task start <ID>...   开始任务");
+    println!("  task done <ID>...    完成任务");
+    println!("  task undo <ID>...    恢复为待办");
 }
 
 fn main() {
@@ -147,37 +286,22 @@ fn main() {
         "list" => {
             list_tasks(&tasks);
         }
-        "start" => {
-            if args.len() < 2 {
-                println!("用法: task start <ID>");
-                return;
-            }
-            if let Ok(id) = args[1].parse::<u32>() {
-                if let Some(task) = find_task_mut(&mut tasks, id) {
-                    task.start();
-                    println!("✓ 任务 #{} 已开始: {}", id, task.title);
-                } else {
-                    println!("找不到任务 #{}", id);
-                }
-            } else {
-                println!("无效的 ID: {}", args[1]);
-            }
+        "stats" => {
+            print_stats(&tasks);
         }
-        "done" => {
+        "start" | "done" | "undo" => {
             if args.len() < 2 {
-                println!("用法: task done <ID>");
+                println!("用法: task {} <ID>...", command);
                 return;
             }
-            if let Ok(id) = args[1].parse::<u32>() {
-                if let Some(task) = find_task_mut(&mut tasks, id) {
-                    task.complete();
-                    println!("✓ 任务 #{} 已完成: {}", id, task.title);
-                } else {
-                    println!("找不到任务 #{}", id);
-                }
-            } else {
-                println!("无效的 ID: {}", args[1]);
-            }
+            // 函数也可以作为值传递：Task::start 的类型就是 fn(&mut Task)
+            let (action, verb): (fn(&mut Task), &str) = match command.as_str() {
+                "start" => (Task::start, "已开始"),
+                "done" => (Task::complete, "已完成"),
+                _ => (Task::reset, "已恢复为待办"),
+            };
+            let results = apply_batch(&mut tasks, &args[1..], action);
+            report_batch(&results, verb);
         }
         _ => {
             println!("未知命令: {}", command);
@@ -185,3 +309,121 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_tasks() -> Vec<Task> {
+        vec![
+            Task::new(1, String::from("a")),
+            Task::new(2, String::from("b")),
+            Task::new(3, String::from("c")),
+        ]
+    }
+
+    #[test]
+    fn test_display_width_mixed() {
+        assert_eq!(display_width(""), 0);
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("任务"), 4);
+        assert_eq!(display_width("写 Rust 代码"), 12);
+        // 全角标点、假名、韩文都占 2 列
+        assert_eq!(display_width("你好，世界！"), 12);
+        assert_eq!(display_width("カナ"), 4);
+        assert_eq!(display_width("한국"), 4);
+    }
+
+    #[test]
+    fn test_pad_by_display_width() {
+        assert_eq!(pad("中", 4), "中  ");
+        assert_eq!(pad("ab", 4), "ab  ");
+        // 内容比宽度长时不截断
+        assert_eq!(pad("中文字", 4), "中文字");
+    }
+
+    #[test]
+    fn test_table_lines_align() {
+        let mut tasks = vec![
+            Task::new(1, String::from("写 Rust 代码")),
+            Task::new(12, String::from("review")),
+        ];
+        tasks[0].due_date = Some(String::from("2024-06-01"));
+        tasks[1].start();
+
+        let table = format_table(&tasks);
+        let lines: Vec<&str> = table.lines().collect();
+
+        // 表头、三条边框、两行数据
+        assert_eq!(lines.len(), 6);
+        // 中英混排时每一行的显示宽度都相同，竖线才能对齐
+        let width = display_width(lines[0]);
+        assert!(lines.iter().all(|l| display_width(l) == width), "{}", table);
+        assert!(lines[3].contains("写 Rust 代码"));
+    }
+
+    fn ids(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_batch_applies_to_each_id() {
+        let mut tasks = sample_tasks();
+
+        let results = apply_batch(&mut tasks, &ids(&["1", "3"]), Task::complete);
+
+        assert_eq!(
+            results,
+            vec![BatchResult::Applied(1), BatchResult::Applied(3)]
+        );
+        assert_eq!(tasks[0].status, Status::Done);
+        assert_eq!(tasks[1].status, Status::Pending);
+        assert_eq!(tasks[2].status, Status::Done);
+    }
+
+    #[test]
+    fn test_batch_continues_after_errors() {
+        let mut tasks = sample_tasks();
+
+        let results = apply_batch(&mut tasks, &ids(&["x", "9", "2"]), Task::start);
+
+        assert_eq!(
+            results,
+            vec![
+                BatchResult::InvalidId(String::from("x")),
+                BatchResult::NotFound(9),
+                BatchResult::Applied(2),
+            ]
+        );
+        assert_eq!(tasks[1].status, Status::InProgress);
+    }
+
+    #[test]
+    fn test_batch_undo() {
+        let mut tasks = sample_tasks();
+        apply_batch(&mut tasks, &ids(&["1", "2"]), Task::complete);
+
+        apply_batch(&mut tasks, &ids(&["1"]), Task::reset);
+
+        assert_eq!(tasks[0].status, Status::Pending);
+        assert_eq!(tasks[1].status, Status::Done);
+    }
+
+    #[test]
+    fn test_count_by_status() {
+        let mut tasks = sample_tasks();
+        tasks.push(Task::new(4, String::from("d")));
+        tasks[0].start();
+        tasks[1].complete();
+        tasks[3].complete();
+
+        assert_eq!(count_by_status(&tasks), [1, 1, 2]);
+        assert_eq!(count_by_status(&tasks).iter().sum::<usize>(), tasks.len());
+    }
+
+    #[test]
+    fn test_count_by_status_empty() {
+        assert_eq!(count_by_status(&[]), [0, 0, 0]);
+        assert_eq!(count_by_status(&sample_tasks()), [3, 0, 0]);
+    }
+}
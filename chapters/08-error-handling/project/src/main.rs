@@ -1,6 +1,8 @@
 use std::env;
-use std::fs::File;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader, Write};
+use std::num::IntErrorKind;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Status {
@@ -100,12 +102,63 @@ impl Task {
 
 const DATA_FILE: &str = "tasks.txt";
 
+/// 命令参数相关的错误
+#[derive(Debug, PartialEq)]
+enum TaskError {
+    /// 不是数字（包括负数、空字符串）
+    InvalidId(String),
+    /// ID 从 1 开始，0 不是合法 ID
+    ZeroId,
+    /// 数字超出 u32 范围
+    IdOutOfRange(String),
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskError::InvalidId(s) => write!(f, "无效的任务 ID '{}'，应为正整数", s),
+            TaskError::ZeroId => write!(f, "任务 ID 从 1 开始，不能为 0"),
+            TaskError::IdOutOfRange(s) => {
+                write!(f, "任务 ID '{}' 超出范围（最大 {}）", s, u32::MAX)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+/// 解析命令行中的任务 ID，所有接收 ID 的命令都通过它校验
+fn parse_id(s: &str) -> Result<u32, TaskError> {
+    match s.trim().parse::<u32>() {
+        Ok(0) => Err(TaskError::ZeroId),
+        Ok(id) => Ok(id),
+        // ParseIntError::kind() 可以区分「太大」和「根本不是数字」
+        Err(e) if *e.kind() == IntErrorKind::PosOverflow => {
+            Err(TaskError::IdOutOfRange(s.to_string()))
+        }
+        Err(_) => Err(TaskError::InvalidId(s.to_string())),
+    }
+}
+
+/// 原子保存：先完整写入临时文件，再用 rename 覆盖原文件
+///
+/// 直接 File::create 会先把原文件清空，写到一半崩溃就丢失所有任务；
+/// 而同一文件系统内的 rename 是原子的，原文件要么是旧内容，要么是新内容
 fn save_tasks(tasks: &[Task], path: &str) -> io::Result<()> {
-    let mut file = File::create(path)?;
+    let tmp = write_temp_file(tasks, path)?;
+    fs::rename(&tmp, path)
+}
+
+/// 把任务写入 `<path>.tmp`，返回临时文件路径
+fn write_temp_file(tasks: &[Task], path: &str) -> io::Result<String> {
+    let tmp = format!("{}.tmp", path);
+    let mut file = File::create(&tmp)?;
     for task in tasks {
         writeln!(file, "{}", task.to_line())?;
     }
-    Ok(())
+    // 确保数据真正落盘后再 rename
+    file.sync_all()?;
+    Ok(tmp)
 }
 
 fn load_tasks(path: &str) -> io::Result<(Vec<Task>, u32)> {
@@ -194,13 +247,18 @@ fn main() {
                 println!("用法: task start <ID>");
                 return;
             }
-            if let Ok(id) = args[1].parse::<u32>() {
-                if let Some(task) = find_task_mut(&mut tasks, id) {
-                    task.status = Status::InProgress;
-                    println!("✓ 任务 #{} 已开始", id);
-                } else {
-                    println!("找不到任务 #{}", id);
+            let id = match parse_id(&args[1]) {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("错误: {}", e);
+                    return;
                 }
+            };
+            if let Some(task) = find_task_mut(&mut tasks, id) {
+                task.status = Status::InProgress;
+                println!("✓ 任务 #{} 已开始", id);
+            } else {
+                println!("找不到任务 #{}", id);
             }
         }
         "done" => {
@@ -208,13 +266,18 @@ fn main() {
                 println!("用法: task done <ID>");
                 return;
             }
-            if let Ok(id) = args[1].parse::<u32>() {
-                if let Some(task) = find_task_mut(&mut tasks, id) {
-                    task.status = Status::Done;
-                    println!("✓ 任务 #{} 已完成", id);
-                } else {
-                    println!("找不到任务 #{}", id);
+            let id = match parse_id(&args[1]) {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("错误: {}", e);
+                    return;
                 }
+            };
+            if let Some(task) = find_task_mut(&mut tasks, id) {
+                task.status = Status::Done;
+                println!("✓ 任务 #{} 已完成", id);
+            } else {
+                println!("找不到任务 #{}", id);
             }
         }
         _ => {
@@ -227,3 +290,76 @@ fn main() {
         eprintln!("保存失败: {}", e);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_original_untouched_until_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.txt");
+        let path = path.to_str().unwrap();
+
+        save_tasks(&[Task::new(1, "旧任务".to_string())], path).unwrap();
+        let before = fs::read_to_string(path).unwrap();
+
+        // 模拟保存的第一步：只写临时文件，不 rename
+        let tasks = vec![
+            Task::new(1, "旧任务".to_string()),
+            Task::new(2, "新任务".to_string()),
+        ];
+        let tmp = write_temp_file(&tasks, path).unwrap();
+
+        assert_eq!(fs::read_to_string(path).unwrap(), before);
+        assert_eq!(fs::read_to_string(&tmp).unwrap().lines().count(), 2);
+
+        // 完成 rename 后原文件才被替换，临时文件消失
+        fs::rename(&tmp, path).unwrap();
+        let (loaded, next_id) = load_tasks(path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(next_id, 3);
+        assert!(!Path::new(&tmp).exists());
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.txt");
+        let path = path.to_str().unwrap();
+
+        let mut task = Task::new(7, "写代码".to_string());
+        task.status = Status::Done;
+        save_tasks(&[task], path).unwrap();
+
+        let (loaded, _) = load_tasks(path).unwrap();
+        assert_eq!(loaded[0].id, 7);
+        assert_eq!(loaded[0].title, "写代码");
+        assert_eq!(loaded[0].status, Status::Done);
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+    }
+
+    #[test]
+    fn test_parse_id_valid() {
+        assert_eq!(parse_id("1"), Ok(1));
+        assert_eq!(parse_id("42"), Ok(42));
+        assert_eq!(parse_id(" 7 "), Ok(7));
+    }
+
+    #[test]
+    fn test_parse_id_zero() {
+        assert_eq!(parse_id("0"), Err(TaskError::ZeroId));
+    }
+
+    #[test]
+    fn test_parse_id_garbage() {
+        assert_eq!(parse_id("abc"), Err(TaskError::InvalidId("abc".to_string())));
+        assert_eq!(parse_id("-1"), Err(TaskError::InvalidId("-1".to_string())));
+        assert_eq!(parse_id(""), Err(TaskError::InvalidId("".to_string())));
+        assert_eq!(
+            parse_id("99999999999"),
+            Err(TaskError::IdOutOfRange("99999999999".to_string()))
+        );
+    }
+}
@@ -59,6 +59,46 @@ impl fmt::Display for Task {
     }
 }
 
+/// 检查值是否满足约束
+///
+/// 和 Display 一样是普通 trait：任何类型都可以实现自己的校验规则，
+/// 调用方只需要知道 `validate()` 返回 Ok 还是 Err
+trait Validate {
+    fn validate(&self) -> Result<(), String>;
+}
+
+impl Validate for Task {
+    fn validate(&self) -> Result<(), String> {
+        if self.title.trim().is_empty() {
+            return Err(format!("任务 #{} 的标题为空", self.id));
+        }
+        if let Some(due) = &self.due_date {
+            if !is_valid_date(due) {
+                return Err(format!(
+                    "任务 #{} 的截止日期 '{}' 无效，格式应为 YYYY-MM-DD",
+                    self.id, due
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 检查日期格式是否为 YYYY-MM-DD
+///
+/// 与第 15 章 task-cli 的 is_valid_date 相同；每章是独立的 crate，所以各自保留一份
+fn is_valid_date(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('-').collect();
+    match parts.as_slice() {
+        [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => {
+            let month: u32 = m.parse().unwrap_or(0);
+            let day: u32 = d.parse().unwrap_or(0);
+            y.parse::<u32>().is_ok() && (1..=12).contains(&month) && (1..=31).contains(&day)
+        }
+        _ => false,
+    }
+}
+
 impl Task {
     fn new(id: u32, title: String) -> Task {
         Task {
@@ -126,9 +166,14 @@ fn load_tasks(path: &str) -> io::Result<(Vec<Task>, u32)> {
     let mut tasks = Vec::new();
     let mut max_id = 0u32;
 
-    for line in reader.lines() {
+    for (line_num, line) in reader.lines().enumerate() {
         let line = line?;
         if let Some(task) = Task::from_line(&line) {
+            // 文件可能被手工编辑过，不合法的任务跳过并提示
+            if let Err(e) = task.validate() {
+                eprintln!("警告: 第 {} 行已跳过: {}", line_num + 1, e);
+                continue;
+            }
             if task.id > max_id {
                 max_id = task.id;
             }
@@ -206,6 +251,10 @@ fn main() {
             }
             let title = args[1..].join(" ");
             let task = Task::new(next_id, title.clone());
+            if let Err(e) = task.validate() {
+                eprintln!("错误: {}", e);
+                return;
+            }
             println!("✓ 任务已添加 (ID: {}): {}", task.id, title);
             tasks.push(task);
             next_id += 1;
@@ -251,3 +300,52 @@ fn main() {
 
     let _ = save_tasks(&tasks, DATA_FILE);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_task() {
+        let mut task = Task::new(1, "写报告".to_string());
+        assert!(task.validate().is_ok());
+
+        task.due_date = Some("2024-06-15".to_string());
+        assert!(task.validate().is_ok());
+    }
+
+    #[test]
+    fn test_empty_title() {
+        let task = Task::new(1, "   ".to_string());
+        let err = task.validate().unwrap_err();
+        assert!(err.contains("标题为空"));
+    }
+
+    #[test]
+    fn test_bad_due_date() {
+        for due in ["2024-6-15", "2024-13-01", "tomorrow"] {
+            let mut task = Task::new(1, "写报告".to_string());
+            task.due_date = Some(due.to_string());
+            let err = task.validate().unwrap_err();
+            assert!(err.contains(due), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_load_skips_invalid_lines() {
+        let path =
+            std::env::temp_dir().join(format!("task-cli-validate-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "1|待办|中|好任务|2024-06-15\n2|待办|中|坏日期|2024/06/15\n3|待办|中| |\n",
+        )
+        .unwrap();
+
+        let (tasks, next_id) = load_tasks(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "好任务");
+        assert_eq!(next_id, 2);
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::{Deref, DerefMut};
+
 struct MiniVec<T> {
     data: Vec<T>,
 }
@@ -7,6 +9,28 @@ impl<T> MiniVec<T> {
         MiniVec { data: Vec::new() }
     }
 
+    // 预先分配至少 n 个元素的空间，push 不超过 n 个时不会重新分配
+    fn with_capacity(n: usize) -> MiniVec<T> {
+        MiniVec {
+            data: Vec::with_capacity(n),
+        }
+    }
+
+    // 已分配的空间能容纳的元素个数（>= len）
+    fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    // 保证还能再放下 additional 个元素
+    fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+    }
+
+    // 把多余的空间还给分配器
+    fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+    }
+
     fn push(&mut self, item: T) {
         self.data.push(item);
     }
@@ -70,6 +94,27 @@ impl<T: std::fmt::Debug> MiniVec<T> {
     }
 }
 
+// 实现 Deref<Target = [T]> 后，&MiniVec<T> 可以自动转换成 &[T]，
+// 切片的所有方法（iter、windows、binary_search……）都能直接调用。
+// 这就是 Vec、String 这类容器的惯用写法。
+//
+// 方法查找时先找 MiniVec 自己的方法，找不到才通过 Deref 去找切片的，
+// 所以上面的 first/last/get/contains 仍然会被优先调用
+impl<T> Deref for MiniVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.data
+    }
+}
+
+// DerefMut 提供可变切片，sort、reverse、swap 等需要 &mut [T] 的方法也能用了
+impl<T> DerefMut for MiniVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
+
 fn main() {
     println!("=== MiniVec 演示 ===\n");
 
@@ -113,5 +158,117 @@ fn main() {
     println!("包含 \"Rust\": {}", string_vec.contains(&String::from("Rust")));
     println!("第一个克隆: {:?}", string_vec.first_clone());
 
+    // 容量管理
+    println!("\n=== 容量管理 ===\n");
+    let mut nums: MiniVec<i32> = MiniVec::new();
+    let mut last_capacity = nums.capacity();
+    println!("初始容量: {}", last_capacity);
+    for i in 0..20 {
+        nums.push(i);
+        // 容量不够时 Vec 会重新分配更大的空间（通常翻倍）
+        if nums.capacity() != last_capacity {
+            println!(
+                "长度 {:>2} 时扩容: {} -> {}",
+                nums.len(),
+                last_capacity,
+                nums.capacity()
+            );
+            last_capacity = nums.capacity();
+        }
+    }
+
+    nums.reserve(100);
+    println!("reserve(100) 后容量: {}", nums.capacity());
+    nums.shrink_to_fit();
+    println!("shrink_to_fit 后容量: {}", nums.capacity());
+
+    let reserved: MiniVec<i32> = MiniVec::with_capacity(10);
+    println!("with_capacity(10) 容量: {}", reserved.capacity());
+
+    // 通过 Deref 使用切片方法
+    println!("\n=== Deref 到切片 ===\n");
+    let mut scores: MiniVec<i32> = MiniVec::new();
+    for s in [72, 95, 60, 88] {
+        scores.push(s);
+    }
+    println!("总分: {}", scores.iter().sum::<i32>());
+    println!("最高分: {:?}", scores.iter().max());
+    scores.sort();
+    println!("排序后: {:?}", scores.to_vec());
+    println!("88 的位置: {:?}", scores.binary_search(&88));
+
     println!("\n=== 演示完成 ===");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_grows_capacity() {
+        let mut vec: MiniVec<i32> = MiniVec::new();
+        vec.push(1);
+
+        vec.reserve(50);
+        assert!(vec.capacity() >= vec.len() + 50);
+
+        vec.shrink_to_fit();
+        assert!(vec.capacity() >= vec.len());
+        assert!(vec.capacity() < 51);
+    }
+
+    #[test]
+    fn test_with_capacity_avoids_reallocation() {
+        let mut vec = MiniVec::with_capacity(10);
+        let capacity = vec.capacity();
+        assert!(capacity >= 10);
+        assert!(vec.is_empty());
+
+        for i in 0..10 {
+            vec.push(i);
+            // 在预留范围内 push，容量保持不变，说明没有重新分配
+            assert_eq!(vec.capacity(), capacity);
+        }
+        assert_eq!(vec.len(), 10);
+    }
+
+    fn mini_vec(items: &[i32]) -> MiniVec<i32> {
+        let mut vec = MiniVec::new();
+        for &item in items {
+            vec.push(item);
+        }
+        vec
+    }
+
+    #[test]
+    fn test_deref_slice_methods() {
+        let vec = mini_vec(&[1, 2, 3, 4]);
+
+        assert_eq!(vec.iter().sum::<i32>(), 10);
+        let pair_sums: Vec<i32> = vec.windows(2).map(|w| w[0] + w[1]).collect();
+        assert_eq!(pair_sums, vec![3, 5, 7]);
+        assert_eq!(vec.binary_search(&3), Ok(2));
+        assert_eq!(vec.binary_search(&5), Err(4));
+
+        // 需要 &[i32] 的函数可以直接传 &MiniVec（解引用强制转换）
+        fn total(items: &[i32]) -> i32 {
+            items.iter().sum()
+        }
+        assert_eq!(total(&vec), 10);
+    }
+
+    #[test]
+    fn test_deref_mut_slice_methods() {
+        let mut vec = mini_vec(&[3, 1, 2]);
+
+        vec.sort();
+        assert_eq!(vec.to_vec(), vec![1, 2, 3]);
+
+        vec.reverse();
+        vec[0] = 30;
+        for x in vec.iter_mut() {
+            *x *= 2;
+        }
+        assert_eq!(vec.to_vec(), vec![60, 4, 2]);
+    }
+}
@@ -24,11 +24,62 @@ fn count_words(text: &str) -> HashMap<String, usize> {
     counts
 }
 
+/// 汇总信息
+#[derive(Debug, PartialEq)]
+struct Summary {
+    /// 处理的单词总数（重复的也计入）
+    total: usize,
+    /// 不同单词的个数
+    distinct: usize,
+}
+
+impl Summary {
+    /// 词汇多样性：不同单词数 / 单词总数，越接近 1 用词越不重复
+    fn diversity(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.distinct as f64 / self.total as f64
+        }
+    }
+}
+
+/// 由词频表计算汇总信息，总数就是所有次数之和
+fn summarize(counts: &HashMap<String, usize>) -> Summary {
+    Summary {
+        total: counts.values().sum(),
+        distinct: counts.len(),
+    }
+}
+
+/// 排序方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortBy {
+    /// 按次数降序，次数相同按单词字母序
+    Count,
+    /// 按单词字母序
+    Alpha,
+}
+
+/// 把统计结果排成确定的顺序
+///
+/// HashMap 的遍历顺序每次运行都可能不同，只按次数排序时并列的单词顺序不固定，
+/// 所以需要用单词本身作为第二排序键
+fn sort_counts(counts: &HashMap<String, usize>, by: SortBy) -> Vec<(&String, &usize)> {
+    let mut items: Vec<_> = counts.iter().collect();
+    match by {
+        // Ordering::then_with: 第一个键相等时才比较第二个键
+        SortBy::Count => items.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0))),
+        SortBy::Alpha => items.sort_by(|a, b| a.0.cmp(b.0)),
+    }
+    items
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     // 读取文本
-    let text = if args.len() > 1 {
+    let text = if args.len() > 1 && !args[1].starts_with("--") {
         fs::read_to_string(&args[1]).expect("无法读取文件")
     } else {
         let mut buf = String::new();
@@ -39,9 +90,23 @@ fn main() {
     // 统计词频
     let counts = count_words(&text);
 
+    // 获取 --sort 参数
+    let sort_by = match args
+        .iter()
+        .position(|a| a == "--sort")
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+    {
+        None | Some("count") => SortBy::Count,
+        Some("alpha") => SortBy::Alpha,
+        Some(other) => {
+            eprintln!("未知的排序方式: {}（可选: count, alpha）", other);
+            std::process::exit(1);
+        }
+    };
+
     // 排序并输出
-    let mut items: Vec<_> = counts.iter().collect();
-    items.sort_by(|a, b| b.1.cmp(a.1));
+    let items = sort_counts(&counts, sort_by);
 
     // 获取 --top 参数
     let top_n = args.iter()
@@ -57,5 +122,55 @@ fn main() {
         println!("{:15} {:>8}", word, count);
     }
 
-    println!("\n总计: {} 个不同单词", counts.len());
+    let summary = summarize(&counts);
+    println!(
+        "\n总计: {} 个单词，{} 个不同单词",
+        summary.total, summary.distinct
+    );
+    println!("词汇多样性: {:.3}", summary.diversity());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_ties_are_alphabetical() {
+        let counts = count_words("pear apple fig apple fig pear kiwi date date");
+
+        let words: Vec<&str> = sort_counts(&counts, SortBy::Count)
+            .iter()
+            .map(|(w, _)| w.as_str())
+            .collect();
+
+        assert_eq!(words, vec!["apple", "date", "fig", "pear", "kiwi"]);
+    }
+
+    #[test]
+    fn test_summary_totals() {
+        let counts = count_words("the cat and the dog and the bird");
+        let summary = summarize(&counts);
+
+        assert_eq!(
+            summary,
+            Summary {
+                total: 8,
+                distinct: 5
+            }
+        );
+        assert!((summary.diversity() - 0.625).abs() < 1e-9);
+
+        // 空输入不会除以零
+        assert_eq!(summarize(&count_words("")).diversity(), 0.0);
+    }
+
+    #[test]
+    fn test_sort_alpha() {
+        let counts = count_words("b a c a");
+
+        let sorted = sort_counts(&counts, SortBy::Alpha);
+        let words: Vec<(&str, usize)> = sorted.iter().map(|(w, c)| (w.as_str(), **c)).collect();
+
+        assert_eq!(words, vec![("a", 2), ("b", 1), ("c", 1)]);
+    }
 }
@@ -1,7 +1,10 @@
 //! task-cli with Serde JSON storage
 
 use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -41,6 +44,34 @@ struct Task {
 
 const DATA_FILE: &str = "tasks.json";
 
+/// list 命令的输出格式
+#[derive(Debug, PartialEq)]
+enum OutputFormat {
+    Json,
+    Yaml,
+}
+
+/// 从 `--format json|yaml` 解析输出格式，默认 JSON
+fn parse_format(args: &[String]) -> Result<OutputFormat, String> {
+    match args.iter().position(|a| a == "--format") {
+        None => Ok(OutputFormat::Json),
+        Some(i) => match args.get(i + 1).map(|s| s.as_str()) {
+            Some("json") => Ok(OutputFormat::Json),
+            Some("yaml") => Ok(OutputFormat::Yaml),
+            Some(other) => Err(format!("不支持的格式: {} (可选 json, yaml)", other)),
+            None => Err("--format 需要一个参数 (json 或 yaml)".to_string()),
+        },
+    }
+}
+
+/// 同一份 Serialize 实现，换一个序列化器就能输出不同格式
+fn render_tasks(tasks: &[Task], format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(tasks).unwrap(),
+        OutputFormat::Yaml => serde_yaml::to_string(tasks).unwrap(),
+    }
+}
+
 fn load_tasks() -> Vec<Task> {
     fs::read_to_string(DATA_FILE)
         .ok()
@@ -48,9 +79,76 @@ fn load_tasks() -> Vec<Task> {
         .unwrap_or_default()
 }
 
-fn save_tasks(tasks: &[Task]) {
-    let json = serde_json::to_string_pretty(tasks).unwrap();
-    fs::write(DATA_FILE, json).unwrap();
+/// 保存任务列表
+///
+/// 1. 已有的文件先复制一份到 `<文件>.bak`，上一个版本随时可以找回
+/// 2. 新内容写入 `<文件>.tmp`，再 rename 覆盖原文件
+///
+/// rename 在同一文件系统内是原子的：任何时刻原文件要么是旧内容，要么是新内容，
+/// 不会因为写到一半崩溃而变成空文件
+fn save_tasks(tasks: &[Task], path: &Path) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(tasks)?;
+
+    if path.exists() {
+        fs::copy(path, with_suffix(path, ".bak"))?;
+    }
+
+    let tmp = with_suffix(path, ".tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+/// 状态变更的结果
+#[derive(Debug, PartialEq)]
+enum Transition {
+    /// 状态已更新
+    Changed,
+    /// 任务已经处于目标状态，什么都没做
+    Unchanged,
+    /// 不允许的变更（已完成的任务不能再开始），状态保持不变
+    Conflict,
+}
+
+/// 把任务切换到目标状态，返回是否真的发生了变化
+fn transition(task: &mut Task, target: Status) -> Transition {
+    if task.status == target {
+        return Transition::Unchanged;
+    }
+    if task.status == Status::Done && target == Status::InProgress {
+        return Transition::Conflict;
+    }
+    task.status = target;
+    Transition::Changed
+}
+
+/// 处理 done / start 命令，返回任务列表是否被修改
+fn update_status(tasks: &mut [Task], id_arg: Option<&String>, target: Status) -> bool {
+    let Some(id) = id_arg.and_then(|s| s.parse::<u32>().ok()) else {
+        eprintln!("请提供有效的任务 ID");
+        return false;
+    };
+    let Some(task) = tasks.iter_mut().find(|t| t.id == id) else {
+        eprintln!("未找到任务: {}", id);
+        return false;
+    };
+
+    let done = target == Status::Done;
+    let result = transition(task, target);
+    match result {
+        Transition::Changed if done => println!("✓ 完成: {}", task.title),
+        Transition::Changed => println!("▶ 开始: {}", task.title),
+        Transition::Unchanged if done => println!("任务已经完成过了: {}", task.title),
+        Transition::Unchanged => println!("任务已在进行中: {}", task.title),
+        Transition::Conflict => eprintln!("警告: 任务已完成，不能再开始: {}", task.title),
+    }
+    result == Transition::Changed
+}
+
+/// 在文件名后追加后缀：tasks.json -> tasks.json.bak
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
 }
 
 fn main() {
@@ -59,11 +157,12 @@ fn main() {
 
     if args.is_empty() {
         println!("task-cli v0.6 (with Serde)");
-        println!("用法: task [add|list|done] ...");
+        println!("用法: task [add|list [--format json|yaml]|start|done] ...");
         return;
     }
 
-    match args[0].as_str() {
+    // 只有任务列表真的被修改时才写文件
+    let changed = match args[0].as_str() {
         "add" => {
             let next_id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
             let title = args[1..].join(" ");
@@ -75,20 +174,184 @@ fn main() {
                 due_date: None,
             });
             println!("✓ 添加: {} (ID: {})", title, next_id);
+            true
         }
         "list" => {
-            println!("{}", serde_json::to_string_pretty(&tasks).unwrap());
-        }
-        "done" => {
-            if let Some(id) = args.get(1).and_then(|s| s.parse::<u32>().ok()) {
-                if let Some(task) = tasks.iter_mut().find(|t| t.id == id) {
-                    task.status = Status::Done;
-                    println!("✓ 完成: {}", task.title);
-                }
+            match parse_format(&args[1..]) {
+                Ok(format) => println!("{}", render_tasks(&tasks, &format)),
+                Err(e) => eprintln!("{}", e),
             }
+            false
+        }
+        "start" => update_status(&mut tasks, args.get(1), Status::InProgress),
+        "done" => update_status(&mut tasks, args.get(1), Status::Done),
+        _ => {
+            println!("未知命令");
+            false
+        }
+    };
+
+    if changed {
+        if let Err(e) = save_tasks(&tasks, Path::new(DATA_FILE)) {
+            eprintln!("保存失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_tasks() -> Vec<Task> {
+        vec![
+            Task {
+                id: 1,
+                title: "学习 Serde".to_string(),
+                status: Status::InProgress,
+                priority: Priority::High,
+                due_date: Some("2024-12-31".to_string()),
+            },
+            Task {
+                id: 2,
+                title: "写测试".to_string(),
+                status: Status::Pending,
+                priority: Priority::Low,
+                due_date: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_yaml_output() {
+        let yaml = render_tasks(&sample_tasks(), &OutputFormat::Yaml);
+
+        // rename_all = "lowercase" 对 YAML 同样生效
+        let expected = "\
+- id: 1
+  title: 学习 Serde
+  status: inprogress
+  priority: high
+  due_date: 2024-12-31
+- id: 2
+  title: 写测试
+  status: pending
+  priority: low
+";
+        assert_eq!(yaml, expected);
+    }
+
+    #[test]
+    fn test_save_creates_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+
+        // 第一次保存：没有旧文件，也就没有备份
+        save_tasks(&sample_tasks()[..1], &path).unwrap();
+        assert!(!dir.path().join("tasks.json.bak").exists());
+        let first = fs::read_to_string(&path).unwrap();
+
+        // 第二次保存：旧内容进入 .bak，新内容替换原文件
+        save_tasks(&sample_tasks(), &path).unwrap();
+        let backup = fs::read_to_string(dir.path().join("tasks.json.bak")).unwrap();
+        assert_eq!(backup, first);
+
+        let saved: Vec<Task> = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.len(), 2);
+    }
+
+    #[test]
+    fn test_save_replaces_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+        fs::write(&path, "[]").unwrap();
+
+        save_tasks(&sample_tasks(), &path).unwrap();
+
+        // 临时文件已经被 rename 掉，目录里只剩正式文件和备份
+        assert!(!dir.path().join("tasks.json.tmp").exists());
+        let mut names: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["tasks.json", "tasks.json.bak"]);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(parse_format(&args(&[])), Ok(OutputFormat::Json));
+        assert_eq!(parse_format(&args(&["--format", "yaml"])), Ok(OutputFormat::Yaml));
+        assert_eq!(parse_format(&args(&["--format", "json"])), Ok(OutputFormat::Json));
+        assert!(parse_format(&args(&["--format", "xml"])).is_err());
+        assert!(parse_format(&args(&["--format"])).is_err());
+    }
+
+    fn task_with(status: Status) -> Task {
+        Task {
+            id: 1,
+            title: "状态测试".to_string(),
+            status,
+            priority: Priority::Medium,
+            due_date: None,
         }
-        _ => println!("未知命令"),
     }
 
-    save_tasks(&tasks);
+    #[test]
+    fn test_transition_to_done() {
+        let mut task = task_with(Status::Pending);
+        assert_eq!(transition(&mut task, Status::Done), Transition::Changed);
+        assert_eq!(task.status, Status::Done);
+
+        let mut task = task_with(Status::InProgress);
+        assert_eq!(transition(&mut task, Status::Done), Transition::Changed);
+        assert_eq!(task.status, Status::Done);
+
+        // 重复完成不算变化
+        let mut task = task_with(Status::Done);
+        assert_eq!(transition(&mut task, Status::Done), Transition::Unchanged);
+        assert_eq!(task.status, Status::Done);
+    }
+
+    #[test]
+    fn test_transition_to_in_progress() {
+        let mut task = task_with(Status::Pending);
+        assert_eq!(
+            transition(&mut task, Status::InProgress),
+            Transition::Changed
+        );
+        assert_eq!(task.status, Status::InProgress);
+
+        let mut task = task_with(Status::InProgress);
+        assert_eq!(
+            transition(&mut task, Status::InProgress),
+            Transition::Unchanged
+        );
+
+        // 已完成的任务不能再开始，状态保持不变
+        let mut task = task_with(Status::Done);
+        assert_eq!(
+            transition(&mut task, Status::InProgress),
+            Transition::Conflict
+        );
+        assert_eq!(task.status, Status::Done);
+    }
+
+    #[test]
+    fn test_update_status_reports_change() {
+        let mut tasks = vec![task_with(Status::Pending)];
+        let id = "1".to_string();
+
+        assert!(update_status(&mut tasks, Some(&id), Status::InProgress));
+        assert!(!update_status(&mut tasks, Some(&id), Status::InProgress));
+        assert!(update_status(&mut tasks, Some(&id), Status::Done));
+        assert!(!update_status(&mut tasks, Some(&id), Status::Done));
+        assert!(!update_status(&mut tasks, Some(&id), Status::InProgress));
+
+        // ID 不存在或缺失时不修改
+        let missing = "9".to_string();
+        assert!(!update_status(&mut tasks, Some(&missing), Status::Done));
+        assert!(!update_status(&mut tasks, None, Status::Done));
+    }
 }
@@ -1,26 +1,63 @@
 //! task-cli v1.0 - Production-ready CLI with Clap
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use std::fs;
+use std::io::{self, BufRead};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Parser)]
 #[command(name = "task")]
 #[command(about = "命令行待办事项管理器", version)]
 struct Cli {
+    /// 在 stderr 输出详细日志，可重复 (-v, -vv)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// 按 -v 的个数决定输出哪些日志，日志写到 stderr，不影响正常输出
+struct Logger {
+    level: u8,
+}
+
+impl Logger {
+    /// -v: 文件路径、加载/保存的任务数等概要信息
+    fn info(&self, msg: impl Display) {
+        self.log(1, msg);
+    }
+
+    /// -vv: 每一次修改的细节
+    fn debug(&self, msg: impl Display) {
+        self.log(2, msg);
+    }
+
+    fn log(&self, level: u8, msg: impl Display) {
+        if self.level >= level {
+            eprintln!("[{}] {}", if level >= 2 { "debug" } else { "info" }, msg);
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// 添加新任务
     Add {
         /// 任务内容
+        #[arg(required_unless_present = "stdin")]
         title: Vec<String>,
+        /// 从标准输入读取，每行一个任务（空行跳过）
+        #[arg(long, conflicts_with = "title")]
+        stdin: bool,
         /// 优先级 (low/medium/high)
         #[arg(short, long, default_value = "medium")]
         priority: String,
+        /// 截止日期 (YYYY-MM-DD)，按 UTC 日期判断是否逾期
+        #[arg(long)]
+        due: Option<String>,
     },
     /// 列出所有任务
     List {
@@ -32,8 +69,17 @@ enum Commands {
     Start { id: u32 },
     /// 完成任务
     Done { id: u32 },
+    /// 把已完成的任务改回待办
+    #[command(alias = "undo")]
+    Reopen { id: u32 },
     /// 删除任务
     Remove { id: u32 },
+    /// 把已完成的任务移到 archive.json
+    Archive,
+    /// 导出为 CSV (id,title,status,priority)
+    Export { path: String },
+    /// 从 CSV 导入，追加到现有任务之后并重新分配 ID
+    Import { path: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,42 +90,263 @@ enum Status { Pending, InProgress, Done }
 #[serde(rename_all = "lowercase")]
 enum Priority { Low, Medium, High }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Task {
     id: u32,
     title: String,
     status: Status,
     priority: Priority,
+    // 旧版 tasks.json 没有这个字段，default 让它反序列化为 None
+    #[serde(default)]
+    due_date: Option<String>,
+}
+
+/// CSV 的一行，只包含交换需要的列
+///
+/// 标题里的逗号、引号和换行由 csv crate 负责加引号和转义
+#[derive(Debug, Serialize, Deserialize)]
+struct CsvRow {
+    id: u32,
+    title: String,
+    status: Status,
+    priority: Priority,
 }
 
 const DATA_FILE: &str = "tasks.json";
+const ARCHIVE_FILE: &str = "archive.json";
 
-fn load() -> Vec<Task> {
-    fs::read_to_string(DATA_FILE)
+fn load(path: &str) -> Vec<Task> {
+    fs::read_to_string(path)
         .ok()
         .and_then(|s| serde_json::from_str(&s).ok())
         .unwrap_or_default()
 }
 
-fn save(tasks: &[Task]) {
-    fs::write(DATA_FILE, serde_json::to_string_pretty(tasks).unwrap()).ok();
+/// 先写 `<path>.tmp` 再 rename 覆盖，写到一半失败时原文件保持不变
+fn save(path: &str, tasks: &[Task]) -> io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, serde_json::to_string_pretty(tasks).unwrap())?;
+    fs::rename(&tmp, path)
+}
+
+/// 把 tasks 中已完成的任务移到 archive 末尾，返回移动的个数
+///
+/// 其余任务保持原来的顺序
+fn archive_done(tasks: &mut Vec<Task>, archive: &mut Vec<Task>) -> usize {
+    let (done, active): (Vec<Task>, Vec<Task>) = std::mem::take(tasks)
+        .into_iter()
+        .partition(|t| matches!(t.status, Status::Done));
+    *tasks = active;
+    let count = done.len();
+    archive.extend(done);
+    count
+}
+
+/// 读取归档文件，文件不存在时视为空归档
+///
+/// 和 `load` 不同，读不了或解析不了都返回错误：归档里是历史数据，
+/// 当成空列表再写回去就把它们全覆盖掉了
+fn load_archive(path: &str) -> io::Result<Vec<Task>> {
+    match fs::read_to_string(path) {
+        Ok(s) => {
+            serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// 把已完成的任务追加到归档文件，写入成功后才从 tasks 中移除，返回归档的个数
+///
+/// 归档读写失败时 tasks 保持不变，调用方就不会写回一个丢了这些任务的列表
+fn archive_to(path: &str, tasks: &mut Vec<Task>) -> io::Result<usize> {
+    let mut archive = load_archive(path)?;
+    let mut remaining = tasks.clone();
+    let count = archive_done(&mut remaining, &mut archive);
+    if count > 0 {
+        save(path, &archive)?;
+        *tasks = remaining;
+    }
+    Ok(count)
+}
+
+/// 把任务写成 CSV，第一行是表头
+fn write_csv<W: io::Write>(tasks: &[Task], out: W) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for t in tasks {
+        writer.serialize(CsvRow {
+            id: t.id,
+            title: t.title.clone(),
+            status: t.status.clone(),
+            priority: t.priority.clone(),
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 读取 CSV，任意一行格式不对都返回错误，不会只导入一半
+fn read_csv<R: io::Read>(input: R) -> csv::Result<Vec<CsvRow>> {
+    csv::Reader::from_reader(input).deserialize().collect()
+}
+
+/// 把导入的行追加到任务列表，返回新分配的 ID
+///
+/// CSV 里的 id 可能和现有任务冲突，所以一律从当前最大 ID 之后重新分配
+fn import_rows(tasks: &mut Vec<Task>, rows: Vec<CsvRow>) -> Vec<u32> {
+    let first_id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+    let mut added = Vec::new();
+
+    for (id, row) in (first_id..).zip(rows) {
+        tasks.push(Task {
+            id,
+            title: row.title,
+            status: row.status,
+            priority: row.priority,
+            due_date: None,
+        });
+        added.push(id);
+    }
+
+    added
+}
+
+/// 把已完成的任务改回 Pending
+///
+/// 返回 None 表示找不到任务，Some(false) 表示任务本来就没完成、什么都没改
+fn reopen(tasks: &mut [Task], id: u32) -> Option<bool> {
+    let t = tasks.iter_mut().find(|t| t.id == id)?;
+    if !matches!(t.status, Status::Done) {
+        return Some(false);
+    }
+    t.status = Status::Pending;
+    Some(true)
+}
+
+/// 批量添加：每行一个任务，ID 从当前最大 ID 之后依次分配
+///
+/// 行首尾的空白会被去掉，空行跳过；返回新任务的 ID
+fn add_lines<S: AsRef<str>>(
+    tasks: &mut Vec<Task>,
+    lines: &[S],
+    priority: &Priority,
+    due: &Option<String>,
+) -> Vec<u32> {
+    let mut next_id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+    let mut added = Vec::new();
+
+    for line in lines {
+        let title = line.as_ref().trim();
+        if title.is_empty() {
+            continue;
+        }
+        tasks.push(Task {
+            id: next_id,
+            title: title.to_string(),
+            status: Status::Pending,
+            priority: priority.clone(),
+            due_date: due.clone(),
+        });
+        added.push(next_id);
+        next_id += 1;
+    }
+
+    added
+}
+
+/// 检查日期格式是否为 YYYY-MM-DD
+fn is_valid_date(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('-').collect();
+    match parts.as_slice() {
+        [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => {
+            let month: u32 = m.parse().unwrap_or(0);
+            let day: u32 = d.parse().unwrap_or(0);
+            y.parse::<u32>().is_ok() && (1..=12).contains(&month) && (1..=31).contains(&day)
+        }
+        _ => false,
+    }
+}
+
+/// 今天的日期，格式 YYYY-MM-DD
+///
+/// 注意这是 UTC 日期而不是本地日期：标准库拿不到本地时区，
+/// 所以在 UTC+8 的早上 8 点之前，逾期判断用的还是「昨天」
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = (secs / 86_400) as i64;
+
+    // 把 1970-01-01 起的天数换算成年月日（Howard Hinnant 的 civil_from_days 算法）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 未完成且截止日期早于今天的任务视为逾期
+///
+/// ISO 日期 (YYYY-MM-DD) 的字典序就是时间顺序，直接比较字符串即可
+fn is_overdue(task: &Task, today: &str) -> bool {
+    if matches!(task.status, Status::Done) {
+        return false;
+    }
+    match &task.due_date {
+        Some(due) => due.as_str() < today,
+        None => false,
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
-    let mut tasks = load();
+    let log = Logger { level: cli.verbose };
+
+    log.info(format!("数据文件: {}", DATA_FILE));
+    let mut tasks = load(DATA_FILE);
+    log.info(format!("已加载 {} 个任务", tasks.len()));
 
     match cli.command {
-        Commands::Add { title, priority } => {
-            let next_id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
-            let title = title.join(" ");
+        Commands::Add { title, stdin, priority, due } => {
+            if let Some(d) = &due {
+                if !is_valid_date(d) {
+                    eprintln!("错误: 无效的截止日期 '{}'，格式应为 YYYY-MM-DD", d);
+                    std::process::exit(1);
+                }
+            }
             let priority = match priority.as_str() {
                 "low" => Priority::Low,
                 "high" => Priority::High,
                 _ => Priority::Medium,
             };
-            tasks.push(Task { id: next_id, title: title.clone(), status: Status::Pending, priority });
-            println!("✓ 添加: {} (ID: {})", title, next_id);
+            if stdin {
+                let lines: Vec<String> = match io::stdin().lock().lines().collect() {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        eprintln!("错误: 读取标准输入失败: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                log.info(format!("从标准输入读取了 {} 行", lines.len()));
+                let ids = add_lines(&mut tasks, &lines, &priority, &due);
+                for id in &ids {
+                    log.debug(format!("新任务 #{}: 优先级 {:?}，截止 {:?}", id, priority, due));
+                }
+                println!("✓ 批量添加 {} 个任务", ids.len());
+            } else {
+                let next_id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+                let title = title.join(" ");
+                log.debug(format!("新任务 #{}: 优先级 {:?}，截止 {:?}", next_id, priority, due));
+                tasks.push(Task { id: next_id, title: title.clone(), status: Status::Pending, priority, due_date: due });
+                println!("✓ 添加: {} (ID: {})", title, next_id);
+            }
         }
         Commands::List { status } => {
             let filtered: Vec<_> = tasks.iter().filter(|t| {
@@ -89,12 +356,14 @@ fn main() {
                     _ => true,
                 }
             }).collect();
+            log.info(format!("过滤条件 {}，匹配 {} 个任务", status, filtered.len()));
 
             if filtered.is_empty() {
                 println!("没有任务");
             } else {
-                println!("{:>3}  {:>8}  {:>6}  任务", "ID", "状态", "优先级");
-                println!("{}", "-".repeat(50));
+                let today = today();
+                println!("{:>3}  {:>8}  {:>6}  {:>10}  任务", "ID", "状态", "优先级", "截止");
+                println!("{}", "-".repeat(62));
                 for t in filtered {
                     let status = match t.status {
                         Status::Pending => "待办",
@@ -106,12 +375,15 @@ fn main() {
                         Priority::Medium => "中",
                         Priority::High => "高",
                     };
-                    println!("{:>3}  {:>8}  {:>6}  {}", t.id, status, priority, t.title);
+                    let due = t.due_date.as_deref().unwrap_or("-");
+                    let mark = if is_overdue(t, &today) { "  ⚠ 逾期" } else { "" };
+                    println!("{:>3}  {:>8}  {:>6}  {:>10}  {}{}", t.id, status, priority, due, t.title, mark);
                 }
             }
         }
         Commands::Start { id } => {
             if let Some(t) = tasks.iter_mut().find(|t| t.id == id) {
+                log.debug(format!("任务 #{}: {:?} -> InProgress", id, t.status));
                 t.status = Status::InProgress;
                 println!("✓ 开始: {}", t.title);
             } else {
@@ -120,22 +392,351 @@ fn main() {
         }
         Commands::Done { id } => {
             if let Some(t) = tasks.iter_mut().find(|t| t.id == id) {
+                log.debug(format!("任务 #{}: {:?} -> Done", id, t.status));
                 t.status = Status::Done;
                 println!("✓ 完成: {}", t.title);
             } else {
                 println!("找不到任务 #{}", id);
             }
         }
+        Commands::Reopen { id } => match reopen(&mut tasks, id) {
+            Some(true) => {
+                log.debug(format!("任务 #{}: Done -> Pending", id));
+                println!("✓ 重新打开任务 #{}", id);
+            }
+            Some(false) => println!("任务 #{} 尚未完成，无需重新打开", id),
+            None => println!("找不到任务 #{}", id),
+        },
         Commands::Remove { id } => {
             let len = tasks.len();
             tasks.retain(|t| t.id != id);
             if tasks.len() < len {
+                log.debug(format!("移除任务 #{}，剩余 {} 个", id, tasks.len()));
                 println!("✓ 已删除任务 #{}", id);
             } else {
                 println!("找不到任务 #{}", id);
             }
         }
+        Commands::Archive => {
+            log.info(format!("归档文件: {}", ARCHIVE_FILE));
+            // 先写归档再写任务列表：归档写入失败就直接退出，任务列表不会被改写
+            match archive_to(ARCHIVE_FILE, &mut tasks) {
+                Ok(0) => {
+                    println!("没有已完成的任务需要归档");
+                    return;
+                }
+                Ok(count) => println!("✓ 已归档 {} 个任务到 {}", count, ARCHIVE_FILE),
+                Err(e) => {
+                    eprintln!("错误: 无法归档到 {}: {}", ARCHIVE_FILE, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Export { path } => {
+            let result = fs::File::create(&path)
+                .map_err(csv::Error::from)
+                .and_then(|f| write_csv(&tasks, f));
+            if let Err(e) = result {
+                eprintln!("错误: 无法导出到 {}: {}", path, e);
+                std::process::exit(1);
+            }
+            println!("✓ 已导出 {} 个任务到 {}", tasks.len(), path);
+        }
+        Commands::Import { path } => {
+            let rows = match fs::File::open(&path)
+                .map_err(csv::Error::from)
+                .and_then(read_csv)
+            {
+                Ok(rows) => rows,
+                Err(e) => {
+                    eprintln!("错误: 无法从 {} 导入: {}", path, e);
+                    std::process::exit(1);
+                }
+            };
+            log.info(format!("从 {} 读取了 {} 行", path, rows.len()));
+            let ids = import_rows(&mut tasks, rows);
+            for id in &ids {
+                log.debug(format!("导入任务 #{}", id));
+            }
+            println!("✓ 已导入 {} 个任务", ids.len());
+        }
+    }
+
+    if let Err(e) = save(DATA_FILE, &tasks) {
+        eprintln!("错误: 无法保存到 {}: {}", DATA_FILE, e);
+        std::process::exit(1);
+    }
+    log.info(format!("已保存 {} 个任务到 {}", tasks.len(), DATA_FILE));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(status: Status, due: Option<&str>) -> Task {
+        Task {
+            id: 1,
+            title: "写报告".to_string(),
+            status,
+            priority: Priority::Medium,
+            due_date: due.map(|d| d.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_overdue_past_due() {
+        let t = task(Status::Pending, Some("2024-01-01"));
+        assert!(is_overdue(&t, "2024-06-15"));
+
+        let t = task(Status::InProgress, Some("2024-06-14"));
+        assert!(is_overdue(&t, "2024-06-15"));
+    }
+
+    #[test]
+    fn test_not_overdue_future_or_today() {
+        let t = task(Status::Pending, Some("2024-12-31"));
+        assert!(!is_overdue(&t, "2024-06-15"));
+
+        let t = task(Status::Pending, Some("2024-06-15"));
+        assert!(!is_overdue(&t, "2024-06-15"));
+    }
+
+    #[test]
+    fn test_done_or_no_due_never_overdue() {
+        let t = task(Status::Done, Some("2024-01-01"));
+        assert!(!is_overdue(&t, "2024-06-15"));
+
+        let t = task(Status::Pending, None);
+        assert!(!is_overdue(&t, "2024-06-15"));
+    }
+
+    #[test]
+    fn test_valid_date() {
+        assert!(is_valid_date("2024-06-15"));
+        assert!(!is_valid_date("2024-6-15"));
+        assert!(!is_valid_date("2024-13-01"));
+        assert!(!is_valid_date("tomorrow"));
+    }
+
+    #[test]
+    fn test_today_format() {
+        let t = today();
+        assert!(is_valid_date(&t), "today() 返回了 {}", t);
+    }
+
+    #[test]
+    fn test_archive_moves_done_tasks() {
+        let mut tasks: Vec<Task> = [Status::Done, Status::Pending, Status::Done, Status::InProgress]
+            .into_iter()
+            .enumerate()
+            .map(|(i, status)| Task { id: i as u32 + 1, ..task(status, None) })
+            .collect();
+        let mut archive = vec![Task { id: 99, ..task(Status::Done, None) }];
+
+        assert_eq!(archive_done(&mut tasks, &mut archive), 2);
+
+        let ids = |list: &[Task]| list.iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(&tasks), vec![2, 4]);
+        // 新归档的任务追加在已有归档之后
+        assert_eq!(ids(&archive), vec![99, 1, 3]);
+    }
+
+    #[test]
+    fn test_archive_nothing_done() {
+        let mut tasks = vec![task(Status::Pending, None)];
+        let mut archive = Vec::new();
+
+        assert_eq!(archive_done(&mut tasks, &mut archive), 0);
+        assert_eq!(tasks.len(), 1);
+        assert!(archive.is_empty());
+    }
+
+    #[test]
+    fn test_archive_write_failure_keeps_tasks() {
+        let mut tasks = vec![task(Status::Done, None), Task { id: 2, ..task(Status::Pending, None) }];
+        // 父目录不存在，归档文件一定写不进去
+        let path = std::env::temp_dir().join("task-cli-no-such-dir").join("archive.json");
+
+        assert!(archive_to(path.to_str().unwrap(), &mut tasks).is_err());
+        // 写入失败时已完成的任务仍然留在列表里
+        assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_archive_corrupt_file_untouched() {
+        let mut tasks = vec![task(Status::Done, None)];
+        let path = std::env::temp_dir().join(format!(
+            "task-cli-corrupt-archive-{}.json",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        fs::write(path, "{ 不是 JSON").unwrap();
+
+        // 归档文件损坏时报错，既不覆盖归档，也不移走任务
+        assert_eq!(
+            archive_to(path, &mut tasks).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(fs::read_to_string(path).unwrap(), "{ 不是 JSON");
+        assert_eq!(tasks.len(), 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_archive_missing_file_is_empty() {
+        let mut tasks = vec![task(Status::Done, None)];
+        let path =
+            std::env::temp_dir().join(format!("task-cli-new-archive-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        assert_eq!(archive_to(path, &mut tasks).unwrap(), 1);
+        assert_eq!(load_archive(path).unwrap().len(), 1);
+        assert!(tasks.is_empty());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_add_lines_bulk() {
+        let mut tasks = vec![Task { id: 5, ..task(Status::Done, None) }];
+        let input = ["买牛奶", "", "  写周报  ", "   ", "修 bug"];
+
+        let ids = add_lines(&mut tasks, &input, &Priority::High, &Some("2024-06-30".to_string()));
+
+        // 空行跳过，ID 接在已有的最大 ID 之后连续分配
+        assert_eq!(ids, vec![6, 7, 8]);
+        let titles: Vec<&str> = tasks[1..].iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["买牛奶", "写周报", "修 bug"]);
+        assert!(tasks[1..].iter().all(|t| matches!(t.priority, Priority::High)
+            && matches!(t.status, Status::Pending)
+            && t.due_date.as_deref() == Some("2024-06-30")));
+    }
+
+    #[test]
+    fn test_add_stdin_flag() {
+        let cli = Cli::try_parse_from(["task", "add", "--stdin"]).unwrap();
+        assert!(matches!(cli.command, Commands::Add { stdin: true, .. }));
+
+        // --stdin 和标题不能同时出现，两者都没有也不行
+        assert!(Cli::try_parse_from(["task", "add", "--stdin", "标题"]).is_err());
+        assert!(Cli::try_parse_from(["task", "add"]).is_err());
+    }
+
+    #[test]
+    fn test_verbose_count() {
+        let level = |args: &[&str]| Cli::try_parse_from(args).unwrap().verbose;
+
+        assert_eq!(level(&["task", "list"]), 0);
+        assert_eq!(level(&["task", "-v", "list"]), 1);
+        assert_eq!(level(&["task", "-vv", "list"]), 2);
+        assert_eq!(level(&["task", "-v", "--verbose", "list"]), 2);
+        // global = true：写在子命令后面也生效
+        assert_eq!(level(&["task", "list", "-vvv"]), 3);
+    }
+
+    #[test]
+    fn test_old_json_without_due() {
+        let json = r#"{"id":1,"title":"旧任务","status":"pending","priority":"low"}"#;
+        let t: Task = serde_json::from_str(json).unwrap();
+        assert!(t.due_date.is_none());
+    }
+
+    #[test]
+    fn test_reopen() {
+        let mut tasks: Vec<Task> = [Status::Done, Status::InProgress]
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| Task { id: i as u32 + 1, ..task(s, None) })
+            .collect();
+
+        assert_eq!(reopen(&mut tasks, 1), Some(true));
+        assert!(matches!(tasks[0].status, Status::Pending));
+
+        // 已经不是 Done 的任务保持原状
+        assert_eq!(reopen(&mut tasks, 1), Some(false));
+        assert_eq!(reopen(&mut tasks, 2), Some(false));
+        assert!(matches!(tasks[1].status, Status::InProgress));
+
+        assert_eq!(reopen(&mut tasks, 9), None);
+    }
+
+    #[test]
+    fn test_undo_alias() {
+        let cli = Cli::try_parse_from(["task", "undo", "3"]).unwrap();
+        assert!(matches!(cli.command, Commands::Reopen { id: 3 }));
+    }
+
+    fn sample_tasks() -> Vec<Task> {
+        vec![
+            Task {
+                id: 1,
+                title: "买牛奶".to_string(),
+                ..task(Status::Pending, None)
+            },
+            Task {
+                id: 2,
+                title: "回复 Bob, Alice".to_string(),
+                status: Status::InProgress,
+                priority: Priority::High,
+                due_date: None,
+            },
+            Task {
+                id: 3,
+                title: r#"读《"Rust" 程序设计》"#.to_string(),
+                status: Status::Done,
+                priority: Priority::Low,
+                due_date: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let tasks = sample_tasks();
+        let mut buf = Vec::new();
+        write_csv(&tasks, &mut buf).unwrap();
+
+        let text = String::from_utf8(buf.clone()).unwrap();
+        assert!(text.starts_with("id,title,status,priority\n"));
+        // 含逗号或引号的标题被加上引号，引号本身写成两个
+        assert!(text.contains(r#"2,"回复 Bob, Alice",inprogress,high"#));
+        assert!(text.contains(r#"3,"读《""Rust"" 程序设计》",done,low"#));
+
+        let rows = read_csv(buf.as_slice()).unwrap();
+        assert_eq!(rows.len(), 3);
+        for (row, t) in rows.iter().zip(&tasks) {
+            assert_eq!(row.id, t.id);
+            assert_eq!(row.title, t.title);
+            assert_eq!(format!("{:?}", row.status), format!("{:?}", t.status));
+            assert_eq!(format!("{:?}", row.priority), format!("{:?}", t.priority));
+        }
     }
 
-    save(&tasks);
+    #[test]
+    fn test_import_assigns_fresh_ids() {
+        let mut buf = Vec::new();
+        write_csv(&sample_tasks(), &mut buf).unwrap();
+
+        // 现有任务的 ID 和 CSV 中的 1、2 冲突
+        let mut tasks = vec![
+            task(Status::Pending, None),
+            Task {
+                id: 2,
+                ..task(Status::Done, None)
+            },
+        ];
+        let ids = import_rows(&mut tasks, read_csv(buf.as_slice()).unwrap());
+
+        assert_eq!(ids, vec![3, 4, 5]);
+        let all: Vec<u32> = tasks.iter().map(|t| t.id).collect();
+        assert_eq!(all, vec![1, 2, 3, 4, 5]);
+        assert_eq!(tasks[3].title, "回复 Bob, Alice");
+        assert!(matches!(tasks[4].status, Status::Done));
+    }
+
+    #[test]
+    fn test_import_rejects_bad_row() {
+        let csv = "id,title,status,priority\n1,ok,pending,low\n2,bad,finished,low\n";
+        assert!(read_csv(csv.as_bytes()).is_err());
+    }
 }
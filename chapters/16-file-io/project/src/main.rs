@@ -1,63 +1,329 @@
 // find-rs: 简化版 find 命令
-// 用法: find-rs <目录> -name <模式>
+// 用法: find-rs <目录> [-name <模式>] [-empty] [--print0] [--depth-first] [--report-errors]
+//               [--sort name|size|mtime] [--reverse] [--ignore <模式>]... [--relative]
+//
+// 遍历顺序: 每个目录内的条目按名称排序。默认遇到子目录立即进入；
+// --depth-first 则先输出当前目录的全部文件，再依次进入子目录。
+// --sort 在输出前把全部结果按文件名、大小或修改时间重新排序，--reverse 反转输出顺序
+// --ignore 可以重复使用，名称匹配的目录不再进入，名称匹配的文件直接跳过
+// --relative 输出相对于起始目录的路径，方便交给其他命令处理
 
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// --sort 的排序依据
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    /// 文件名（不含目录部分）
+    Name,
+    /// 文件大小
+    Size,
+    /// 最后修改时间
+    Mtime,
+}
+
+impl SortKey {
+    fn parse(s: &str) -> Option<SortKey> {
+        match s {
+            "name" => Some(SortKey::Name),
+            "size" => Some(SortKey::Size),
+            "mtime" => Some(SortKey::Mtime),
+            _ => None,
+        }
+    }
+}
+
+/// 查找选项
+struct FindOptions {
+    /// 文件名模式（支持 * 通配符），None 表示不按名称过滤
+    pattern: Option<String>,
+    /// 只匹配空文件和空目录
+    empty: bool,
+    /// 用 NUL 字节代替换行分隔结果，配合 `xargs -0` 使用
+    print0: bool,
+    /// 先处理完当前目录的文件，再进入子目录
+    depth_first: bool,
+    /// 结束后在 stderr 汇总因权限不足而跳过的目录
+    report_errors: bool,
+    /// 输出前按指定依据排序，None 表示保持遍历顺序
+    sort: Option<SortKey>,
+    /// 反转输出顺序
+    reverse: bool,
+    /// 要排除的文件/目录名模式，与 -name 使用相同的通配符规则
+    ignore: Vec<String>,
+    /// 输出相对于起始目录的路径
+    relative: bool,
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 4 || args[2] != "-name" {
-        eprintln!("用法: find-rs <目录> -name <模式>");
-        eprintln!("示例: find-rs . -name *.rs");
-        std::process::exit(1);
+    let options = match args.get(1..).and_then(parse_options) {
+        Some(options) => options,
+        None => {
+            eprintln!(
+                "用法: find-rs <目录> [-name <模式>] [-empty] [--print0] [--depth-first] [--report-errors]"
+            );
+            eprintln!(
+                "                    [--sort name|size|mtime] [--reverse] [--ignore <模式>]... [--relative]"
+            );
+            eprintln!("示例: find-rs . -name *.rs");
+            eprintln!("      find-rs . -empty");
+            eprintln!("      find-rs . --sort size --reverse");
+            eprintln!("      find-rs . -name *.rs --ignore target --ignore .git");
+            eprintln!("      find-rs src -name *.rs --relative");
+            std::process::exit(1);
+        }
+    };
+
+    let root = Path::new(&args[1]);
+
+    let mut found = Vec::new();
+    let mut denied = Vec::new();
+    find_files(root, &options, &mut found, &mut denied);
+
+    if let Some(key) = options.sort {
+        sort_paths(&mut found, key);
+    }
+    if options.reverse {
+        found.reverse();
+    }
+
+    // StdoutLock 避免每次写入都重新加锁
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for path in &found {
+        // 排序需要读取元数据，所以一直用完整路径，直到输出时才去掉前缀
+        let shown = display_path(path, root, options.relative);
+        print_path(shown, options.print0, &mut out);
+    }
+
+    if options.report_errors && !denied.is_empty() {
+        eprintln!("\n{} 个目录因权限不足被跳过:", denied.len());
+        for path in &denied {
+            eprintln!("  {}", path.display());
+        }
+    }
+}
+
+/// 解析 <目录> 之后的参数
+fn parse_options(args: &[String]) -> Option<FindOptions> {
+    if args.is_empty() {
+        return None;
     }
 
-    let dir = &args[1];
-    let pattern = &args[3];
+    let mut options = FindOptions {
+        pattern: None,
+        empty: false,
+        print0: false,
+        depth_first: false,
+        report_errors: false,
+        sort: None,
+        reverse: false,
+        ignore: Vec::new(),
+        relative: false,
+    };
+
+    // args[0] 是目录
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-name" if i + 1 < args.len() => {
+                options.pattern = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "-empty" => {
+                options.empty = true;
+                i += 1;
+            }
+            "--print0" => {
+                options.print0 = true;
+                i += 1;
+            }
+            "--depth-first" => {
+                options.depth_first = true;
+                i += 1;
+            }
+            "--report-errors" => {
+                options.report_errors = true;
+                i += 1;
+            }
+            "--sort" if i + 1 < args.len() => {
+                options.sort = Some(SortKey::parse(&args[i + 1])?);
+                i += 2;
+            }
+            "--reverse" => {
+                options.reverse = true;
+                i += 1;
+            }
+            "--ignore" if i + 1 < args.len() => {
+                // 模式只和单个名称比较，`target/` 这种写法去掉结尾的 / 再用
+                let pattern = args[i + 1].trim_end_matches('/');
+                options.ignore.push(pattern.to_string());
+                i += 2;
+            }
+            "--relative" => {
+                options.relative = true;
+                i += 1;
+            }
+            _ => return None,
+        }
+    }
 
-    find_files(Path::new(dir), pattern);
+    Some(options)
 }
 
 /// 递归查找匹配模式的文件
 ///
 /// # 参数
 /// - dir: 起始目录
-/// - pattern: 文件名模式（支持 * 通配符）
-fn find_files(dir: &Path, pattern: &str) {
+/// - options: 查找选项
+/// - found: 按遍历顺序收集匹配的路径，由调用方排序和输出
+/// - denied: 收集因权限不足而无法读取的目录，由调用方决定是否报告
+fn find_files(
+    dir: &Path,
+    options: &FindOptions,
+    found: &mut Vec<PathBuf>,
+    denied: &mut Vec<PathBuf>,
+) {
     // read_dir 返回 Result<ReadDir>
     // ReadDir 是一个迭代器，产出 Result<DirEntry>
     let entries = match fs::read_dir(dir) {
         Ok(entries) => entries,
         Err(e) => {
-            // 某些目录可能没有权限访问，静默跳过
-            if e.kind() != std::io::ErrorKind::PermissionDenied {
-                eprintln!("无法读取目录 {}: {}", dir.display(), e);
-            }
+            record_read_error(dir, &e, denied);
             return;
         }
     };
 
-    for entry in entries {
-        // 每个 entry 也是 Result
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
+    // 先收集再排序，read_dir 本身不保证任何顺序
+    // 每个 entry 也是 Result，读取失败的条目直接跳过
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
 
-        let path = entry.path();
+    if options.depth_first {
+        // partition 是稳定的，两组内部仍保持名称顺序
+        let (dirs, files): (Vec<PathBuf>, Vec<PathBuf>) =
+            paths.into_iter().partition(|p| p.is_dir());
+        paths = files;
+        paths.extend(dirs);
+    }
 
+    for path in paths {
+        // 被排除的目录连同其中的所有内容都不再访问
+        if is_ignored(&path, &options.ignore) {
+            continue;
+        }
         if path.is_dir() {
-            // 递归进入子目录
-            find_files(&path, pattern);
-        } else {
-            // 检查文件名是否匹配
-            if matches_pattern(&path, pattern) {
-                println!("{}", path.display());
+            // 目录只有在 -empty 时才可能作为结果输出
+            if options.empty && is_match(&path, options) {
+                found.push(path.clone());
             }
+            // 递归进入子目录
+            find_files(&path, options, found, denied);
+        } else if is_match(&path, options) {
+            found.push(path);
+        }
+    }
+}
+
+/// 处理读取目录失败：权限不足的目录先记下来继续遍历，其他错误直接打印
+fn record_read_error(dir: &Path, e: &std::io::Error, denied: &mut Vec<PathBuf>) {
+    if e.kind() == std::io::ErrorKind::PermissionDenied {
+        denied.push(dir.to_path_buf());
+    } else {
+        eprintln!("无法读取目录 {}: {}", dir.display(), e);
+    }
+}
+
+/// 按 key 排序，key 相同时按完整路径排序，保证结果可复现
+///
+/// sort_by_cached_key 对每个路径只读取一次元数据；
+/// 读取失败时大小视为 0，修改时间视为 1970-01-01
+fn sort_paths(paths: &mut [PathBuf], key: SortKey) {
+    match key {
+        SortKey::Name => {
+            paths.sort_by_cached_key(|p| (p.file_name().map(|n| n.to_owned()), p.clone()))
+        }
+        SortKey::Size => paths.sort_by_cached_key(|p| {
+            let size = fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+            (size, p.clone())
+        }),
+        SortKey::Mtime => paths.sort_by_cached_key(|p| {
+            let mtime = fs::metadata(p)
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (mtime, p.clone())
+        }),
+    }
+}
+
+/// 路径的最后一个组成部分（文件名或目录名）是否匹配任一排除模式
+///
+/// 只看名称而不看完整路径，`--ignore target` 会排除任意层级下名为 target 的目录
+fn is_ignored(path: &Path, ignore: &[String]) -> bool {
+    ignore.iter().any(|pattern| matches_pattern(path, pattern))
+}
+
+/// 检查路径是否满足所有条件（条件之间是「与」的关系）
+fn is_match(path: &Path, options: &FindOptions) -> bool {
+    // 检查文件名是否匹配
+    if let Some(pattern) = &options.pattern {
+        if !matches_pattern(path, pattern) {
+            return false;
         }
     }
+    !options.empty || is_empty(path)
+}
+
+/// 空文件：长度为 0；空目录：没有任何条目
+fn is_empty(path: &Path) -> bool {
+    if path.is_dir() {
+        // 只需要看第一个条目是否存在，不必读完整个目录
+        fs::read_dir(path)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false)
+    } else {
+        fs::metadata(path).map(|m| m.len() == 0).unwrap_or(false)
+    }
+}
+
+/// 要输出的路径：--relative 时去掉起始目录前缀
+///
+/// strip_prefix 按路径组成部分比较，起始目录是 `.` 时 `./src/main.rs` 会变成 `src/main.rs`
+fn display_path<'a>(path: &'a Path, root: &Path, relative: bool) -> &'a Path {
+    if !relative {
+        return path;
+    }
+    // 结果都是从 root 遍历得到的，前缀一定存在；万一不存在就原样输出
+    path.strip_prefix(root).unwrap_or(path)
+}
+
+/// 输出一个匹配的路径
+///
+/// 文件名可以包含空格甚至换行，只有 NUL 字节不可能出现在路径中，
+/// 所以 --print0 用 NUL 分隔才能保证下游程序正确切分
+///
+/// --print0 的输出是给程序读的，Unix 上原样写出路径的字节；
+/// `display()` 会把非 UTF-8 的字节替换成 �，下游拿到的就是一个不存在的路径
+fn print_path(path: &Path, print0: bool, out: &mut impl Write) {
+    // 写入失败（如下游管道已关闭）时直接忽略
+    if !print0 {
+        let _ = writeln!(out, "{}", path.display());
+        return;
+    }
+
+    #[cfg(unix)]
+    let written = {
+        use std::os::unix::ffi::OsStrExt;
+        out.write_all(path.as_os_str().as_bytes())
+    };
+    #[cfg(not(unix))]
+    let written = write!(out, "{}", path.display());
+    let _ = written.and_then(|_| out.write_all(b"\0"));
 }
 
 /// 检查路径的文件名是否匹配模式
@@ -74,13 +340,11 @@ fn matches_pattern(path: &Path, pattern: &str) -> bool {
     };
 
     // 简单的通配符匹配实现
-    if pattern.starts_with('*') {
+    if let Some(suffix) = pattern.strip_prefix('*') {
         // *.rs -> 匹配以 .rs 结尾
-        let suffix = &pattern[1..];
         filename.ends_with(suffix)
-    } else if pattern.ends_with('*') {
+    } else if let Some(prefix) = pattern.strip_suffix('*') {
         // test* -> 匹配以 test 开头
-        let prefix = &pattern[..pattern.len() - 1];
         filename.starts_with(prefix)
     } else if pattern.contains('*') {
         // a*b -> 匹配以 a 开头且以 b 结尾
@@ -114,9 +378,340 @@ mod tests {
         assert!(!matches_pattern(Path::new("main_test.rs"), "test*"));
     }
 
+    fn find_output(dir: &Path, print0: bool) -> Vec<u8> {
+        let options = FindOptions {
+            print0,
+            ..options(Some("*.rs"))
+        };
+        let mut found = Vec::new();
+        find_files(dir, &options, &mut found, &mut Vec::new());
+
+        let mut out = Vec::new();
+        for path in &found {
+            print_path(path, options.print0, &mut out);
+        }
+        out
+    }
+
+    /// 只指定模式、其余都是默认值的选项
+    fn options(pattern: Option<&str>) -> FindOptions {
+        FindOptions {
+            pattern: pattern.map(|p| p.to_string()),
+            empty: false,
+            print0: false,
+            depth_first: false,
+            report_errors: false,
+            sort: None,
+            reverse: false,
+            ignore: Vec::new(),
+            relative: false,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_print0_keeps_non_utf8_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"dir/bad\xffname.rs"));
+
+        let mut out = Vec::new();
+        print_path(path, true, &mut out);
+        assert_eq!(out, b"dir/bad\xffname.rs\0");
+
+        // 按行输出是给人看的，仍然用 display()
+        let mut out = Vec::new();
+        print_path(path, false, &mut out);
+        assert_eq!(out, "dir/bad\u{fffd}name.rs\n".as_bytes());
+    }
+
+    #[test]
+    fn test_print0_separator() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "").unwrap();
+        fs::write(dir.path().join("my file.rs"), "").unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let out = find_output(dir.path(), true);
+
+        // 两个匹配，每个后面跟一个 NUL，且没有换行
+        assert_eq!(out.iter().filter(|&&b| b == 0).count(), 2);
+        assert!(!out.contains(&b'\n'));
+
+        let mut names: Vec<String> = out
+            .split(|&b| b == 0)
+            .filter(|s| !s.is_empty())
+            .map(|s| String::from_utf8_lossy(s).into_owned())
+            .collect();
+        names.sort();
+        assert!(names[0].ends_with("a.rs"));
+        assert!(names[1].ends_with("my file.rs"));
+    }
+
+    #[test]
+    fn test_newline_is_default() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "").unwrap();
+
+        let out = find_output(dir.path(), false);
+
+        assert!(out.ends_with(b"a.rs\n"));
+        assert!(!out.contains(&0));
+    }
+
+    /// 在临时目录下查找，按遍历顺序返回相对路径
+    fn find_ordered(dir: &Path, options: &FindOptions) -> Vec<String> {
+        let mut found = Vec::new();
+        find_files(dir, options, &mut found, &mut Vec::new());
+        relative(dir, &found)
+    }
+
+    fn relative(dir: &Path, paths: &[PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|p| p.strip_prefix(dir).unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// 在临时目录下查找，返回排序后的相对路径
+    fn find_relative(dir: &Path, options: &FindOptions) -> Vec<String> {
+        let mut paths = find_ordered(dir, options);
+        paths.sort();
+        paths
+    }
+
+    fn empty_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("empty.txt"), "").unwrap();
+        fs::write(dir.path().join("full.txt"), "data").unwrap();
+        fs::write(dir.path().join("empty.rs"), "").unwrap();
+        fs::create_dir(dir.path().join("empty_dir")).unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub").join("nested.txt"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_empty_predicate() {
+        let dir = empty_tree();
+        let options = FindOptions {
+            empty: true,
+            ..options(None)
+        };
+
+        // sub 目录里有文件，所以不算空目录
+        assert_eq!(
+            find_relative(dir.path(), &options),
+            vec!["empty.rs", "empty.txt", "empty_dir", "sub/nested.txt"]
+        );
+    }
+
+    #[test]
+    fn test_empty_with_name() {
+        let dir = empty_tree();
+        let options = FindOptions {
+            empty: true,
+            ..options(Some("*.txt"))
+        };
+
+        assert_eq!(
+            find_relative(dir.path(), &options),
+            vec!["empty.txt", "sub/nested.txt"]
+        );
+    }
+
+    #[test]
+    fn test_depth_first_order() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("a_dir")).unwrap();
+        fs::write(dir.path().join("a_dir").join("inner.txt"), "").unwrap();
+        fs::write(dir.path().join("b.txt"), "").unwrap();
+        fs::write(dir.path().join("c.txt"), "").unwrap();
+
+        let mut options = options(Some("*.txt"));
+
+        // 默认：按名称顺序，a_dir 排在前面，所以先进入它
+        assert_eq!(
+            find_ordered(dir.path(), &options),
+            vec!["a_dir/inner.txt", "b.txt", "c.txt"]
+        );
+
+        // --depth-first：先输出当前目录的文件，再进入子目录
+        options.depth_first = true;
+        assert_eq!(
+            find_ordered(dir.path(), &options),
+            vec!["b.txt", "c.txt", "a_dir/inner.txt"]
+        );
+    }
+
+    #[test]
+    fn test_permission_denied_reported() {
+        use std::io::{Error, ErrorKind};
+
+        // 直接构造错误，不依赖真实的文件权限（root 读任何目录都不会被拒绝）
+        let mut denied = Vec::new();
+        let locked = Path::new("data/locked");
+        record_read_error(
+            locked,
+            &Error::from(ErrorKind::PermissionDenied),
+            &mut denied,
+        );
+        assert_eq!(denied, vec![locked.to_path_buf()]);
+
+        // 其他错误只打印，不算作权限不足
+        record_read_error(
+            Path::new("data/gone"),
+            &Error::from(ErrorKind::NotFound),
+            &mut denied,
+        );
+        assert_eq!(denied, vec![locked.to_path_buf()]);
+    }
+
+    #[test]
+    fn test_sort_by_name_size_mtime() {
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        // (路径, 大小, 修改时间偏移秒数)
+        let files = [
+            ("sub/a.txt", 30, 200),
+            ("b.txt", 10, 300),
+            ("c.txt", 20, 100),
+        ];
+        for (name, size, offset) in files {
+            let path = dir.path().join(name);
+            fs::write(&path, "x".repeat(size)).unwrap();
+            let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + offset);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(mtime)
+                .unwrap();
+        }
+
+        let mut found = Vec::new();
+        find_files(
+            dir.path(),
+            &options(Some("*.txt")),
+            &mut found,
+            &mut Vec::new(),
+        );
+        let sorted = |key: SortKey| {
+            let mut paths = found.clone();
+            sort_paths(&mut paths, key);
+            relative(dir.path(), &paths)
+        };
+
+        // 按文件名排序忽略目录部分，sub/a.txt 排在最前
+        assert_eq!(sorted(SortKey::Name), vec!["sub/a.txt", "b.txt", "c.txt"]);
+        assert_eq!(sorted(SortKey::Size), vec!["b.txt", "c.txt", "sub/a.txt"]);
+        assert_eq!(sorted(SortKey::Mtime), vec!["c.txt", "sub/a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn test_parse_sort_options() {
+        let args: Vec<String> = [".", "--sort", "size", "--reverse"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let options = parse_options(&args).unwrap();
+        assert_eq!(options.sort, Some(SortKey::Size));
+        assert!(options.reverse);
+
+        let args: Vec<String> = [".", "--sort", "color"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(parse_options(&args).is_none());
+    }
+
     #[test]
     fn test_exact_pattern() {
         assert!(matches_pattern(Path::new("Cargo.toml"), "Cargo.toml"));
         assert!(!matches_pattern(Path::new("Cargo.lock"), "Cargo.toml"));
     }
+
+    #[test]
+    fn test_ignore_prunes_directory_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "").unwrap();
+        fs::create_dir_all(dir.path().join("target").join("debug")).unwrap();
+        fs::write(dir.path().join("target").join("debug").join("build.rs"), "").unwrap();
+        fs::create_dir_all(dir.path().join("src").join("target")).unwrap();
+        fs::write(dir.path().join("src").join("lib.rs"), "").unwrap();
+        fs::write(dir.path().join("src").join("target").join("gen.rs"), "").unwrap();
+
+        let options = FindOptions {
+            ignore: vec!["target".to_string()],
+            ..options(Some("*.rs"))
+        };
+
+        // 任意层级下名为 target 的目录及其内容都被排除
+        assert_eq!(
+            find_relative(dir.path(), &options),
+            vec!["main.rs", "src/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn test_ignore_files_by_pattern() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "").unwrap();
+        fs::write(dir.path().join("a_test.rs"), "").unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".git").join("config"), "").unwrap();
+
+        let args: Vec<String> = [".", "--ignore", "*_test.rs", "--ignore", ".git/"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let options = parse_options(&args).unwrap();
+        assert_eq!(options.ignore, vec!["*_test.rs", ".git"]);
+
+        assert_eq!(find_relative(dir.path(), &options), vec!["a.rs"]);
+    }
+
+    #[test]
+    fn test_relative_output() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src").join("bin")).unwrap();
+        fs::write(dir.path().join("src").join("bin").join("tool.rs"), "").unwrap();
+
+        let args: Vec<String> = [".", "-name", "*.rs", "--relative"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let options = parse_options(&args).unwrap();
+        assert!(options.relative);
+
+        let mut found = Vec::new();
+        find_files(dir.path(), &options, &mut found, &mut Vec::new());
+        let mut out = Vec::new();
+        for path in &found {
+            print_path(
+                display_path(path, dir.path(), options.relative),
+                options.print0,
+                &mut out,
+            );
+        }
+        let expected = Path::new("src").join("bin").join("tool.rs");
+        assert_eq!(out, format!("{}\n", expected.display()).into_bytes());
+
+        // 不加 --relative 时保持完整路径
+        let full = display_path(&found[0], dir.path(), false);
+        assert!(full.starts_with(dir.path()));
+    }
+
+    #[test]
+    fn test_relative_drops_current_dir() {
+        let path = Path::new("./src/main.rs");
+        assert_eq!(
+            display_path(path, Path::new("."), true),
+            Path::new("src/main.rs")
+        );
+        assert_eq!(display_path(path, Path::new("."), false), path);
+    }
 }
@@ -2,8 +2,9 @@
 // 提供文件操作的通用工具函数
 
 use std::fs;
-use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
 
 /// 文件统计信息
 #[derive(Debug, Default)]
@@ -42,6 +43,169 @@ pub fn stats_file(path: &Path) -> io::Result<FileStats> {
     Ok(stats)
 }
 
+/// 并行统计多个文件
+///
+/// 文件按顺序切成若干块，每个线程处理一块；按块的顺序 join，
+/// 所以返回结果的顺序与输入一致，和线程谁先完成无关
+pub fn stats_files_parallel(paths: Vec<PathBuf>) -> Vec<(PathBuf, io::Result<FileStats>)> {
+    // 线程数不超过 CPU 核数，也不超过文件数
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(paths.len())
+        .max(1);
+    let chunk_size = paths.len().div_ceil(workers).max(1);
+
+    // thread::scope 保证作用域结束前所有线程都已结束，
+    // 所以线程可以直接借用 paths，不需要 Arc
+    let results: Vec<io::Result<FileStats>> = thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || chunk.iter().map(|p| stats_file(p)).collect::<Vec<_>>())
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("统计线程 panic"))
+            .collect()
+    });
+
+    paths.into_iter().zip(results).collect()
+}
+
+/// 判断是否为二进制文件时采样的字节数
+const BINARY_SAMPLE_SIZE: usize = 8 * 1024;
+
+/// 判断文件是否为二进制文件
+///
+/// 与 git、grep 的做法相同：读取开头一段内容，出现 NUL 字节就认为是二进制。
+/// 文本文件（包括 UTF-8 中文）几乎不会包含 0x00
+pub fn is_binary(path: &Path) -> io::Result<bool> {
+    let file = fs::File::open(path)?;
+    let mut sample = Vec::with_capacity(BINARY_SAMPLE_SIZE);
+    // take 限制最多读取的字节数，大文件也只读开头
+    file.take(BINARY_SAMPLE_SIZE as u64)
+        .read_to_end(&mut sample)?;
+    Ok(sample.contains(&0))
+}
+
+/// 按行读取，遇到非 UTF-8 内容时用 U+FFFD 替换，而不是像 `BufRead::lines` 那样报错
+///
+/// 日志等文本里偶尔混入的非法字节不应该让整个文件读不下去
+pub struct LossyLines<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> LossyLines<R> {
+    pub fn new(reader: R) -> Self {
+        LossyLines {
+            reader,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LossyLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buf.clear();
+        match self.reader.read_until(b'\n', &mut self.buf) {
+            Ok(0) => None,
+            Ok(_) => {
+                // 与 lines() 一致：去掉行尾的 \n 或 \r\n
+                if self.buf.ends_with(b"\n") {
+                    self.buf.pop();
+                    if self.buf.ends_with(b"\r") {
+                        self.buf.pop();
+                    }
+                }
+                Some(Ok(String::from_utf8_lossy(&self.buf).into_owned()))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// 流式过滤：返回只产出匹配行的迭代器，适合大文件
+pub fn filter_lines_iter<P>(
+    path: &Path,
+    pred: P,
+) -> io::Result<impl Iterator<Item = io::Result<String>>>
+where
+    P: Fn(&str) -> bool,
+{
+    let file = fs::File::open(path)?;
+    let lines = LossyLines::new(BufReader::new(file));
+
+    // 读取错误原样向下游传递，由调用方决定如何处理
+    Ok(lines.filter(move |line| match line {
+        Ok(line) => pred(line),
+        Err(_) => true,
+    }))
+}
+
+/// 读取文件中所有满足条件的行
+pub fn filter_lines<P>(path: &Path, pred: P) -> io::Result<Vec<String>>
+where
+    P: Fn(&str) -> bool,
+{
+    // Iterator<Item = Result<T>> 可以直接 collect 成 Result<Vec<T>>，遇到第一个错误即停止
+    filter_lines_iter(path, pred)?.collect()
+}
+
+/// tail_lines 每次从文件末尾向前读取的字节数
+const TAIL_CHUNK_SIZE: u64 = 8 * 1024;
+
+/// 读取文件的最后 n 行
+///
+/// 从文件末尾开始按块向前读，数到足够的换行符就停止，
+/// 大日志文件只需读取末尾很小的一部分。文件不足 n 行时返回全部行
+pub fn tail_lines(path: &Path, n: usize) -> io::Result<Vec<String>> {
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut file = fs::File::open(path)?;
+    let mut pos = file.metadata()?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    let mut newlines = 0;
+    // 末尾的换行符只是最后一行的结束，不是行之间的分隔，需要多数一个
+    let mut needed = n;
+
+    while pos > 0 {
+        let size = TAIL_CHUNK_SIZE.min(pos);
+        pos -= size;
+
+        let mut chunk = vec![0; size as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut chunk)?;
+
+        if tail.is_empty() && chunk.last() == Some(&b'\n') {
+            needed += 1;
+        }
+        newlines += chunk.iter().filter(|&&b| b == b'\n').count();
+
+        // 新读到的块在前面
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+
+        // 有 n 个分隔换行符，说明最后 n 行已经完整读入
+        if newlines >= needed {
+            break;
+        }
+    }
+
+    // 块边界可能切在多字节字符中间，所以拼完再整体解码
+    let text = String::from_utf8_lossy(&tail);
+    let lines: Vec<&str> = text.lines().collect();
+    let skip = lines.len().saturating_sub(n);
+    Ok(lines[skip..].iter().map(|s| s.to_string()).collect())
+}
+
 /// 安全写入文件（先写临时文件，再原子重命名）
 pub fn safe_write(path: &Path, content: &str) -> io::Result<()> {
     let tmp = path.with_extension("tmp");
@@ -50,6 +214,137 @@ pub fn safe_write(path: &Path, content: &str) -> io::Result<()> {
     Ok(())
 }
 
+/// 文件使用的换行符风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`，Unix / macOS
+    Lf,
+    /// `\r\n`，Windows
+    CrLf,
+    /// 单独的 `\r`，老式 Mac OS
+    Cr,
+    /// 同一个文件里混用了多种换行符
+    Mixed,
+}
+
+impl LineEnding {
+    /// 换行符本身；Mixed 没有统一的写法，按 LF 处理
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf | LineEnding::Mixed => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// 把任意换行符的文本转换成这种风格，用于写回文件时保持原来的风格
+    pub fn apply(&self, s: &str) -> String {
+        let normalized = normalize_newlines(s);
+        match self {
+            LineEnding::Lf | LineEnding::Mixed => normalized,
+            _ => normalized.replace('\n', self.as_str()),
+        }
+    }
+}
+
+/// 把 CRLF 和单独的 CR 统一转换成 LF
+///
+/// 必须先替换 `\r\n`，否则它会被拆成两个换行
+pub fn normalize_newlines(s: &str) -> String {
+    s.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// 检测文件使用的换行符
+///
+/// 只有一种换行符时返回它，多种并存时返回 Mixed；
+/// 没有任何换行符的文件（空文件或只有一行）按 LF 处理
+pub fn detect_line_ending(path: &Path) -> io::Result<LineEnding> {
+    let bytes = fs::read(path)?;
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                crlf += 1;
+                i += 1;
+            }
+            b'\r' => cr += 1,
+            b'\n' => lf += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    Ok(match (lf > 0, crlf > 0, cr > 0) {
+        (_, false, false) => LineEnding::Lf,
+        (false, true, false) => LineEnding::CrLf,
+        (false, false, true) => LineEnding::Cr,
+        _ => LineEnding::Mixed,
+    })
+}
+
+/// human_bytes 使用的单位，每一级是上一级的 1024 倍
+const BYTE_UNITS: [&str; 6] = ["KB", "MB", "GB", "TB", "PB", "EB"];
+
+/// 把字节数格式化成易读的形式，如 `512 B`、`1.5 KB`、`3.2 MB`
+///
+/// 不足 1024 字节时显示整数；否则保留一位小数，
+/// 四舍五入后达到 1024 的（如 1023.97 KB）进位到下一个单位，显示为 1.0 MB
+pub fn human_bytes(n: u64) -> String {
+    if n < 1024 {
+        return format!("{} B", n);
+    }
+
+    let mut value = n as f64 / 1024.0;
+    let mut unit = 0;
+    // 按保留一位小数后的结果判断是否进位
+    while (value * 10.0).round() >= 10240.0 && unit + 1 < BYTE_UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, BYTE_UNITS[unit])
+}
+
+/// 单个字符在等宽终端中占的列数
+///
+/// 中日韩文字和全角符号占 2 列，控制字符不占位置，其余占 1 列。
+/// 这里只列出常见的宽字符区间，不追求覆盖完整的 Unicode 宽度表
+///
+/// 每一章都是独立的 crate，第 7 章 task 表格里的 char_width 是同一份区间表，
+/// 有意各自保留一份；修改区间时两处要一起改
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x00..=0x1F | 0x7F => 0,
+        0x1100..=0x115F          // 韩文字母
+        | 0x2E80..=0x303E        // CJK 部首、标点
+        | 0x3041..=0x33FF        // 日文假名、CJK 符号
+        | 0x3400..=0x4DBF        // CJK 扩展 A
+        | 0x4E00..=0x9FFF        // CJK 统一汉字
+        | 0xA000..=0xA4CF        // 彝文
+        | 0xAC00..=0xD7A3        // 韩文音节
+        | 0xF900..=0xFAFF        // CJK 兼容汉字
+        | 0xFE30..=0xFE4F        // CJK 兼容标点
+        | 0xFF00..=0xFF60        // 全角 ASCII、全角标点
+        | 0xFFE0..=0xFFE6        // 全角符号
+        | 0x1F300..=0x1F64F      // 表情符号
+        | 0x20000..=0x3FFFD => 2, // CJK 扩展 B 及以后
+        _ => 1,
+    }
+}
+
+/// 字符串在终端中的显示宽度（列数）
+///
+/// `{:<10}` 按字符个数补齐，一个汉字却占 2 列，所以对齐含中文的表格要用显示宽度。
+/// 制表符跳到下一个 `tab_width` 的整数倍列，宽度取决于它前面有多少列；
+/// `tab_width` 为 0 时制表符不占位置
+pub fn display_width(s: &str, tab_width: usize) -> usize {
+    s.chars().fold(0, |col, c| match c {
+        '\t' if tab_width > 0 => (col / tab_width + 1) * tab_width,
+        _ => col + char_width(c),
+    })
+}
+
 /// 确认提示
 pub fn confirm(prompt: &str) -> bool {
     use std::io::Write;
@@ -81,4 +376,223 @@ mod tests {
         assert_eq!(stats.blank, 1);
         assert_eq!(stats.code, 2);
     }
+
+    #[test]
+    fn test_stats_files_parallel_keeps_order() {
+        // 每个文件的行数不同，便于确认结果和路径一一对应
+        let files: Vec<NamedTempFile> = (1..=10)
+            .map(|n| {
+                let mut file = NamedTempFile::new().unwrap();
+                for i in 0..n {
+                    writeln!(file, "line {}", i).unwrap();
+                }
+                file
+            })
+            .collect();
+
+        let mut paths: Vec<PathBuf> = files.iter().map(|f| f.path().to_path_buf()).collect();
+        paths.push(PathBuf::from("/nonexistent/file.txt"));
+
+        let results = stats_files_parallel(paths.clone());
+
+        assert_eq!(results.len(), 11);
+        for (i, (path, stats)) in results.iter().take(10).enumerate() {
+            assert_eq!(path, &paths[i]);
+            assert_eq!(stats.as_ref().unwrap().lines, i + 1);
+        }
+        assert!(results[10].1.is_err());
+    }
+
+    #[test]
+    fn test_stats_files_parallel_empty() {
+        assert!(stats_files_parallel(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_is_binary() {
+        let mut text = NamedTempFile::new().unwrap();
+        writeln!(text, "普通文本\nplain text").unwrap();
+        assert!(!is_binary(text.path()).unwrap());
+
+        let mut binary = NamedTempFile::new().unwrap();
+        binary.write_all(b"\x7fELF\x02\x01\x00\x00data").unwrap();
+        assert!(is_binary(binary.path()).unwrap());
+
+        let empty = NamedTempFile::new().unwrap();
+        assert!(!is_binary(empty.path()).unwrap());
+    }
+
+    #[test]
+    fn test_filter_lines() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "INFO start").unwrap();
+        writeln!(file, "ERROR disk full").unwrap();
+        writeln!(file, "INFO running").unwrap();
+        write!(file, "ERROR timeout").unwrap();
+
+        let lines = filter_lines(file.path(), |l| l.starts_with("ERROR")).unwrap();
+        assert_eq!(lines, vec!["ERROR disk full", "ERROR timeout"]);
+    }
+
+    #[test]
+    fn test_filter_lines_iter() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "a1\r\nb2\r\na3\r\n").unwrap();
+
+        let lines: Vec<String> = filter_lines_iter(file.path(), |l| l.starts_with('a'))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(lines, vec!["a1", "a3"]);
+    }
+
+    #[test]
+    fn test_filter_lines_non_utf8() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"ok line\nbad \xff\xfe byte\n").unwrap();
+
+        let lines = filter_lines(file.path(), |l| l.contains("bad")).unwrap();
+        assert_eq!(lines, vec!["bad \u{FFFD}\u{FFFD} byte"]);
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        let mut file = NamedTempFile::new().unwrap();
+        for i in 1..=5 {
+            writeln!(file, "line {}", i).unwrap();
+        }
+
+        let tail = tail_lines(file.path(), 2).unwrap();
+        assert_eq!(tail, vec!["line 4", "line 5"]);
+
+        // 不足 n 行时返回全部
+        let tail = tail_lines(file.path(), 10).unwrap();
+        assert_eq!(tail.len(), 5);
+        assert_eq!(tail[0], "line 1");
+
+        assert!(tail_lines(file.path(), 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tail_lines_no_trailing_newline() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "first\nsecond\nthird").unwrap();
+
+        assert_eq!(tail_lines(file.path(), 1).unwrap(), vec!["third"]);
+        assert_eq!(tail_lines(file.path(), 2).unwrap(), vec!["second", "third"]);
+    }
+
+    #[test]
+    fn test_tail_lines_across_chunks() {
+        // 每行 100 字节，总大小远超一个块，最后几行跨越块边界
+        let mut file = NamedTempFile::new().unwrap();
+        for i in 0..500 {
+            writeln!(file, "{:099}", i).unwrap();
+        }
+
+        let tail = tail_lines(file.path(), 3).unwrap();
+        assert_eq!(
+            tail,
+            vec![
+                format!("{:099}", 497),
+                format!("{:099}", 498),
+                format!("{:099}", 499)
+            ]
+        );
+
+        let tail = tail_lines(file.path(), 200).unwrap();
+        assert_eq!(tail.len(), 200);
+        assert_eq!(tail[0], format!("{:099}", 300));
+    }
+
+    #[test]
+    fn test_human_bytes() {
+        assert_eq!(human_bytes(0), "0 B");
+        assert_eq!(human_bytes(1023), "1023 B");
+        // 正好 1024 进位到 KB
+        assert_eq!(human_bytes(1024), "1.0 KB");
+        assert_eq!(human_bytes(1536), "1.5 KB");
+        assert_eq!(human_bytes(10 * 1024 + 100), "10.1 KB");
+        assert_eq!(human_bytes(3_355_443), "3.2 MB");
+        assert_eq!(human_bytes(1024 * 1024 * 1024), "1.0 GB");
+        assert_eq!(human_bytes(5 * (1 << 40)), "5.0 TB");
+        assert_eq!(human_bytes(u64::MAX), "16.0 EB");
+    }
+
+    #[test]
+    fn test_human_bytes_rounding_rolls_over() {
+        // 1048575 B = 1023.999 KB，保留一位小数是 1024.0，应该显示为 1.0 MB
+        assert_eq!(human_bytes(1024 * 1024 - 1), "1.0 MB");
+        assert_eq!(human_bytes(1023 * 1024), "1023.0 KB");
+    }
+
+    #[test]
+    fn test_display_width_ascii_and_cjk() {
+        assert_eq!(display_width("", 4), 0);
+        assert_eq!(display_width("hello", 4), 5);
+        assert_eq!(display_width("文件", 4), 4);
+        assert_eq!(display_width("a中b", 4), 4);
+        // 全角标点也占 2 列
+        assert_eq!(display_width("你好，世界", 4), 10);
+    }
+
+    #[test]
+    fn test_display_width_tabs() {
+        // 制表符补齐到下一个制表位，而不是固定占 tab_width 列
+        assert_eq!(display_width("\t", 4), 4);
+        assert_eq!(display_width("ab\tc", 4), 5);
+        assert_eq!(display_width("abcd\tc", 4), 9);
+        assert_eq!(display_width("ab\tc", 8), 9);
+        assert_eq!(display_width("\t\t", 8), 16);
+
+        // 汉字按 2 列计算制表位：「中文」占 4 列，正好落在制表位上
+        assert_eq!(display_width("中文\tx", 4), 9);
+        assert_eq!(display_width("中\tx", 4), 5);
+        assert_eq!(display_width("中\tx", 8), 9);
+
+        assert_eq!(display_width("a\tb", 0), 2);
+    }
+
+    /// 把原始字节写入临时文件并检测换行符
+    fn ending_of(content: &[u8]) -> LineEnding {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        detect_line_ending(file.path()).unwrap()
+    }
+
+    #[test]
+    fn test_detect_line_ending() {
+        assert_eq!(ending_of(b"a\nb\n"), LineEnding::Lf);
+        assert_eq!(ending_of(b"a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(ending_of(b"a\rb\r"), LineEnding::Cr);
+        assert_eq!(ending_of(b"a\r\nb\nc\r\n"), LineEnding::Mixed);
+        assert_eq!(ending_of(b"a\rb\r\n"), LineEnding::Mixed);
+        // 没有换行符时按 LF 处理
+        assert_eq!(ending_of(b""), LineEnding::Lf);
+        assert_eq!(ending_of(b"single line"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_normalize_newlines() {
+        assert_eq!(normalize_newlines("a\nb\n"), "a\nb\n");
+        assert_eq!(normalize_newlines("a\r\nb\r\n"), "a\nb\n");
+        assert_eq!(normalize_newlines("a\rb\r\nc\n"), "a\nb\nc\n");
+        // 连续的 \r\r\n 是一个 CR 加一个 CRLF，即两个换行
+        assert_eq!(normalize_newlines("a\r\r\nb"), "a\n\nb");
+    }
+
+    #[test]
+    fn test_line_ending_apply_preserves_style() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"one\r\ntwo\r\n").unwrap();
+
+        // 读出来统一成 LF 处理，写回时恢复成原来的 CRLF
+        let ending = detect_line_ending(file.path()).unwrap();
+        let text = normalize_newlines(&fs::read_to_string(file.path()).unwrap());
+        assert_eq!(text, "one\ntwo\n");
+
+        safe_write(file.path(), &ending.apply(&format!("{}three\n", text))).unwrap();
+        assert_eq!(fs::read(file.path()).unwrap(), b"one\r\ntwo\r\nthree\r\n");
+        assert_eq!(LineEnding::Mixed.apply("a\r\nb\r"), "a\nb\n");
+    }
 }
@@ -1,16 +1,26 @@
 // line-stats: 代码行统计工具
-// 用法: line-stats <文件或glob模式>...
+// 用法: line-stats [--all] <文件或glob模式>...
 // 示例: line-stats src/**/*.rs
 
-use common::FileStats;
+use common::{display_width, FileStats};
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// 文件名一列的显示宽度
+const NAME_WIDTH: usize = 40;
+
+/// 文件名里的制表符按 8 列展开，与大多数终端一致
+const TAB_WIDTH: usize = 8;
 
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
 
+    // --all: 二进制文件也参与统计
+    let include_all = args.iter().any(|a| a == "--all");
+    let args: Vec<String> = args.into_iter().filter(|a| a != "--all").collect();
+
     if args.is_empty() {
-        eprintln!("用法: line-stats <文件或glob模式>...");
+        eprintln!("用法: line-stats [--all] <文件或glob模式>...");
         eprintln!("示例: line-stats src/**/*.rs");
         std::process::exit(1);
     }
@@ -33,30 +43,33 @@ fn main() {
 
     // 打印表头
     println!(
-        "{:<40} {:>8} {:>8} {:>8}",
-        "文件", "行数", "空行", "代码行"
+        "{} {:>8} {:>8} {:>8}",
+        pad_name("文件"),
+        "行数",
+        "空行",
+        "代码行"
     );
     println!("{}", "-".repeat(68));
 
-    // 统计每个文件
+    // 二进制文件单独列出，其余文件并行统计
+    let (skipped, texts): (Vec<PathBuf>, Vec<PathBuf>) =
+        files.into_iter().partition(|p| should_skip(p, include_all));
+
     let mut total = FileStats::default();
+    let mut counted = 0;
 
-    for path in &files {
-        match common::stats_file(path) {
+    for (path, result) in common::stats_files_parallel(texts) {
+        match result {
             Ok(stats) => {
-                // 截断过长的文件名
-                let display_name = path.to_string_lossy();
-                let display_name = if display_name.len() > 38 {
-                    format!("...{}", &display_name[display_name.len() - 35..])
-                } else {
-                    display_name.to_string()
-                };
-
                 println!(
-                    "{:<40} {:>8} {:>8} {:>8}",
-                    display_name, stats.lines, stats.blank, stats.code
+                    "{} {:>8} {:>8} {:>8}",
+                    pad_name(&shorten(&path)),
+                    stats.lines,
+                    stats.blank,
+                    stats.code
                 );
 
+                counted += 1;
                 total.lines += stats.lines;
                 total.blank += stats.blank;
                 total.code += stats.code;
@@ -68,14 +81,108 @@ fn main() {
         }
     }
 
+    for path in &skipped {
+        println!("{} (binary, skipped)", pad_name(&shorten(path)));
+    }
+
     // 打印总计
     println!("{}", "-".repeat(68));
     println!(
-        "{:<40} {:>8} {:>8} {:>8}",
-        format!("总计 ({} 个文件)", files.len()),
+        "{} {:>8} {:>8} {:>8}",
+        pad_name(&format!("总计 ({} 个文件)", counted)),
         total.lines,
         total.blank,
         total.code
     );
-    println!("总字节数: {} bytes", total.bytes);
+    println!(
+        "总字节数: {} ({} bytes)",
+        common::human_bytes(total.bytes as u64),
+        total.bytes
+    );
+}
+
+/// 截断过长的文件名，保留末尾部分
+///
+/// 按显示宽度而不是字节数计算：中文路径既不会在字符中间被切开，
+/// 也不会因为一个汉字占 3 个字节而被过早截断
+fn shorten(path: &Path) -> String {
+    let display_name = path.to_string_lossy();
+    if display_width(&display_name, TAB_WIDTH) <= NAME_WIDTH - 2 {
+        return display_name.to_string();
+    }
+
+    // 从末尾开始取字符，直到放不下为止
+    let mut width = 0;
+    let mut start = display_name.len();
+    for (i, c) in display_name.char_indices().rev() {
+        width += display_width(c.encode_utf8(&mut [0; 4]), TAB_WIDTH);
+        if width > NAME_WIDTH - 5 {
+            break;
+        }
+        start = i;
+    }
+    format!("...{}", &display_name[start..])
+}
+
+/// 按显示宽度把文件名补齐到 NAME_WIDTH 列
+fn pad_name(name: &str) -> String {
+    let padding = NAME_WIDTH.saturating_sub(display_width(name, TAB_WIDTH));
+    format!("{}{}", name, " ".repeat(padding))
+}
+
+/// 是否跳过该文件：默认跳过二进制文件，--all 时全部统计
+///
+/// 无法读取时不跳过，交给 stats_file 报告具体错误
+fn should_skip(path: &Path, include_all: bool) -> bool {
+    !include_all && common::is_binary(path).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_binary_skipped_by_default() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"PK\x03\x04\x00\x00binary").unwrap();
+
+        assert!(should_skip(file.path(), false));
+        assert!(!should_skip(file.path(), true));
+    }
+
+    #[test]
+    fn test_text_not_skipped() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "fn main() {{}}").unwrap();
+
+        assert!(!should_skip(file.path(), false));
+    }
+
+    #[test]
+    fn test_shorten_by_display_width() {
+        let short = Path::new("src/main.rs");
+        assert_eq!(shorten(short), "src/main.rs");
+
+        let ascii = format!("{}/main.rs", "a".repeat(40));
+        let shortened = shorten(Path::new(&ascii));
+        assert!(shortened.starts_with("...") && shortened.ends_with("main.rs"));
+        assert_eq!(display_width(&shortened, TAB_WIDTH), NAME_WIDTH - 2);
+
+        // 以前按字节截断，切到汉字中间会 panic
+        let cjk = format!("{}/说明.md", "文档".repeat(10));
+        let shortened = shorten(Path::new(&cjk));
+        assert!(shortened.ends_with("说明.md"));
+        assert!(display_width(&shortened, TAB_WIDTH) <= NAME_WIDTH - 2);
+    }
+
+    #[test]
+    fn test_pad_name_aligns_cjk() {
+        assert_eq!(display_width(&pad_name("文件"), TAB_WIDTH), NAME_WIDTH);
+        assert_eq!(
+            display_width(&pad_name("src/main.rs"), TAB_WIDTH),
+            NAME_WIDTH
+        );
+    }
 }
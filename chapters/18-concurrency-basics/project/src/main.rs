@@ -1,20 +1,70 @@
 // parallel-hash: 并行计算多个文件的 SHA256 哈希
-// 用法: parallel-hash <文件>...
+// 用法: parallel-hash [--output <清单文件> | --dups] <文件>...
+//       parallel-hash --diff <目录A> <目录B>
 // 示例: parallel-hash *.txt
+//       parallel-hash --output SHA256SUMS *.txt
+//       parallel-hash --dups photos/*.jpg
+//       parallel-hash --diff backup/ current/
+//
+// --output 把结果写成 `哈希  路径` 格式的清单（与 sha256sum 相同，
+// 可以用 sha256sum -c 校验），而不是打印到标准输出
+//
+// --dups 按哈希把文件分组，只列出内容完全相同的文件，用来找多余的副本
+//
+// --diff 并行计算两棵目录树中所有文件的哈希，按相对路径比较，
+// 列出内容不同、只在 A 中、只在 B 中的文件；有差异时退出码为 1（与 diff 相同）
 
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
 use std::time::Instant;
 
 fn main() {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().map(String::as_str) == Some("--diff") {
+        match &args[1..] {
+            [a, b] => run_diff(Path::new(a), Path::new(b)),
+            _ => {
+                eprintln!("用法: parallel-hash --diff <目录A> <目录B>");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // 取出 --output <path>，剩下的都是要计算的文件
+    let output = match args.iter().position(|a| a == "--output") {
+        Some(i) if i + 1 < args.len() => {
+            let path = PathBuf::from(args.remove(i + 1));
+            args.remove(i);
+            Some(path)
+        }
+        Some(_) => {
+            eprintln!("--output 需要一个文件路径");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    let dups = match args.iter().position(|a| a == "--dups") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    if dups && output.is_some() {
+        eprintln!("--dups 不能与 --output 同时使用");
+        std::process::exit(1);
+    }
 
     if args.is_empty() {
-        eprintln!("用法: parallel-hash <文件>...");
+        eprintln!("用法: parallel-hash [--output <清单文件> | --dups] <文件>...");
         eprintln!("示例: parallel-hash *.txt");
         std::process::exit(1);
     }
@@ -37,8 +87,10 @@ fn main() {
     let results = hash_files_parallel(paths);
 
     // 输出结果
-    for (path, hash) in &results {
-        println!("{}  sha256:{}", path.display(), hash);
+    if dups {
+        print_duplicates(&find_duplicates(&results));
+    } else {
+        print_results(&results, output.as_deref());
     }
 
     let duration = start.elapsed();
@@ -49,10 +101,185 @@ fn main() {
     );
 }
 
+/// 打印每个文件的哈希，或者写入 --output 指定的清单
+fn print_results(results: &[(PathBuf, String)], output: Option<&Path>) {
+    match output {
+        Some(manifest) => {
+            if let Err(e) = write_manifest(manifest, results) {
+                eprintln!("写入 {} 失败: {}", manifest.display(), e);
+                std::process::exit(1);
+            }
+            println!("清单已写入 {}", manifest.display());
+        }
+        None => {
+            for (path, hash) in results {
+                println!("{}  sha256:{}", path.display(), hash);
+            }
+        }
+    }
+}
+
+/// 按哈希分组，返回成员不止一个的组
+///
+/// 组内路径保持输入顺序，组之间按各自第一个路径在输入中的位置排序，输出可复现；
+/// 读取失败的文件没有有效哈希，不参与分组
+fn find_duplicates(results: &[(PathBuf, String)]) -> Vec<(String, Vec<PathBuf>)> {
+    let mut groups: HashMap<&str, Vec<PathBuf>> = HashMap::new();
+    // 记录每个哈希第一次出现的顺序
+    let mut order = Vec::new();
+
+    for (path, hash) in results {
+        if hash.starts_with("ERROR") {
+            continue;
+        }
+        let group = groups.entry(hash).or_default();
+        if group.is_empty() {
+            order.push(hash.as_str());
+        }
+        group.push(path.clone());
+    }
+
+    order
+        .into_iter()
+        .filter_map(|hash| {
+            let paths = groups.remove(hash)?;
+            (paths.len() > 1).then(|| (hash.to_string(), paths))
+        })
+        .collect()
+}
+
+/// 打印 --dups 的结果，每组先列哈希再列文件
+fn print_duplicates(groups: &[(String, Vec<PathBuf>)]) {
+    if groups.is_empty() {
+        println!("没有内容相同的文件");
+        return;
+    }
+    for (hash, paths) in groups {
+        println!("sha256:{} ({} 个文件)", hash, paths.len());
+        for path in paths {
+            println!("  {}", path.display());
+        }
+    }
+}
+
+/// --diff 模式：比较两个目录并打印差异，然后退出进程
+fn run_diff(a: &Path, b: &Path) -> ! {
+    let start = Instant::now();
+
+    // 两棵树互不相关，用 scope 在两个线程里同时计算；
+    // scope 保证线程在返回前结束，所以可以直接借用 a 和 b
+    let (tree_a, tree_b) = thread::scope(|s| {
+        let handle_a = s.spawn(|| hash_tree(a));
+        let tree_b = hash_tree(b);
+        (handle_a.join().unwrap(), tree_b)
+    });
+
+    let (tree_a, tree_b) = match (tree_a, tree_b) {
+        (Ok(tree_a), Ok(tree_b)) => (tree_a, tree_b),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("无法读取目录: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let diff = diff_trees(&tree_a, &tree_b);
+    for path in &diff.changed {
+        println!("不同:    {}", path.display());
+    }
+    for path in &diff.only_a {
+        println!("仅在 A:  {}", path.display());
+    }
+    for path in &diff.only_b {
+        println!("仅在 B:  {}", path.display());
+    }
+
+    println!(
+        "\n比较了 {} + {} 个文件，用时 {:.2} 秒",
+        tree_a.len(),
+        tree_b.len(),
+        start.elapsed().as_secs_f64()
+    );
+
+    if diff.is_empty() {
+        println!("两个目录内容相同");
+        std::process::exit(0);
+    }
+    std::process::exit(1);
+}
+
+/// 两棵目录树的差异，路径都是相对于各自根目录的，并按路径排序
+#[derive(Debug, Default, PartialEq)]
+struct TreeDiff {
+    /// 两边都有但内容不同
+    changed: Vec<PathBuf>,
+    /// 只在 A 中
+    only_a: Vec<PathBuf>,
+    /// 只在 B 中
+    only_b: Vec<PathBuf>,
+}
+
+impl TreeDiff {
+    fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.only_a.is_empty() && self.only_b.is_empty()
+    }
+}
+
+/// 递归收集目录下的所有普通文件
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 计算目录树中所有文件的哈希，返回 相对路径 -> 哈希
+///
+/// 用 BTreeMap 而不是 HashMap，遍历时天然按路径排序，输出可复现
+fn hash_tree(root: &Path) -> io::Result<BTreeMap<PathBuf, String>> {
+    let mut files = Vec::new();
+    collect_files(root, &mut files)?;
+
+    Ok(hash_files_parallel(files)
+        .into_iter()
+        .map(|(path, hash)| {
+            // collect_files 返回的路径都以 root 开头
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            (relative, hash)
+        })
+        .collect())
+}
+
+/// 按相对路径比较两棵树的哈希
+fn diff_trees(a: &BTreeMap<PathBuf, String>, b: &BTreeMap<PathBuf, String>) -> TreeDiff {
+    let mut diff = TreeDiff::default();
+
+    for (path, hash_a) in a {
+        match b.get(path) {
+            Some(hash_b) if hash_b != hash_a => diff.changed.push(path.clone()),
+            Some(_) => {}
+            None => diff.only_a.push(path.clone()),
+        }
+    }
+    diff.only_b = b.keys().filter(|p| !a.contains_key(*p)).cloned().collect();
+
+    diff
+}
+
 /// 并行计算多个文件的哈希值
 ///
-/// 使用 Arc 共享文件列表，每个线程负责一个文件
+/// 使用 Arc 共享文件列表，每个线程负责一个文件。
+/// 返回结果的顺序与输入顺序一致
 fn hash_files_parallel(paths: Vec<PathBuf>) -> Vec<(PathBuf, String)> {
+    hash_files_with(paths, hash_file)
+}
+
+/// hash_files_parallel 的实现，哈希函数作为参数传入，方便测试替换
+fn hash_files_with(paths: Vec<PathBuf>, hasher: fn(&PathBuf) -> String) -> Vec<(PathBuf, String)> {
     // Arc: Atomic Reference Count，原子引用计数
     // 允许多个线程共享所有权
     let paths = Arc::new(paths);
@@ -65,10 +292,11 @@ fn hash_files_parallel(paths: Vec<PathBuf>) -> Vec<(PathBuf, String)> {
 
         // thread::spawn 需要 'static 生命周期
         // move 闭包将 paths 和 i 的所有权移入线程
+        // 线程把自己的下标 i 一起返回，收集后据此排序
         let handle = thread::spawn(move || {
             let path = &paths[i];
-            let hash = hash_file(path);
-            (path.clone(), hash)
+            let hash = hasher(path);
+            (i, path.clone(), hash)
         });
 
         handles.push(handle);
@@ -76,9 +304,14 @@ fn hash_files_parallel(paths: Vec<PathBuf>) -> Vec<(PathBuf, String)> {
 
     // 收集所有线程的结果
     // join() 等待线程完成并返回结果
-    handles
+    let mut results: Vec<(usize, PathBuf, String)> =
+        handles.into_iter().filter_map(|h| h.join().ok()).collect();
+
+    // 不依赖 join 的顺序：按输入下标显式排序，输出才是可复现的
+    results.sort_by_key(|(i, _, _)| *i);
+    results
         .into_iter()
-        .filter_map(|h| h.join().ok())
+        .map(|(_, path, hash)| (path, hash))
         .collect()
 }
 
@@ -97,6 +330,41 @@ fn hash_file(path: &PathBuf) -> String {
     }
 }
 
+/// 生成清单内容：每行 `哈希  路径`
+///
+/// 读取失败的文件没有有效哈希，不写入清单，只在 stderr 提示
+fn format_manifest(results: &[(PathBuf, String)]) -> String {
+    let mut manifest = String::new();
+    for (path, hash) in results {
+        if hash.starts_with("ERROR") {
+            eprintln!("跳过 {}: {}", path.display(), hash);
+            continue;
+        }
+        manifest.push_str(&format!("{}  {}\n", hash, path.display()));
+    }
+    manifest
+}
+
+/// 把清单原子地写入 path
+///
+/// 先写到同目录下的临时文件再 rename：rename 在同一文件系统内是原子的，
+/// 中途出错也不会留下只写了一半的清单
+fn write_manifest(path: &Path, results: &[(PathBuf, String)]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, format_manifest(results))?;
+    // rename 失败时删掉临时文件，再把原来的错误返回
+    match fs::rename(&tmp, path) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,4 +400,121 @@ mod tests {
         let results = hash_files_parallel(paths);
         assert_eq!(results.len(), 2);
     }
+
+    /// 文件内容是一个数字 n，数字越小睡得越久，越晚完成
+    fn slow_hasher(path: &PathBuf) -> String {
+        let n: u64 = fs::read_to_string(path).unwrap().parse().unwrap();
+        thread::sleep(std::time::Duration::from_millis((5 - n) * 20));
+        n.to_string()
+    }
+
+    #[test]
+    fn test_results_follow_input_order() {
+        let files: Vec<NamedTempFile> = (0..5)
+            .map(|n| {
+                let mut file = NamedTempFile::new().unwrap();
+                write!(file, "{}", n).unwrap();
+                file
+            })
+            .collect();
+        let paths: Vec<PathBuf> = files.iter().map(|f| f.path().to_path_buf()).collect();
+
+        // 第一个文件最后完成，但结果仍按输入顺序排列
+        let results = hash_files_with(paths.clone(), slow_hasher);
+
+        let result_paths: Vec<PathBuf> = results.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(result_paths, paths);
+        let hashes: Vec<&str> = results.iter().map(|(_, h)| h.as_str()).collect();
+        assert_eq!(hashes, vec!["0", "1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_write_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        fs::write(&a, "hello world").unwrap();
+        fs::write(&b, "test2").unwrap();
+        let missing = dir.path().join("missing.txt");
+
+        let results = hash_files_parallel(vec![a.clone(), missing, b.clone()]);
+        let manifest = dir.path().join("SHA256SUMS");
+        write_manifest(&manifest, &results).unwrap();
+
+        // 读取失败的文件被跳过，其余每行都是 `哈希  路径`，且与计算结果一致
+        let content = fs::read_to_string(&manifest).unwrap();
+        let expected = format!(
+            "{}  {}\n{}  {}\n",
+            hash_file(&a),
+            a.display(),
+            hash_file(&b),
+            b.display()
+        );
+        assert_eq!(content, expected);
+        let hello = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        assert!(content.starts_with(&format!("{}  ", hello)));
+
+        // 不留下临时文件
+        assert!(!dir.path().join("SHA256SUMS.tmp").exists());
+    }
+
+    #[test]
+    fn test_diff_trees() {
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        for dir in [a.path(), b.path()] {
+            fs::create_dir(dir.join("sub")).unwrap();
+            fs::write(dir.join("same.txt"), "unchanged").unwrap();
+            fs::write(dir.join("sub").join("config.toml"), "v = 1").unwrap();
+        }
+        // B 中修改一个文件、新增一个文件，A 中多一个 B 没有的文件
+        fs::write(b.path().join("sub").join("config.toml"), "v = 2").unwrap();
+        fs::write(b.path().join("sub").join("new.txt"), "new").unwrap();
+        fs::write(a.path().join("old.txt"), "old").unwrap();
+
+        let tree_a = hash_tree(a.path()).unwrap();
+        let tree_b = hash_tree(b.path()).unwrap();
+        assert_eq!(tree_a.len(), 3);
+        assert_eq!(tree_b.len(), 3);
+
+        let diff = diff_trees(&tree_a, &tree_b);
+        assert_eq!(
+            diff,
+            TreeDiff {
+                changed: vec![PathBuf::from("sub/config.toml")],
+                only_a: vec![PathBuf::from("old.txt")],
+                only_b: vec![PathBuf::from("sub/new.txt")],
+            }
+        );
+
+        // 和自己比较没有差异
+        assert!(diff_trees(&tree_a, &tree_a).is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        let b = dir.path().join("b.txt");
+        let copy = dir.path().join("a copy.txt");
+        fs::write(&a, "same content").unwrap();
+        fs::write(&b, "different").unwrap();
+        fs::write(&copy, "same content").unwrap();
+        let missing = dir.path().join("missing.txt");
+
+        let results = hash_files_parallel(vec![a.clone(), b, missing, copy.clone()]);
+        let groups = find_duplicates(&results);
+
+        // 只有 a 和它的副本成组，b 和读取失败的文件都不出现
+        assert_eq!(groups, vec![(hash_file(&a), vec![a, copy])]);
+    }
+
+    #[test]
+    fn test_find_duplicates_none() {
+        let results = vec![
+            (PathBuf::from("x"), "1".to_string()),
+            (PathBuf::from("y"), "2".to_string()),
+        ];
+        assert!(find_duplicates(&results).is_empty());
+    }
 }
@@ -1,14 +1,135 @@
 // log-watcher: 多文件日志监控工具
-// 用法: log-watcher <文件>... --pattern <匹配模式>
-// 示例: log-watcher app.log web.log --pattern ERROR
+// 用法: log-watcher <文件或目录>... --pattern <匹配模式>... [--json] [--recursive]
+//                    [--since <时间> [--time-regex <正则>] [--drop-untimed]]
+//                    [--alert-after <N>] [--count-only]
+// 示例: log-watcher app.log web.log --pattern ERROR --pattern WARN
+//       log-watcher app.log --pattern ERROR --since 2024-06-01T12:00:00
+//       log-watcher app.log web.log --pattern ERROR --alert-after 100
+//       log-watcher app.log.1.gz --pattern ERROR
+//       log-watcher /var/log/myapp --recursive --pattern ERROR
+//       log-watcher app.log web.log --pattern ERROR --count-only
+//
+// 以 .gz 结尾的文件会先用 gzip 解压再逐行匹配，可以直接扫描轮转后压缩的日志
+// 参数是目录时监控其中所有 *.log 文件，--recursive 时也包括各级子目录
+// --count-only 不输出匹配的行，结束时只输出每个文件的匹配数和总数（类似 grep -c）
 
+use flate2::read::GzDecoder;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::sync::mpsc;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
 use std::thread;
 
+/// 默认的时间戳正则：2024-06-01T12:00:00 或 2024-06-01 12:00:00
+const DEFAULT_TIME_REGEX: &str = r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}";
+
+/// 命令行配置
+struct Config {
+    /// 要监控的文件或目录
+    files: Vec<String>,
+    /// 目录参数是否递归查找子目录
+    recursive: bool,
+    /// 行过滤条件
+    filter: LineFilter,
+    /// 是否以 JSON 格式输出（每行一个对象）
+    json: bool,
+    /// 单个文件的匹配数超过这个值时告警一次
+    alert_after: Option<usize>,
+    /// 只统计匹配数，不输出匹配的行
+    count_only: bool,
+}
+
+/// 每个文件的匹配数
+///
+/// BTreeMap 按文件名排序，--count-only 的输出顺序与线程完成的先后无关
+struct MatchCounts {
+    per_file: BTreeMap<String, usize>,
+    total: usize,
+}
+
+impl MatchCounts {
+    /// 所有文件先记为 0，没有匹配的文件也会出现在统计里
+    fn new(files: &[String]) -> Self {
+        MatchCounts {
+            per_file: files.iter().map(|f| (f.clone(), 0)).collect(),
+            total: 0,
+        }
+    }
+
+    fn record(&mut self, file: &str) {
+        *self.per_file.entry(file.to_string()).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    /// 每个文件一行：普通模式为 `文件:匹配数`，JSON 模式为 `{"file":...,"count":...}`
+    fn format(&self, json: bool) -> String {
+        self.per_file
+            .iter()
+            .map(|(file, count)| {
+                if json {
+                    format!("{}\n", serde_json::json!({ "file": file, "count": count }))
+                } else {
+                    format!("{}:{}\n", file, count)
+                }
+            })
+            .collect()
+    }
+}
+
+/// 按文件统计匹配数，在某个文件越过阈值的那一刻给出告警
+struct AlertTracker {
+    threshold: usize,
+    counts: HashMap<String, usize>,
+}
+
+impl AlertTracker {
+    fn new(threshold: usize) -> Self {
+        AlertTracker {
+            threshold,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// 记录 file 的一条匹配，刚好越过阈值时返回告警内容
+    ///
+    /// 只在计数等于 threshold + 1 时返回，之后继续增长也不再重复告警
+    fn record(&mut self, file: &str) -> Option<String> {
+        let count = self.counts.entry(file.to_string()).or_insert(0);
+        *count += 1;
+        if *count == self.threshold + 1 {
+            Some(format!("!! {} 的匹配超过 {} 条", file, self.threshold))
+        } else {
+            None
+        }
+    }
+}
+
+/// 一行日志需要满足的条件，所有监控线程共享同一份
+struct LineFilter {
+    /// 匹配模式，可以有多个，命中任意一个即可
+    patterns: Vec<String>,
+    /// 时间过滤，None 表示不按时间过滤
+    since: Option<TimeFilter>,
+}
+
+/// 按行内时间戳过滤：早于 since 的行跳过
+struct TimeFilter {
+    /// 起始时间（ISO 格式）
+    since: String,
+    /// 从行中提取时间戳；有捕获组时取第 1 组，否则取整个匹配
+    regex: Regex,
+    /// 找不到时间戳的行是否保留
+    keep_untimed: bool,
+}
+
 /// 日志条目
+///
+/// 派生 Serialize 后可以直接输出为 JSON，字段名即 JSON 的键名
+#[derive(Serialize)]
 struct LogEntry {
     /// 来源文件
     file: String,
@@ -22,81 +143,286 @@ fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
 
     // 解析参数
-    let (files, pattern) = match parse_args(&args) {
-        Some(parsed) => parsed,
+    let config = match parse_args(&args) {
+        Some(config) => config,
         None => {
-            eprintln!("用法: log-watcher <文件>... --pattern <匹配模式>");
-            eprintln!("示例: log-watcher app.log web.log --pattern ERROR");
+            eprintln!(
+                "用法: log-watcher <文件或目录>... --pattern <匹配模式>... [--json] [--recursive]"
+            );
+            eprintln!("                   [--since <时间> [--time-regex <正则>] [--drop-untimed]]");
+            eprintln!("                   [--alert-after <N>] [--count-only]");
+            eprintln!("示例: log-watcher app.log web.log --pattern ERROR --pattern WARN");
             std::process::exit(1);
         }
     };
 
+    // 目录先展开成其中的日志文件，之后每个文件一个线程
+    let files = expand_paths(&config.files, config.recursive);
     if files.is_empty() {
         eprintln!("没有指定要监控的文件");
         std::process::exit(1);
     }
 
-    println!("开始监控 {} 个文件，匹配模式: \"{}\"", files.len(), pattern);
-    println!("按 Ctrl+C 停止\n");
+    let patterns = config
+        .filter
+        .patterns
+        .iter()
+        .map(|p| format!("\"{}\"", p))
+        .collect::<Vec<_>>()
+        .join(" | ");
+
+    // JSON 模式下提示信息输出到 stderr，保证 stdout 只有 JSON 行，方便管道处理
+    if config.json {
+        eprintln!("开始监控 {} 个文件，匹配模式: {}", files.len(), patterns);
+    } else {
+        println!("开始监控 {} 个文件，匹配模式: {}", files.len(), patterns);
+        println!("按 Ctrl+C 停止\n");
+    }
+
+    let mut counts = MatchCounts::new(&files);
+
+    // 过滤条件只读，用 Arc 在线程间共享，不必每个线程复制一份
+    let rx = spawn_watchers(files, Arc::new(config.filter));
+
+    let mut alerts = config.alert_after.map(AlertTracker::new);
+    receive_matches(
+        rx,
+        config.json,
+        config.count_only,
+        &mut counts,
+        alerts.as_mut(),
+        &mut io::stdout(),
+    );
+
+    if config.count_only {
+        print!("{}", counts.format(config.json));
+    }
+
+    if config.json {
+        eprintln!("监控结束，共匹配 {} 条", counts.total);
+    } else {
+        println!("\n监控结束，共匹配 {} 条", counts.total);
+    }
+}
+
+/// 接收匹配结果并计数，--count-only 时不输出匹配的行
+fn receive_matches(
+    rx: impl IntoIterator<Item = LogEntry>,
+    json: bool,
+    count_only: bool,
+    counts: &mut MatchCounts,
+    mut alerts: Option<&mut AlertTracker>,
+    out: &mut impl Write,
+) {
+    // rx 实现了 IntoIterator，可以直接 for 循环
+    // 当所有发送端关闭时，迭代自动结束
+    for entry in rx {
+        if !count_only {
+            // 写入失败（如下游管道已关闭）时忽略，继续计数
+            let _ = writeln!(out, "{}", format_entry(&entry, json));
+        }
+        counts.record(&entry.file);
+
+        // 告警也要醒目，但 JSON 模式下 stdout 只能有 JSON，所以写到 stderr
+        if let Some(alert) = alerts.as_mut().and_then(|a| a.record(&entry.file)) {
+            if json {
+                eprintln!("{}", alert);
+            } else {
+                let _ = writeln!(out, "{}", alert);
+            }
+        }
+    }
+}
 
+/// 为每个文件创建一个监控线程，返回汇总所有匹配结果的接收端
+fn spawn_watchers(files: Vec<String>, filter: Arc<LineFilter>) -> mpsc::Receiver<LogEntry> {
     // 创建通道
     // mpsc: Multiple Producer, Single Consumer
     // tx: transmitter (发送端), rx: receiver (接收端)
     let (tx, rx) = mpsc::channel::<LogEntry>();
 
-    // 为每个文件创建监控线程
     for file in files {
         // clone() 创建发送端的副本
         // 每个生产者线程拥有自己的发送端
         let tx = tx.clone();
-        let pattern = pattern.clone();
+        let filter = Arc::clone(&filter);
 
         thread::spawn(move || {
-            watch_file(&file, &pattern, tx);
+            watch_file(&file, &filter, tx);
         });
     }
 
     // 重要：关闭原始发送端
     // 当所有发送端（包括克隆的）都关闭时，接收端的迭代才会结束
     drop(tx);
+    rx
+}
 
-    // 统计匹配数
-    let mut match_count = 0;
+/// 把参数中的目录展开成其中的 *.log 文件，普通文件原样保留
+///
+/// 同一目录下的文件按名称排序，输出顺序可复现
+fn expand_paths(paths: &[String], recursive: bool) -> Vec<String> {
+    let mut files = Vec::new();
+    for path in paths {
+        let dir = Path::new(path);
+        if !dir.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
 
-    // 接收并打印匹配的日志
-    // rx 实现了 IntoIterator，可以直接 for 循环
-    // 当所有发送端关闭时，迭代自动结束
-    for entry in rx {
-        println!(
-            "[{} L{}] {}",
-            entry.file, entry.line_num, entry.line
-        );
-        match_count += 1;
+        let mut found = Vec::new();
+        if let Err(e) = find_logs(dir, recursive, &mut found) {
+            eprintln!("无法读取目录 {}: {}", path, e);
+            continue;
+        }
+        if found.is_empty() {
+            eprintln!("目录 {} 中没有 .log 文件", path);
+        }
+        files.extend(found.iter().map(|p| p.to_string_lossy().into_owned()));
+    }
+    files
+}
+
+/// 收集 dir 中扩展名为 .log 的文件，recursive 时进入子目录
+fn find_logs(dir: &Path, recursive: bool, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            // 子目录读取失败不影响其他文件
+            if recursive {
+                if let Err(e) = find_logs(&path, recursive, found) {
+                    eprintln!("无法读取目录 {}: {}", path.display(), e);
+                }
+            }
+        } else if path.extension().is_some_and(|ext| ext == "log") {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 格式化一条匹配结果
+///
+/// - 普通模式: `[文件 L行号] 内容`
+/// - JSON 模式: `{"file":...,"line":...,"line_num":...}`，引号等特殊字符由 serde_json 转义
+fn format_entry(entry: &LogEntry, json: bool) -> String {
+    if json {
+        // LogEntry 只包含字符串和整数，序列化不会失败
+        serde_json::to_string(entry).expect("LogEntry 序列化失败")
+    } else {
+        format!("[{} L{}] {}", entry.file, entry.line_num, entry.line)
     }
+}
+
+/// 一行是否包含任意一个模式
+fn matches_any(line: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|p| line.contains(p.as_str()))
+}
 
-    println!("\n监控结束，共匹配 {} 条", match_count);
+impl LineFilter {
+    /// 先按时间过滤，再匹配模式
+    fn matches(&self, line: &str) -> bool {
+        if let Some(since) = &self.since {
+            if !since.is_recent(line) {
+                return false;
+            }
+        }
+        matches_any(line, &self.patterns)
+    }
+}
+
+impl TimeFilter {
+    /// 行内时间戳不早于 since 时返回 true
+    ///
+    /// ISO 时间的字典序就是时间顺序，直接比较字符串即可；
+    /// 日期和时间之间的 'T' 统一换成空格，两种写法可以混用
+    fn is_recent(&self, line: &str) -> bool {
+        let captures = match self.regex.captures(line) {
+            Some(c) => c,
+            None => return self.keep_untimed,
+        };
+        let timestamp = captures.get(1).unwrap_or_else(|| captures.get(0).unwrap());
+
+        normalize_time(timestamp.as_str()) >= normalize_time(&self.since)
+    }
+}
+
+fn normalize_time(s: &str) -> String {
+    s.replacen('T', " ", 1)
+}
+
+/// --since 的值是否是 ISO 时间的前缀：至少有日期，后面的时分秒可以省略
+///
+/// 比较靠的是字典序，写成 2024/06/01 或 6-1 这样的格式不会报错，
+/// 只会悄悄过滤错行，所以解析参数时就要拒绝
+fn is_iso_prefix(s: &str) -> bool {
+    const TEMPLATE: &[u8] = b"0000-00-00T00:00:00";
+    s.len() >= "0000-00-00".len()
+        && s.len() <= TEMPLATE.len()
+        && s.bytes().zip(TEMPLATE).all(|(c, &t)| match t {
+            b'0' => c.is_ascii_digit(),
+            b'T' => c == b'T' || c == b' ',
+            _ => c == t,
+        })
+}
+
+/// gzip 文件开头固定的两个字节
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 打开日志文件，.gz 文件返回解压后的内容
+///
+/// Box<dyn BufRead> 让两种读取器有相同的类型，调用方不需要关心文件是否压缩。
+/// .gz 文件先检查开头的魔数：扩展名是 .gz 但内容是纯文本时直接报错，
+/// 而不是交给解压器产生一堆乱码或难懂的错误
+fn open_log(path: &str) -> io::Result<Box<dyn BufRead>> {
+    let mut file = File::open(path)?;
+
+    if Path::new(path).extension().is_some_and(|ext| ext == "gz") {
+        let mut magic = [0u8; 2];
+        let is_gzip = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+        if !is_gzip {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "文件扩展名是 .gz，但内容不是 gzip 格式",
+            ));
+        }
+        // 魔数已经被读走，回到文件开头再交给解压器
+        file.seek(SeekFrom::Start(0))?;
+        return Ok(Box::new(BufReader::new(GzDecoder::new(file))));
+    }
+
+    Ok(Box::new(BufReader::new(file)))
 }
 
 /// 监控单个文件
-fn watch_file(path: &str, pattern: &str, tx: mpsc::Sender<LogEntry>) {
-    let file = match File::open(path) {
-        Ok(f) => f,
+fn watch_file(path: &str, filter: &LineFilter, tx: mpsc::Sender<LogEntry>) {
+    let reader = match open_log(path) {
+        Ok(r) => r,
         Err(e) => {
             eprintln!("无法打开文件 {}: {}", path, e);
             return;
         }
     };
 
-    let reader = BufReader::new(file);
-
     for (line_num, line) in reader.lines().enumerate() {
         let line = match line {
             Ok(l) => l,
-            Err(_) => continue,
+            // 不是合法 UTF-8 的行跳过
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => continue,
+            // 其他错误（如压缩数据损坏）继续读也没有意义
+            Err(e) => {
+                eprintln!("读取 {} 失败: {}", path, e);
+                break;
+            }
         };
 
         // 检查是否匹配模式
-        if line.contains(pattern) {
+        if filter.matches(&line) {
             let entry = LogEntry {
                 file: path.to_string(),
                 line,
@@ -113,14 +439,44 @@ fn watch_file(path: &str, pattern: &str, tx: mpsc::Sender<LogEntry>) {
 }
 
 /// 解析命令行参数
-fn parse_args(args: &[String]) -> Option<(Vec<String>, String)> {
+fn parse_args(args: &[String]) -> Option<Config> {
     let mut files = Vec::new();
-    let mut pattern = None;
+    let mut patterns = Vec::new();
+    let mut json = false;
+    let mut recursive = false;
+    let mut since = None;
+    let mut time_regex = DEFAULT_TIME_REGEX.to_string();
+    let mut keep_untimed = true;
+    let mut alert_after = None;
+    let mut count_only = false;
 
     let mut i = 0;
     while i < args.len() {
         if args[i] == "--pattern" && i + 1 < args.len() {
-            pattern = Some(args[i + 1].clone());
+            // --pattern 可重复出现，全部收集起来
+            patterns.push(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--json" {
+            json = true;
+            i += 1;
+        } else if args[i] == "--recursive" {
+            recursive = true;
+            i += 1;
+        } else if args[i] == "--since" && i + 1 < args.len() {
+            since = Some(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--time-regex" && i + 1 < args.len() {
+            time_regex = args[i + 1].clone();
+            i += 2;
+        } else if args[i] == "--drop-untimed" {
+            keep_untimed = false;
+            i += 1;
+        } else if args[i] == "--count-only" {
+            count_only = true;
+            i += 1;
+        } else if args[i] == "--alert-after" && i + 1 < args.len() {
+            // 不是数字时打印用法
+            alert_after = Some(args[i + 1].parse().ok()?);
             i += 2;
         } else {
             files.push(args[i].clone());
@@ -128,5 +484,400 @@ fn parse_args(args: &[String]) -> Option<(Vec<String>, String)> {
         }
     }
 
-    Some((files, pattern?))
+    // 至少需要一个模式
+    if patterns.is_empty() {
+        return None;
+    }
+
+    // 正则写错、--since 不是 ISO 时间时同样打印用法
+    let since = match since {
+        Some(since) if !is_iso_prefix(&since) => return None,
+        Some(since) => Some(TimeFilter {
+            since,
+            regex: Regex::new(&time_regex).ok()?,
+            keep_untimed,
+        }),
+        None => None,
+    };
+
+    Some(Config {
+        files,
+        recursive,
+        filter: LineFilter { patterns, since },
+        json,
+        alert_after,
+        count_only,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str) -> LogEntry {
+        LogEntry {
+            file: "app.log".to_string(),
+            line: line.to_string(),
+            line_num: 42,
+        }
+    }
+
+    #[test]
+    fn test_json_entry_fields() {
+        let output = format_entry(&entry("ERROR disk full"), true);
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(value["file"], "app.log");
+        assert_eq!(value["line_num"], 42);
+        assert_eq!(value["line"], "ERROR disk full");
+    }
+
+    #[test]
+    fn test_json_escapes_quotes() {
+        let output = format_entry(&entry(r#"ERROR user "bob" not found"#), true);
+
+        assert!(output.contains(r#"\"bob\""#));
+        assert!(!output.contains('\n'));
+
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["line"], r#"ERROR user "bob" not found"#);
+    }
+
+    #[test]
+    fn test_plain_format() {
+        let output = format_entry(&entry("ERROR disk full"), false);
+        assert_eq!(output, "[app.log L42] ERROR disk full");
+    }
+
+    #[test]
+    fn test_parse_json_flag() {
+        let args: Vec<String> = ["app.log", "--pattern", "ERROR", "--json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = parse_args(&args).unwrap();
+
+        assert_eq!(config.files, vec!["app.log"]);
+        assert_eq!(config.filter.patterns, vec!["ERROR"]);
+        assert!(config.json);
+    }
+
+    #[test]
+    fn test_parse_multiple_patterns() {
+        let args: Vec<String> = ["--pattern", "ERROR", "app.log", "--pattern", "WARN"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = parse_args(&args).unwrap();
+
+        assert_eq!(config.files, vec!["app.log"]);
+        assert_eq!(config.filter.patterns, vec!["ERROR", "WARN"]);
+
+        // 没有 --pattern 时打印用法
+        let args = vec!["app.log".to_string()];
+        assert!(parse_args(&args).is_none());
+    }
+
+    #[test]
+    fn test_matches_any_pattern() {
+        let patterns = vec!["ERROR".to_string(), "WARN".to_string()];
+
+        assert!(matches_any("ERROR disk full", &patterns));
+        assert!(matches_any("WARN low memory", &patterns));
+        assert!(!matches_any("INFO started", &patterns));
+        assert!(!matches_any("INFO started", &[]));
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_since_cutoff() {
+        let config = parse_args(&args(&[
+            "app.log",
+            "--pattern",
+            "ERROR",
+            "--since",
+            "2024-06-01T12:00:00",
+        ]))
+        .unwrap();
+        let filter = &config.filter;
+
+        assert!(!filter.matches("2024-06-01T11:59:59 ERROR too early"));
+        assert!(filter.matches("2024-06-01T12:00:00 ERROR exactly at cutoff"));
+        assert!(filter.matches("2024-06-01 12:30:00 ERROR space separator"));
+        assert!(filter.matches("2024-06-02T00:00:00 ERROR next day"));
+        // 时间满足但模式不匹配
+        assert!(!filter.matches("2024-06-02T00:00:00 INFO ok"));
+        // 默认保留没有时间戳的行
+        assert!(filter.matches("ERROR no timestamp"));
+    }
+
+    #[test]
+    fn test_custom_time_regex_and_drop_untimed() {
+        let config = parse_args(&args(&[
+            "app.log",
+            "--pattern",
+            "ERROR",
+            "--since",
+            "2024-06-01",
+            "--time-regex",
+            r"^\[(\d{4}-\d{2}-\d{2})\]",
+            "--drop-untimed",
+        ]))
+        .unwrap();
+        let filter = &config.filter;
+
+        assert!(!filter.matches("[2024-05-31] ERROR old"));
+        assert!(filter.matches("[2024-06-01] ERROR new"));
+        assert!(!filter.matches("ERROR no timestamp"));
+    }
+
+    #[test]
+    fn test_since_must_be_iso() {
+        let parse = |since: &str| parse_args(&args(&["--pattern", "ERROR", "--since", since]));
+
+        assert!(parse("2024-06-01").is_some());
+        assert!(parse("2024-06-01 12:00").is_some());
+        assert!(parse("2024-06-01T12:00:00").is_some());
+
+        assert!(parse("2024/06/01").is_none());
+        assert!(parse("2024-6-1").is_none());
+        assert!(parse("yesterday").is_none());
+        assert!(parse("2024-06-01T12:00:00Z").is_none());
+    }
+
+    #[test]
+    fn test_invalid_time_regex() {
+        let result = parse_args(&args(&[
+            "--pattern",
+            "ERROR",
+            "--since",
+            "2024-06-01",
+            "--time-regex",
+            "(",
+        ]));
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_alert_fires_once_per_file() {
+        let mut tracker = AlertTracker::new(2);
+        let files = [
+            "app.log", "web.log", "app.log", "app.log", "web.log", "app.log", "web.log",
+        ];
+
+        let alerts: Vec<(usize, String)> = files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| tracker.record(file).map(|alert| (i, alert)))
+            .collect();
+
+        // app.log 在第 3 条匹配（下标 3）时越过阈值，第 4 条不再告警；
+        // web.log 独立计数，在下标 6 越过阈值
+        assert_eq!(
+            alerts,
+            vec![
+                (3, "!! app.log 的匹配超过 2 条".to_string()),
+                (6, "!! web.log 的匹配超过 2 条".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_alert_after() {
+        let config = parse_args(&args(&[
+            "app.log",
+            "--pattern",
+            "ERROR",
+            "--alert-after",
+            "100",
+        ]))
+        .unwrap();
+        assert_eq!(config.alert_after, Some(100));
+
+        let config = parse_args(&args(&["app.log", "--pattern", "ERROR"])).unwrap();
+        assert_eq!(config.alert_after, None);
+
+        assert!(parse_args(&args(&["--pattern", "ERROR", "--alert-after", "many"])).is_none());
+    }
+
+    #[test]
+    fn test_scan_gzip_log() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log.1.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder
+            .write_all(b"INFO started\nERROR disk full\nINFO ok\nERROR timeout\n")
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let filter = LineFilter {
+            patterns: vec!["ERROR".to_string()],
+            since: None,
+        };
+        let (tx, rx) = mpsc::channel();
+        watch_file(path.to_str().unwrap(), &filter, tx);
+
+        let found: Vec<(usize, String)> = rx.iter().map(|e| (e.line_num, e.line)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, "ERROR disk full".to_string()),
+                (4, "ERROR timeout".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plaintext_with_gz_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fake.gz");
+        std::fs::write(&path, "ERROR not really compressed\n").unwrap();
+
+        let err = open_log(path.to_str().unwrap()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("gzip"));
+
+        // 普通扩展名的文件照常按文本读取
+        let plain = dir.path().join("app.log");
+        std::fs::write(&plain, "ERROR plain\n").unwrap();
+        let lines: Vec<String> = open_log(plain.to_str().unwrap())
+            .unwrap()
+            .lines()
+            .map(|l| l.unwrap())
+            .collect();
+        assert_eq!(lines, vec!["ERROR plain"]);
+    }
+
+    fn log_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.log"), "INFO start\nERROR app failed\n").unwrap();
+        std::fs::write(dir.path().join("web.log"), "ERROR web failed\nINFO ok\n").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ERROR not a log\n").unwrap();
+        std::fs::create_dir(dir.path().join("old")).unwrap();
+        std::fs::write(dir.path().join("old").join("db.log"), "ERROR db failed\n").unwrap();
+        dir
+    }
+
+    /// 相对于 dir 的路径，方便比较
+    fn names(dir: &Path, files: &[String]) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| {
+                Path::new(f)
+                    .strip_prefix(dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_directory_spawns_watcher_per_log() {
+        let dir = log_dir();
+        let files = expand_paths(&[dir.path().to_string_lossy().into_owned()], false);
+        assert_eq!(names(dir.path(), &files), vec!["app.log", "web.log"]);
+
+        let filter = LineFilter {
+            patterns: vec!["ERROR".to_string()],
+            since: None,
+        };
+        let rx = spawn_watchers(files, Arc::new(filter));
+
+        // 两个线程的输出顺序不固定，排序后比较
+        let mut found: Vec<String> = rx.iter().map(|e| e.line).collect();
+        found.sort();
+        assert_eq!(found, vec!["ERROR app failed", "ERROR web failed"]);
+    }
+
+    #[test]
+    fn test_recursive_directory() {
+        let dir = log_dir();
+        let files = expand_paths(&[dir.path().to_string_lossy().into_owned()], true);
+        assert_eq!(
+            names(dir.path(), &files),
+            vec!["app.log", "old/db.log", "web.log"]
+        );
+    }
+
+    #[test]
+    fn test_directory_without_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("readme.md"), "").unwrap();
+
+        let files = expand_paths(&[dir.path().to_string_lossy().into_owned()], true);
+        assert!(files.is_empty());
+
+        // 不是目录的参数原样保留，打开失败由 watch_file 报告
+        let files = expand_paths(&args(&["missing.log"]), false);
+        assert_eq!(files, vec!["missing.log"]);
+    }
+
+    #[test]
+    fn test_count_only_suppresses_lines() {
+        let config = parse_args(&args(&[
+            "app.log",
+            "web.log",
+            "--pattern",
+            "ERROR",
+            "--count-only",
+        ]))
+        .unwrap();
+        assert!(config.count_only);
+
+        let entries = ["app.log", "web.log", "app.log"].map(|file| LogEntry {
+            file: file.to_string(),
+            ..entry("ERROR boom")
+        });
+        let files = args(&["app.log", "web.log", "db.log"]);
+        let mut counts = MatchCounts::new(&files);
+        let mut out = Vec::new();
+        receive_matches(
+            entries,
+            config.json,
+            config.count_only,
+            &mut counts,
+            None,
+            &mut out,
+        );
+
+        // 没有逐行输出，只有统计；没有匹配的文件记为 0
+        assert!(out.is_empty());
+        assert_eq!(counts.total, 3);
+        assert_eq!(counts.format(false), "app.log:2\ndb.log:0\nweb.log:1\n");
+        assert_eq!(
+            counts.format(true).lines().next(),
+            Some(r#"{"count":2,"file":"app.log"}"#)
+        );
+    }
+
+    #[test]
+    fn test_lines_printed_without_count_only() {
+        let config = parse_args(&args(&["app.log", "--pattern", "ERROR"])).unwrap();
+        assert!(!config.count_only);
+
+        let mut counts = MatchCounts::new(&args(&["app.log"]));
+        let mut out = Vec::new();
+        receive_matches(
+            [entry("ERROR boom")],
+            config.json,
+            config.count_only,
+            &mut counts,
+            None,
+            &mut out,
+        );
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[app.log L42] ERROR boom\n"
+        );
+        assert_eq!(counts.total, 1);
+    }
 }
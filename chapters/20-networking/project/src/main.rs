@@ -1,20 +1,62 @@
 // kv-server: 简单的键值存储服务器（单线程版）
-// 用法: kv-server [--port PORT]
+// 用法: kv-server [--port PORT] [--max-line BYTES] [--init FILE] [--password PASSWORD]
 //
 // 协议:
+//   连接建立后服务器先发送问候行 KV 1.0 READY\n，之后才开始读取命令
+//
 //   SET key value\n  -> OK\n
 //   GET key\n        -> VALUE value\n 或 NOT_FOUND\n
-//   DEL key\n        -> OK\n
+//   MGET k1 k2 ...\n -> 每个键一行 VALUE value 或 NOT_FOUND
+//   DEL k1 k2 ...\n  -> DELETED n\n（n 为实际删除的键个数）
+//   FLUSHDB\n        -> OK\n（清空所有键）
 //   KEYS\n           -> KEYS key1 key2 ...\n
+//   HELP\n           -> HELP 命令1 | 命令2 | ...\n
+//   AUTH password\n  -> OK\n 或 ERROR invalid password\n
+//   VERSION\n        -> VERSION 1.0\n
 //   QUIT\n           -> 关闭连接
+//
+// 命令名不区分大小写（set / SET / Set 都可以）
+//
+// 超过 --max-line 字节（默认 64KB）的命令行会被丢弃，返回 ERROR line too long\n，
+// 连接保持可用
+//
+// --init FILE 在开始监听前逐行执行 FILE 中的命令，用来预置数据；
+// 空行和 # 开头的行会被跳过，某条命令出错只打印警告，不影响启动
+//
+// 设置了 --password 时，每个连接必须先发送 AUTH password，
+// 在此之前除 VERSION 和 QUIT 以外的命令都返回 NOAUTH Authentication required\n
 
 use std::collections::HashMap;
 use std::env;
-use std::io::{BufRead, BufReader, Write};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
 use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+
+/// 支持的命令及语法，HELP 和启动提示都从这里生成
+const COMMANDS: &[&str] = &[
+    "SET key value",
+    "GET key",
+    "MGET key...",
+    "DEL key...",
+    "FLUSHDB",
+    "KEYS",
+    "HELP",
+    "AUTH password",
+    "VERSION",
+    "QUIT",
+];
+
+/// 协议版本，出现在问候行和 VERSION 的响应中
+const PROTOCOL_VERSION: &str = "1.0";
+
+/// 单行命令的默认长度上限
+const DEFAULT_MAX_LINE: usize = 64 * 1024;
 
 fn main() {
     let port = parse_port();
+    let max_line = parse_max_line();
+    let password = parse_option::<String>("--password");
     let addr = format!("127.0.0.1:{}", port);
 
     // TcpListener::bind 绑定到指定地址
@@ -28,11 +70,26 @@ fn main() {
     };
 
     println!("kv-server 启动，监听 {}", addr);
-    println!("支持命令: SET key value | GET key | DEL key | KEYS | QUIT");
+    println!("支持命令: {}", COMMANDS.join(" | "));
+    if password.is_some() {
+        println!("已启用密码认证，连接后需先发送 AUTH <密码>");
+    }
 
     // 存储使用 HashMap
     let mut store: HashMap<String, String> = HashMap::new();
 
+    // 先执行初始化脚本，再接受连接，客户端看到的就是预置好的数据
+    if let Some(path) = parse_option::<String>("--init") {
+        let result = File::open(&path).and_then(|f| run_script(BufReader::new(f), &mut store));
+        match result {
+            Ok(count) => println!("初始化脚本 {} 执行完毕，共 {} 条命令", path, count),
+            Err(e) => {
+                eprintln!("无法读取初始化脚本 {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     // listener.incoming() 返回连接迭代器
     // 每次迭代返回 Result<TcpStream>
     for stream in listener.incoming() {
@@ -41,7 +98,7 @@ fn main() {
                 let peer = stream.peer_addr().ok();
                 println!("\n客户端连接: {:?}", peer);
 
-                handle_client(stream, &mut store);
+                handle_client(stream, &mut store, max_line, password.as_deref());
 
                 println!("客户端断开: {:?}", peer);
             }
@@ -52,8 +109,34 @@ fn main() {
     }
 }
 
+/// 单个连接的状态
+struct Session<'a> {
+    /// 服务器密码，None 表示不需要认证
+    password: Option<&'a str>,
+    /// 是否已经通过 AUTH
+    authenticated: bool,
+}
+
+impl<'a> Session<'a> {
+    /// 没有设置密码时，连接一开始就是已认证状态
+    fn new(password: Option<&'a str>) -> Self {
+        Session {
+            password,
+            authenticated: password.is_none(),
+        }
+    }
+}
+
 /// 处理单个客户端连接
-fn handle_client(stream: TcpStream, store: &mut HashMap<String, String>) {
+fn handle_client(
+    stream: TcpStream,
+    store: &mut HashMap<String, String>,
+    max_line: usize,
+    password: Option<&str>,
+) {
+    // 认证状态属于连接：每个新连接都要重新 AUTH
+    let mut session = Session::new(password);
+
     // try_clone() 创建一个独立的句柄
     // 这样读和写可以使用不同的句柄，避免借用冲突
     let mut writer = match stream.try_clone() {
@@ -61,13 +144,29 @@ fn handle_client(stream: TcpStream, store: &mut HashMap<String, String>) {
         Err(_) => return,
     };
 
-    // BufReader 包装原始 stream 用于读取
-    let reader = BufReader::new(stream);
+    // 先发问候行再读命令，客户端据此确认连上的是 kv-server 以及协议版本。
+    // 问候行只有十几个字节，一定能放进内核的发送缓冲区，write_all 会立即返回；
+    // 即使客户端连接后先发命令、不读问候行，也不会互相等待而卡住
+    if writer.write_all(banner().as_bytes()).is_err() {
+        return;
+    }
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => break,
+    // BufReader 包装原始 stream 用于读取
+    let mut reader = BufReader::new(stream);
+
+    // 不用 reader.lines()：它会为一行分配任意大的内存，
+    // 客户端只要一直不发换行符就能把服务器内存耗尽
+    loop {
+        let line = match read_line_limited(&mut reader, max_line) {
+            Ok(LineRead::Line(l)) => l,
+            Ok(LineRead::TooLong) => {
+                println!("  收到超长命令，已丢弃");
+                if writer.write_all(b"ERROR line too long\n").is_err() {
+                    break;
+                }
+                continue;
+            }
+            Ok(LineRead::Eof) | Err(_) => break,
         };
 
         if line.is_empty() {
@@ -77,7 +176,7 @@ fn handle_client(stream: TcpStream, store: &mut HashMap<String, String>) {
         println!("  收到: {}", line);
 
         // 解析并执行命令
-        let response = execute_command(&line, store);
+        let response = execute_in_session(&line, store, &mut session);
 
         println!("  响应: {}", response.trim());
 
@@ -93,33 +192,148 @@ fn handle_client(stream: TcpStream, store: &mut HashMap<String, String>) {
     }
 }
 
+/// 连接建立时发送的问候行
+fn banner() -> String {
+    format!("KV {} READY\n", PROTOCOL_VERSION)
+}
+
+/// 一次有长度限制的按行读取的结果
+#[derive(Debug, PartialEq)]
+enum LineRead {
+    /// 完整的一行（不含行尾的 \n 或 \r\n）
+    Line(String),
+    /// 该行超过长度上限，内容已被丢弃
+    TooLong,
+    /// 连接已关闭
+    Eof,
+}
+
+/// 读取一行，最多保留 `max_len` 字节
+///
+/// 超长的行会继续读到换行符为止但不保存，
+/// 这样下一次调用仍然从新的一行开始，连接可以继续使用
+fn read_line_limited<R: BufRead>(reader: &mut R, max_len: usize) -> io::Result<LineRead> {
+    let mut buf = Vec::new();
+    let mut too_long = false;
+
+    loop {
+        // fill_buf 返回缓冲区里已有的数据，不会额外分配
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            // 对端关闭：没读到任何内容才算 EOF，否则把最后一行交给调用方
+            if buf.is_empty() && !too_long {
+                return Ok(LineRead::Eof);
+            }
+            break;
+        }
+
+        let newline = available.iter().position(|&b| b == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+
+        if !too_long {
+            if buf.len() + chunk.len() > max_len {
+                too_long = true;
+                buf = Vec::new();
+            } else {
+                buf.extend_from_slice(chunk);
+            }
+        }
+
+        // 换行符本身也要消费掉
+        let used = newline.map_or(available.len(), |i| i + 1);
+        reader.consume(used);
+
+        if newline.is_some() {
+            break;
+        }
+    }
+
+    if too_long {
+        return Ok(LineRead::TooLong);
+    }
+
+    if buf.last() == Some(&b'\r') {
+        buf.pop();
+    }
+    String::from_utf8(buf)
+        .map(LineRead::Line)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// 在连接的上下文中执行命令：处理 AUTH，未认证时拦截其他命令
+///
+/// 认证只和连接有关，所以放在这一层；execute_command 只负责操作数据，
+/// --init 脚本也直接调用它，不需要认证
+fn execute_in_session(
+    line: &str,
+    store: &mut HashMap<String, String>,
+    session: &mut Session,
+) -> String {
+    let mut parts = line.splitn(2, ' ');
+    let verb = parts.next().unwrap_or("").to_uppercase();
+
+    match verb.as_str() {
+        "AUTH" => {
+            let given = parts.next().unwrap_or("");
+            match session.password {
+                None => "ERROR no password is set\n".to_string(),
+                Some(password) if given == password => {
+                    session.authenticated = true;
+                    "OK\n".to_string()
+                }
+                // 密码错误不改变当前状态
+                Some(_) => "ERROR invalid password\n".to_string(),
+            }
+        }
+        // 未认证也允许 VERSION 和 QUIT：客户端可以先确认协议版本，也可以正常断开
+        "VERSION" | "QUIT" => execute_command(line, store),
+        _ if !session.authenticated => "NOAUTH Authentication required\n".to_string(),
+        _ => execute_command(line, store),
+    }
+}
+
 /// 执行命令并返回响应
 fn execute_command(line: &str, store: &mut HashMap<String, String>) -> String {
     // splitn(3, ' ') 最多分割成 3 部分
     // 这样 value 可以包含空格
     let parts: Vec<&str> = line.splitn(3, ' ').collect();
 
-    match parts.as_slice() {
+    // 命令名统一转成大写后再匹配，每个命令只需要写一个分支
+    let verb = parts[0].to_uppercase();
+
+    match (verb.as_str(), &parts[1..]) {
         // SET key value
-        ["SET", key, value] | ["set", key, value] => {
+        ("SET", [key, value]) => {
             store.insert(key.to_string(), value.to_string());
             "OK\n".to_string()
         }
 
         // GET key
-        ["GET", key] | ["get", key] => match store.get(*key) {
+        ("GET", [key]) => match store.get(*key) {
             Some(value) => format!("VALUE {}\n", value),
             None => "NOT_FOUND\n".to_string(),
         },
 
-        // DEL key
-        ["DEL", key] | ["del", key] => {
-            store.remove(*key);
+        // MGET 的键个数不固定，不能用上面的 splitn(3, ' ')，需要完整切分
+        ("MGET", _) => {
+            let keys: Vec<&str> = line.split_whitespace().skip(1).collect();
+            execute_mget(&keys, store)
+        }
+
+        // DEL 和 MGET 一样，键个数不固定，需要完整切分
+        ("DEL", _) => {
+            let keys: Vec<&str> = line.split_whitespace().skip(1).collect();
+            execute_del(&keys, store)
+        }
+
+        // FLUSHDB - 清空所有键
+        ("FLUSHDB", []) => {
+            store.clear();
             "OK\n".to_string()
         }
 
         // KEYS - 列出所有键
-        ["KEYS"] | ["keys"] => {
+        ("KEYS", []) => {
             let keys: Vec<&String> = store.keys().collect();
             if keys.is_empty() {
                 "KEYS (empty)\n".to_string()
@@ -134,27 +348,93 @@ fn execute_command(line: &str, store: &mut HashMap<String, String>) -> String {
             }
         }
 
+        // HELP - 列出所有命令
+        ("HELP", []) => format!("HELP {}\n", COMMANDS.join(" | ")),
+
+        // VERSION - 返回协议版本
+        ("VERSION", []) => format!("VERSION {}\n", PROTOCOL_VERSION),
+
         // QUIT
-        ["QUIT"] | ["quit"] => "BYE\n".to_string(),
+        ("QUIT", []) => "BYE\n".to_string(),
 
         // 未知命令
         _ => "ERROR unknown command\n".to_string(),
     }
 }
 
-/// 解析端口参数
-fn parse_port() -> u16 {
-    let args: Vec<String> = env::args().collect();
+/// MGET: 按顺序返回每个键的值，每个键占一行
+fn execute_mget(keys: &[&str], store: &HashMap<String, String>) -> String {
+    if keys.is_empty() {
+        return "ERROR MGET requires at least one key\n".to_string();
+    }
 
-    for i in 0..args.len() {
-        if args[i] == "--port" && i + 1 < args.len() {
-            if let Ok(port) = args[i + 1].parse() {
-                return port;
-            }
+    keys.iter()
+        .map(|key| match store.get(*key) {
+            Some(value) => format!("VALUE {}\n", value),
+            None => "NOT_FOUND\n".to_string(),
+        })
+        .collect()
+}
+
+/// DEL: 删除所有给出的键，返回实际删除的个数（不存在的键不计数）
+fn execute_del(keys: &[&str], store: &mut HashMap<String, String>) -> String {
+    if keys.is_empty() {
+        return "ERROR DEL requires at least one key\n".to_string();
+    }
+
+    let deleted = keys
+        .iter()
+        .filter(|key| store.remove(**key).is_some())
+        .count();
+    format!("DELETED {}\n", deleted)
+}
+
+/// 逐行执行脚本中的命令，返回执行的命令条数
+///
+/// 空行和 # 开头的注释行会被跳过；返回 ERROR 的命令只打印警告并继续，
+/// 只有读取脚本本身失败时才返回 Err
+fn run_script<R: BufRead>(reader: R, store: &mut HashMap<String, String>) -> io::Result<usize> {
+    let mut count = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
         }
+
+        let response = execute_command(line, store);
+        if response.starts_with("ERROR") {
+            eprintln!(
+                "警告: 初始化脚本第 {} 行 `{}` 执行失败: {}",
+                index + 1,
+                line,
+                response.trim()
+            );
+        }
+        count += 1;
     }
 
-    7878 // 默认端口
+    Ok(count)
+}
+
+/// 解析端口参数
+fn parse_port() -> u16 {
+    parse_option("--port").unwrap_or(7878) // 默认端口
+}
+
+/// 解析单行长度上限
+fn parse_max_line() -> usize {
+    parse_option("--max-line").unwrap_or(DEFAULT_MAX_LINE)
+}
+
+/// 查找 `name VALUE` 形式的参数并解析 VALUE
+fn parse_option<T: FromStr>(name: &str) -> Option<T> {
+    let args: Vec<String> = env::args().collect();
+
+    args.windows(2)
+        .find(|pair| pair[0] == name)
+        .and_then(|pair| pair[1].parse().ok())
 }
 
 #[cfg(test)]
@@ -186,12 +466,43 @@ mod tests {
         store.insert("key".to_string(), "value".to_string());
 
         let response = execute_command("DEL key", &mut store);
-        assert_eq!(response, "OK\n");
+        assert_eq!(response, "DELETED 1\n");
 
         let response = execute_command("GET key", &mut store);
         assert_eq!(response, "NOT_FOUND\n");
     }
 
+    #[test]
+    fn test_del_many_counts_removed() {
+        let mut store = HashMap::new();
+        execute_command("SET a 1", &mut store);
+        execute_command("SET b 2", &mut store);
+        execute_command("SET c 3", &mut store);
+
+        // missing 不存在，重复的 a 只删除一次
+        let response = execute_command("DEL a b missing a", &mut store);
+        assert_eq!(response, "DELETED 2\n");
+        assert_eq!(execute_command("GET c", &mut store), "VALUE 3\n");
+        assert_eq!(store.len(), 1);
+
+        assert_eq!(execute_command("DEL missing", &mut store), "DELETED 0\n");
+        assert!(execute_command("DEL", &mut store).starts_with("ERROR"));
+    }
+
+    #[test]
+    fn test_flushdb() {
+        let mut store = HashMap::new();
+        execute_command("SET a 1", &mut store);
+        execute_command("SET b 2", &mut store);
+
+        assert_eq!(execute_command("flushdb", &mut store), "OK\n");
+        assert!(store.is_empty());
+        assert_eq!(execute_command("KEYS", &mut store), "KEYS (empty)\n");
+
+        // FLUSHDB 不接受参数
+        assert!(execute_command("FLUSHDB now", &mut store).starts_with("ERROR"));
+    }
+
     #[test]
     fn test_value_with_spaces() {
         let mut store = HashMap::new();
@@ -202,4 +513,254 @@ mod tests {
         let response = execute_command("GET msg", &mut store);
         assert_eq!(response, "VALUE Hello World\n");
     }
+
+    #[test]
+    fn test_mget_mixed() {
+        let mut store = HashMap::new();
+        execute_command("SET a 1", &mut store);
+        execute_command("SET c hello world", &mut store);
+
+        let response = execute_command("MGET a b c", &mut store);
+        assert_eq!(response, "VALUE 1\nNOT_FOUND\nVALUE hello world\n");
+
+        let response = execute_command("mget b", &mut store);
+        assert_eq!(response, "NOT_FOUND\n");
+    }
+
+    #[test]
+    fn test_read_line_limited_too_long() {
+        let long = "x".repeat(100);
+        let input = format!("SET a 1\r\nSET b {}\nGET a\n", long);
+        let mut reader = io::Cursor::new(input);
+
+        assert_eq!(
+            read_line_limited(&mut reader, 32).unwrap(),
+            LineRead::Line("SET a 1".to_string())
+        );
+        assert_eq!(
+            read_line_limited(&mut reader, 32).unwrap(),
+            LineRead::TooLong
+        );
+
+        // 超长行被整行丢弃，后面的命令不受影响
+        assert_eq!(
+            read_line_limited(&mut reader, 32).unwrap(),
+            LineRead::Line("GET a".to_string())
+        );
+        assert_eq!(read_line_limited(&mut reader, 32).unwrap(), LineRead::Eof);
+    }
+
+    #[test]
+    fn test_read_line_limited_small_buffer() {
+        // BufReader 容量比行还小，超长行跨越多次 fill_buf
+        let input = format!("{}\nKEYS", "y".repeat(50));
+        let mut reader = BufReader::with_capacity(8, input.as_bytes());
+
+        assert_eq!(
+            read_line_limited(&mut reader, 16).unwrap(),
+            LineRead::TooLong
+        );
+        // 最后一行没有换行符也能读到
+        assert_eq!(
+            read_line_limited(&mut reader, 16).unwrap(),
+            LineRead::Line("KEYS".to_string())
+        );
+        assert_eq!(read_line_limited(&mut reader, 16).unwrap(), LineRead::Eof);
+    }
+
+    #[test]
+    fn test_verb_case_insensitive() {
+        let mut store = HashMap::new();
+
+        assert_eq!(execute_command("set a 1", &mut store), "OK\n");
+        assert_eq!(execute_command("Get a", &mut store), "VALUE 1\n");
+        assert_eq!(execute_command("gEt a", &mut store), "VALUE 1\n");
+        assert_eq!(execute_command("MGet a", &mut store), "VALUE 1\n");
+        assert_eq!(execute_command("Keys", &mut store), "KEYS a\n");
+        assert_eq!(execute_command("Del a", &mut store), "DELETED 1\n");
+        assert_eq!(execute_command("quit", &mut store), "BYE\n");
+
+        // 只有命令名不区分大小写，键仍然区分
+        execute_command("SET Key v", &mut store);
+        assert_eq!(execute_command("GET key", &mut store), "NOT_FOUND\n");
+    }
+
+    #[test]
+    fn test_help_lists_all_commands() {
+        let mut store = HashMap::new();
+
+        let response = execute_command("help", &mut store);
+        assert!(response.starts_with("HELP "));
+        assert!(response.ends_with('\n'));
+        for name in [
+            "SET", "GET", "MGET", "DEL", "FLUSHDB", "KEYS", "HELP", "VERSION", "QUIT",
+        ] {
+            assert!(response.contains(name), "HELP 缺少 {}", name);
+        }
+    }
+
+    #[test]
+    fn test_mget_no_keys() {
+        let mut store = HashMap::new();
+
+        let response = execute_command("MGET", &mut store);
+        assert!(response.starts_with("ERROR"));
+    }
+
+    #[test]
+    fn test_run_script_seeds_store() {
+        let script = "\
+# 预置数据
+SET name Alice
+SET city New York
+
+BOGUS command
+SET lang rust
+DEL lang
+SET lang Rust
+";
+        let mut store = HashMap::new();
+        let count = run_script(script.as_bytes(), &mut store).unwrap();
+
+        // 注释和空行不计数，出错的命令也不会中断后续命令
+        assert_eq!(count, 6);
+        assert_eq!(execute_command("GET name", &mut store), "VALUE Alice\n");
+        assert_eq!(execute_command("GET city", &mut store), "VALUE New York\n");
+        assert_eq!(execute_command("GET lang", &mut store), "VALUE Rust\n");
+        assert_eq!(store.len(), 3);
+    }
+
+    #[test]
+    fn test_auth_required_before_commands() {
+        let mut store = HashMap::new();
+        let mut session = Session::new(Some("secret"));
+
+        assert_eq!(
+            execute_in_session("SET name Alice", &mut store, &mut session),
+            "NOAUTH Authentication required\n"
+        );
+        assert_eq!(
+            execute_in_session("GET name", &mut store, &mut session),
+            "NOAUTH Authentication required\n"
+        );
+        // 被拦截的命令没有执行
+        assert!(store.is_empty());
+
+        // 密码错误：返回错误，仍未认证
+        assert_eq!(
+            execute_in_session("AUTH wrong", &mut store, &mut session),
+            "ERROR invalid password\n"
+        );
+        assert!(!session.authenticated);
+        assert_eq!(
+            execute_in_session("KEYS", &mut store, &mut session),
+            "NOAUTH Authentication required\n"
+        );
+
+        // 密码正确后其他命令正常执行
+        assert_eq!(
+            execute_in_session("auth secret", &mut store, &mut session),
+            "OK\n"
+        );
+        assert_eq!(
+            execute_in_session("SET name Alice", &mut store, &mut session),
+            "OK\n"
+        );
+        assert_eq!(
+            execute_in_session("GET name", &mut store, &mut session),
+            "VALUE Alice\n"
+        );
+
+        // 已认证后再输错密码也不会被登出
+        execute_in_session("AUTH wrong", &mut store, &mut session);
+        assert!(session.authenticated);
+    }
+
+    #[test]
+    fn test_no_password_configured() {
+        let mut store = HashMap::new();
+        let mut session = Session::new(None);
+
+        // 没有设置密码时不需要认证，AUTH 返回错误
+        assert_eq!(
+            execute_in_session("SET a 1", &mut store, &mut session),
+            "OK\n"
+        );
+        assert_eq!(
+            execute_in_session("AUTH anything", &mut store, &mut session),
+            "ERROR no password is set\n"
+        );
+    }
+
+    #[test]
+    fn test_quit_allowed_without_auth() {
+        let mut store = HashMap::new();
+        let mut session = Session::new(Some("secret"));
+
+        assert_eq!(
+            execute_in_session("QUIT", &mut store, &mut session),
+            "BYE\n"
+        );
+    }
+
+    /// 启动只服务一个连接的服务器，返回连接到它的客户端
+    fn connect_test_server(password: Option<&'static str>) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut store = HashMap::new();
+            handle_client(stream, &mut store, DEFAULT_MAX_LINE, password);
+        });
+
+        let client = TcpStream::connect(addr).unwrap();
+        // 兜底：服务端没有响应时测试不会永远挂住
+        client
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        client
+    }
+
+    #[test]
+    fn test_banner_sent_on_connect() {
+        let client = connect_test_server(None);
+        let mut writer = client.try_clone().unwrap();
+        let mut reader = BufReader::new(client);
+
+        // 什么都不发送，第一行就是问候行
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "KV 1.0 READY\n");
+
+        writer.write_all(b"VERSION\n").unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "VERSION 1.0\n");
+    }
+
+    #[test]
+    fn test_write_before_reading_banner() {
+        let client = connect_test_server(None);
+        let mut writer = client.try_clone().unwrap();
+        let mut reader = BufReader::new(client);
+
+        // 先把命令都发出去再开始读，问候行仍然排在所有响应之前
+        writer.write_all(b"SET a 1\nGET a\nQUIT\n").unwrap();
+
+        let mut output = String::new();
+        io::Read::read_to_string(&mut reader, &mut output).unwrap();
+        assert_eq!(output, "KV 1.0 READY\nOK\nVALUE 1\nBYE\n");
+    }
+
+    #[test]
+    fn test_version_allowed_without_auth() {
+        let mut store = HashMap::new();
+        let mut session = Session::new(Some("secret"));
+
+        assert_eq!(
+            execute_in_session("version", &mut store, &mut session),
+            "VERSION 1.0\n"
+        );
+    }
 }
@@ -1,29 +1,283 @@
 // kv-server-mt: 多线程键值存储服务器
-// 用法: kv-server-mt [--port PORT] [--threads N]
+// 用法: kv-server-mt [--port PORT] [--threads N] [--idle-timeout SECS] [--max-session-secs SECS]
+//                     [--slow-ms MS] [--snapshot PATH]
+//
+// 参数既可以写成 `--port 8080`，也可以写成 `--port=8080`；
+// 值无法解析或出现未知参数时打印错误并退出，而不是悄悄使用默认值
 //
 // 特性:
 // - 线程池处理多个客户端
 // - RwLock 实现读写分离
 // - 支持并发访问
+// - LATENCY 命令报告锁等待时间
+// - DUMP / LOAD 命令以 JSON 备份和恢复全部数据
+// - EXPIRE 设置过期时间，后台清理线程定期删除过期的键
+// - SLOWLOG 查看执行时间超过 --slow-ms 的命令
+// - --snapshot 指定快照文件：启动时自动载入，运行期间后台线程定期保存
 
 mod thread_pool;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::env;
-use std::io::{BufRead, BufReader, Write};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
 use std::net::{TcpListener, TcpStream};
-use std::sync::{Arc, RwLock};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use thread_pool::ThreadPool;
 
-// Store 类型别名：原子引用计数 + 读写锁 + HashMap
+// Store 类型别名：原子引用计数 + 读写锁 + Keyspace
 // Arc: 允许多线程共享所有权
 // RwLock: 读操作可并发，写操作独占
-type Store = Arc<RwLock<HashMap<String, String>>>;
+type Store = Arc<RwLock<Keyspace>>;
+
+/// 后台清理线程的运行间隔
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 指定 --snapshot 时，后台线程每隔多久保存一次快照
+///
+/// 服务器目前没有关闭命令，只能被 Ctrl+C 直接结束，所以不能指望退出时再保存；
+/// 定期保存意味着进程被杀掉时最多丢失最近一个间隔内的写入
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 存储的全部内容
+///
+/// 过期时间和数据放在同一把锁后面，EXPIRE、DEL 等命令一次加锁就能同时更新两者
+#[derive(Default)]
+struct Keyspace {
+    data: HashMap<String, String>,
+    /// 设置了过期时间的键 -> 过期时刻
+    expires: HashMap<String, Instant>,
+}
+
+impl Keyspace {
+    fn is_expired(&self, key: &str, now: Instant) -> bool {
+        self.expires.get(key).is_some_and(|&t| t <= now)
+    }
+
+    /// 读取键，已过期的键视为不存在
+    ///
+    /// 读锁下不能删除，真正的删除交给后台清理线程
+    fn get(&self, key: &str) -> Option<&String> {
+        if self.is_expired(key, Instant::now()) {
+            None
+        } else {
+            self.data.get(key)
+        }
+    }
+
+    /// 删除所有已过期的键，返回删除的个数
+    ///
+    /// 只遍历 expires，没设置过期时间的键不用检查，持锁时间与数据总量无关
+    fn remove_expired(&mut self, now: Instant) -> usize {
+        let expired: Vec<String> = self
+            .expires
+            .iter()
+            .filter(|(_, &t)| t <= now)
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in &expired {
+            self.expires.remove(key);
+            self.data.remove(key);
+        }
+        expired.len()
+    }
+}
+
+/// 锁等待时间统计
+///
+/// 多个工作线程同时更新，所以全部使用原子类型，不需要再加一把锁
+#[derive(Default)]
+struct LockStats {
+    /// 获取锁的次数
+    samples: AtomicU64,
+    /// 累计等待时间（纳秒）
+    total_nanos: AtomicU64,
+    /// 最长一次等待时间（纳秒）
+    max_nanos: AtomicU64,
+}
+
+impl LockStats {
+    /// 获取读锁并记录等待时间
+    fn read<'a>(&self, store: &'a Store) -> RwLockReadGuard<'a, Keyspace> {
+        let start = Instant::now();
+        let guard = store.read().unwrap();
+        self.record(start.elapsed());
+        guard
+    }
+
+    /// 获取写锁并记录等待时间
+    fn write<'a>(&self, store: &'a Store) -> RwLockWriteGuard<'a, Keyspace> {
+        let start = Instant::now();
+        let guard = store.write().unwrap();
+        self.record(start.elapsed());
+        guard
+    }
+
+    fn record(&self, waited: Duration) {
+        let nanos = waited.as_nanos() as u64;
+        // Relaxed 足够：这些只是统计数字，不用于线程间同步
+        self.samples.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// 生成 LATENCY 命令的响应
+    fn report(&self) -> String {
+        let samples = self.samples.load(Ordering::Relaxed);
+        let total = self.total_nanos.load(Ordering::Relaxed);
+        let max = self.max_nanos.load(Ordering::Relaxed);
+        let avg = total.checked_div(samples).unwrap_or(0);
+
+        format!(
+            "LATENCY samples={} avg_us={:.3} max_us={:.3}\n",
+            samples,
+            avg as f64 / 1000.0,
+            max as f64 / 1000.0
+        )
+    }
+}
+
+/// 慢日志最多保留的条数，超出后丢弃最旧的
+const SLOWLOG_MAX_LEN: usize = 128;
+
+/// 一条慢日志
+struct SlowEntry {
+    /// 命令开始执行的时刻（Unix 秒）
+    timestamp: u64,
+    duration: Duration,
+    command: String,
+}
+
+/// 慢日志：记录执行时间超过阈值的命令
+///
+/// 所有工作线程共享同一份，用 Mutex 保护。只有慢命令才会加锁写入，
+/// 正常命令只做一次时间比较，不会因此互相等待
+struct SlowLog {
+    /// None 表示不记录
+    threshold: Option<Duration>,
+    capacity: usize,
+    entries: Mutex<VecDeque<SlowEntry>>,
+}
+
+impl SlowLog {
+    fn new(threshold: Option<Duration>, capacity: usize) -> Self {
+        SlowLog {
+            threshold,
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// 执行时间达到阈值时记录命令
+    fn record(&self, command: &str, started: SystemTime, duration: Duration) {
+        // 没有设置阈值，或者还没达到阈值，都不记录
+        match self.threshold {
+            Some(t) if duration >= t => {}
+            _ => return,
+        }
+        let timestamp = started
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let mut entries = self.entries.lock().unwrap();
+        // 环形缓冲：满了就先挤掉最旧的一条
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(SlowEntry {
+            timestamp,
+            duration,
+            command: command.to_string(),
+        });
+    }
+
+    /// 生成 SLOWLOG 命令的响应：第一行是条数，之后每行一条，最新的在前
+    fn report(&self) -> String {
+        let entries = self.entries.lock().unwrap();
+        let mut out = format!("SLOWLOG {}\n", entries.len());
+        for entry in entries.iter().rev() {
+            out.push_str(&format!(
+                "{} {}us {}\n",
+                entry.timestamp,
+                entry.duration.as_micros(),
+                entry.command
+            ));
+        }
+        out
+    }
+}
+
+/// 把未过期的数据保存为 JSON 文件，格式与 DUMP 相同
+///
+/// 先写临时文件再 rename，保存到一半失败也不会破坏上一次的快照。
+/// 与 DUMP 一样不保存过期时间
+fn save_snapshot(store: &Store, path: &Path) -> io::Result<()> {
+    let json = {
+        let ks = store.read().unwrap();
+        let now = Instant::now();
+        let live: HashMap<&String, &String> = ks
+            .data
+            .iter()
+            .filter(|(k, _)| !ks.is_expired(k, now))
+            .collect();
+        serde_json::to_string(&live)?
+    };
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+/// 读取快照文件，文件不存在时返回 None
+fn load_snapshot(path: &Path) -> io::Result<Option<Keyspace>> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let data: HashMap<String, String> = serde_json::from_str(&json)?;
+    Ok(Some(Keyspace {
+        data,
+        expires: HashMap::new(),
+    }))
+}
+
+/// 启动参数
+struct Config {
+    port: u16,
+    threads: usize,
+    /// 连接空闲超过该时间没有收到命令则关闭，None 表示不限制
+    idle_timeout: Option<Duration>,
+    /// 连接建立后最多保持多久，无论是否活跃，None 表示不限制
+    max_session: Option<Duration>,
+    /// 执行时间达到该值的命令写入慢日志，None 表示不记录
+    slow_threshold: Option<Duration>,
+    /// 启动时从这里载入数据，运行期间定期保存到这里
+    snapshot: Option<PathBuf>,
+}
 
 fn main() {
-    let (port, thread_count) = parse_args();
-    let addr = format!("127.0.0.1:{}", port);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = match parse_args(&args) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            eprintln!("参数错误: {}", e);
+            eprintln!(
+                "用法: kv-server-mt [--port PORT] [--threads N] [--idle-timeout SECS] [--max-session-secs SECS]"
+            );
+            eprintln!("                    [--slow-ms MS] [--snapshot PATH]");
+            std::process::exit(1);
+        }
+    };
+    let addr = format!("127.0.0.1:{}", config.port);
 
     let listener = match TcpListener::bind(&addr) {
         Ok(l) => l,
@@ -35,24 +289,58 @@ fn main() {
 
     println!("kv-server (多线程版) 启动");
     println!("监听地址: {}", addr);
-    println!("线程池大小: {}", thread_count);
-    println!("支持命令: SET key value | GET key | DEL key | KEYS | QUIT\n");
+    println!("线程池大小: {}", config.threads);
+    if let Some(timeout) = config.idle_timeout {
+        println!("空闲超时: {} 秒", timeout.as_secs());
+    }
+    if let Some(limit) = config.max_session {
+        println!("会话时长上限: {} 秒", limit.as_secs());
+    }
+    if let Some(threshold) = config.slow_threshold {
+        println!("慢日志阈值: {} 毫秒", threshold.as_millis());
+    }
+    println!(
+        "支持命令: SET key value | GET key | DEL key | EXPIRE key secs | KEYS | DUMP | LOAD json | LATENCY | SLOWLOG | QUIT\n"
+    );
+
+    // 共享存储，有快照时从快照恢复
+    let keyspace = match config.snapshot.as_deref().map(load_snapshot) {
+        Some(Ok(Some(keyspace))) => {
+            println!("已从快照载入 {} 个键", keyspace.data.len());
+            keyspace
+        }
+        Some(Ok(None)) | None => Keyspace::default(),
+        // 快照损坏时不能用空数据启动，否则下一次定期保存会把它覆盖掉
+        Some(Err(e)) => {
+            eprintln!("无法载入快照: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let store: Store = Arc::new(RwLock::new(keyspace));
 
-    // 共享存储
-    let store: Store = Arc::new(RwLock::new(HashMap::new()));
+    // 后台清理线程：即使过期的键再也没人访问，内存也能被回收
+    spawn_sweeper(&store, SWEEP_INTERVAL);
+    if let Some(path) = &config.snapshot {
+        spawn_snapshotter(&store, path.clone(), SNAPSHOT_INTERVAL);
+    }
+    let stats = Arc::new(LockStats::default());
+    let slowlog = Arc::new(SlowLog::new(config.slow_threshold, SLOWLOG_MAX_LEN));
 
     // 创建线程池
-    let pool = ThreadPool::new(thread_count);
+    let pool = ThreadPool::new(config.threads);
 
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
                 // 克隆 Arc，只增加引用计数
                 let store = Arc::clone(&store);
+                let stats = Arc::clone(&stats);
+                let slowlog = Arc::clone(&slowlog);
+                let config = Arc::clone(&config);
 
                 // 提交任务到线程池
                 pool.execute(move || {
-                    handle_client(stream, store);
+                    handle_client(stream, store, stats, slowlog, &config);
                 });
             }
             Err(e) => {
@@ -63,29 +351,69 @@ fn main() {
 }
 
 /// 处理单个客户端连接
-fn handle_client(stream: TcpStream, store: Store) {
+fn handle_client(
+    stream: TcpStream,
+    store: Store,
+    stats: Arc<LockStats>,
+    slowlog: Arc<SlowLog>,
+    config: &Config,
+) {
     let peer = stream.peer_addr().ok();
     println!("[{:?}] 客户端连接", peer);
 
+    // 记录连接时刻，算出会话的截止时间
+    let deadline = config.max_session.map(|limit| Instant::now() + limit);
+
+    if let Err(e) = configure_stream(&stream, config.idle_timeout) {
+        eprintln!("[{:?}] 设置 socket 选项失败: {}", peer, e);
+        return;
+    }
+
     // try_clone() 创建独立的写入句柄
     let mut writer = match stream.try_clone() {
         Ok(s) => s,
         Err(_) => return,
     };
 
-    let reader = BufReader::new(stream);
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    loop {
+        // 没有数据时 read 会一直阻塞，所以每次读之前把读超时缩短到会话剩余时间，
+        // 静默的连接也能按时关闭
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        if remaining == Some(Duration::ZERO) {
+            println!("[{:?}] 会话时间已到，关闭连接", peer);
+            break;
+        }
+        let timeout = read_timeout(config.idle_timeout, remaining);
+        if reader.get_ref().set_read_timeout(timeout).is_err() {
+            break;
+        }
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if is_timeout(&e) => {
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    println!("[{:?}] 会话时间已到，关闭连接", peer);
+                } else {
+                    println!("[{:?}] 空闲超时，关闭连接", peer);
+                }
+                break;
+            }
             Err(_) => break,
-        };
+        }
 
+        let line = line.trim_end_matches(['\r', '\n']);
         if line.is_empty() {
             continue;
         }
 
-        let response = execute_command(&line, &store);
+        // 已经读到的命令总会执行完并回复，会话上限只在读下一条命令前检查
+
+        let response = execute_timed(line, &store, &stats, &slowlog);
 
         if writer.write_all(response.as_bytes()).is_err() {
             break;
@@ -99,24 +427,74 @@ fn handle_client(stream: TcpStream, store: Store) {
     println!("[{:?}] 客户端断开", peer);
 }
 
+/// 调整连接的 socket 选项
+///
+/// - TCP_NODELAY: 关闭 Nagle 算法。Nagle 会把小包攒起来再发，
+///   对「一问一答」的协议来说会让每个响应多等几十毫秒
+/// - 读超时: read 阻塞超过该时间返回错误，用来关闭空闲连接
+fn configure_stream(stream: &TcpStream, idle_timeout: Option<Duration>) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(idle_timeout)?;
+    Ok(())
+}
+
+/// 空闲超时和会话剩余时间取较小的一个作为读超时
+fn read_timeout(idle: Option<Duration>, remaining: Option<Duration>) -> Option<Duration> {
+    match (idle, remaining) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// 读超时在不同平台上表现为 WouldBlock 或 TimedOut
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// 执行命令并计时，超过阈值的写入慢日志
+///
+/// SLOWLOG 在这里直接处理：它只读慢日志，不访问 store，自己也不计入慢日志
+fn execute_timed(line: &str, store: &Store, stats: &LockStats, slowlog: &SlowLog) -> String {
+    if line.eq_ignore_ascii_case("SLOWLOG") {
+        return slowlog.report();
+    }
+
+    let started = SystemTime::now();
+    // 耗时用单调时钟 Instant 测量，系统时间可能被调整
+    let start = Instant::now();
+    let response = execute_command(line, store, stats);
+    slowlog.record(line, started, start.elapsed());
+    response
+}
+
 /// 执行命令
-fn execute_command(line: &str, store: &Store) -> String {
+fn execute_command(line: &str, store: &Store, stats: &LockStats) -> String {
+    // LOAD 的参数是一整段 JSON，里面可能有空格，不能用下面的 splitn(3, ' ')
+    if let Some(("LOAD" | "load", json)) = line.split_once(' ') {
+        return execute_load(json, store, stats);
+    }
+
     let parts: Vec<&str> = line.splitn(3, ' ').collect();
 
     match parts.as_slice() {
         // SET 需要写锁
         ["SET", key, value] | ["set", key, value] => {
             // write() 获取写锁，阻塞其他所有访问
-            let mut store = store.write().unwrap();
-            store.insert(key.to_string(), value.to_string());
+            let mut store = stats.write(store);
+            store.data.insert(key.to_string(), value.to_string());
+            // 与 Redis 相同：重新 SET 会清除原来的过期时间
+            store.expires.remove(*key);
             "OK\n".to_string()
         }
 
         // GET 只需要读锁
         ["GET", key] | ["get", key] => {
             // read() 获取读锁，允许多个读者并发
-            let store = store.read().unwrap();
-            match store.get(*key) {
+            let store = stats.read(store);
+            match store.get(key) {
                 Some(value) => format!("VALUE {}\n", value),
                 None => "NOT_FOUND\n".to_string(),
             }
@@ -124,15 +502,36 @@ fn execute_command(line: &str, store: &Store) -> String {
 
         // DEL 需要写锁
         ["DEL", key] | ["del", key] => {
-            let mut store = store.write().unwrap();
-            store.remove(*key);
+            let mut store = stats.write(store);
+            store.data.remove(*key);
+            store.expires.remove(*key);
+            "OK\n".to_string()
+        }
+
+        // EXPIRE key seconds - 设置过期时间，需要写锁
+        ["EXPIRE", key, secs] | ["expire", key, secs] => {
+            let secs: u64 = match secs.parse() {
+                Ok(secs) => secs,
+                Err(_) => return "ERROR invalid seconds\n".to_string(),
+            };
+            let mut store = stats.write(store);
+            if store.get(key).is_none() {
+                return "NOT_FOUND\n".to_string();
+            }
+            let deadline = Instant::now() + Duration::from_secs(secs);
+            store.expires.insert(key.to_string(), deadline);
             "OK\n".to_string()
         }
 
         // KEYS 只需要读锁
         ["KEYS"] | ["keys"] => {
-            let store = store.read().unwrap();
-            let keys: Vec<&String> = store.keys().collect();
+            let store = stats.read(store);
+            let now = Instant::now();
+            let keys: Vec<&String> = store
+                .data
+                .keys()
+                .filter(|k| !store.is_expired(k, now))
+                .collect();
             if keys.is_empty() {
                 "KEYS (empty)\n".to_string()
             } else {
@@ -146,32 +545,591 @@ fn execute_command(line: &str, store: &Store) -> String {
             }
         }
 
+        // DUMP - 把整个 store 序列化成一行 JSON，只需要读锁
+        ["DUMP"] | ["dump"] => {
+            let store = stats.read(store);
+            let now = Instant::now();
+            let live: HashMap<&String, &String> = store
+                .data
+                .iter()
+                .filter(|(k, _)| !store.is_expired(k, now))
+                .collect();
+            match serde_json::to_string(&live) {
+                Ok(json) => format!("DUMP {}\n", json),
+                Err(e) => format!("ERROR {}\n", e),
+            }
+        }
+
+        // LATENCY - 报告锁等待时间（本身不访问 store）
+        ["LATENCY"] | ["latency"] => stats.report(),
+
         ["QUIT"] | ["quit"] => "BYE\n".to_string(),
 
         _ => "ERROR unknown command\n".to_string(),
     }
 }
 
-/// 解析命令行参数
-fn parse_args() -> (u16, usize) {
-    let args: Vec<String> = env::args().collect();
-    let mut port = 7878u16;
-    let mut threads = 4usize;
+/// LOAD: 用 JSON 对象替换 store 的全部内容
+///
+/// 先在锁外解析，解析失败直接返回错误，store 保持不变；
+/// 解析成功后只在替换的一瞬间持有写锁
+fn execute_load(json: &str, store: &Store, stats: &LockStats) -> String {
+    let data: HashMap<String, String> = match serde_json::from_str(json) {
+        Ok(data) => data,
+        Err(e) => return format!("ERROR invalid JSON: {}\n", e),
+    };
+
+    let count = data.len();
+    // DUMP 不包含过期时间，载入的键都是永久的
+    *stats.write(store) = Keyspace {
+        data,
+        expires: HashMap::new(),
+    };
+    format!("OK {} keys loaded\n", count)
+}
+
+/// 启动后台清理线程，每隔 interval 删除一次过期的键
+///
+/// 线程只持有 Weak 引用：store 的最后一个 Arc 被释放后，线程自动退出。
+/// 直接调用 write() 而不经过 LockStats，LATENCY 只统计客户端命令
+fn spawn_sweeper(store: &Store, interval: Duration) -> thread::JoinHandle<()> {
+    let store: Weak<RwLock<Keyspace>> = Arc::downgrade(store);
+
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let Some(store) = store.upgrade() else {
+            break;
+        };
+        // 写锁只在 remove_expired 期间持有
+        let removed = store.write().unwrap().remove_expired(Instant::now());
+        if removed > 0 {
+            println!("[sweeper] 删除了 {} 个过期的键", removed);
+        }
+    })
+}
+
+/// 启动后台快照线程，每隔 interval 把数据保存到 path
+///
+/// 和清理线程一样只持有 Weak 引用，store 被释放后自动退出。
+/// 保存失败只记录错误，下一轮再试，不影响服务器继续运行
+fn spawn_snapshotter(store: &Store, path: PathBuf, interval: Duration) -> thread::JoinHandle<()> {
+    let store: Weak<RwLock<Keyspace>> = Arc::downgrade(store);
+
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let Some(store) = store.upgrade() else {
+            break;
+        };
+        if let Err(e) = save_snapshot(&store, &path) {
+            eprintln!("[snapshot] 保存到 {} 失败: {}", path.display(), e);
+        }
+    })
+}
+
+/// 解析命令行参数（不含程序名）
+fn parse_args(args: &[String]) -> Result<Config, String> {
+    let mut config = Config {
+        port: 7878,
+        threads: 4,
+        idle_timeout: None,
+        max_session: None,
+        slow_threshold: None,
+        snapshot: None,
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        // `--name=value` 拆成名称和值；`--name value` 的值在下一个参数里
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || match inline {
+            Some(value) => Ok(value),
+            None => iter
+                .next()
+                .map(String::as_str)
+                .ok_or_else(|| format!("{} 缺少参数值", name)),
+        };
+
+        match name {
+            "--port" => config.port = parse_value(name, value()?)?,
+            "--threads" => {
+                config.threads = parse_value(name, value()?)?;
+                if config.threads == 0 {
+                    return Err("--threads 必须大于 0".to_string());
+                }
+            }
+            // 0 表示不限制
+            "--idle-timeout" => {
+                config.idle_timeout = non_zero_secs(parse_value(name, value()?)?);
+            }
+            // 与 --idle-timeout 相同，0 表示不限制
+            "--max-session-secs" => {
+                config.max_session = non_zero_secs(parse_value(name, value()?)?);
+            }
+            // 0 表示记录所有命令
+            "--slow-ms" => {
+                config.slow_threshold = Some(Duration::from_millis(parse_value(name, value()?)?));
+            }
+            "--snapshot" => config.snapshot = Some(PathBuf::from(value()?)),
+            other => return Err(format!("未知参数: {}", other)),
+        }
+    }
+
+    Ok(config)
+}
+
+/// 把参数值解析成目标类型，失败时在错误里带上参数名和原始值
+fn parse_value<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} 的值无效: {}", name, value))
+}
+
+/// 秒数为 0 时视为不限制
+fn non_zero_secs(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn new_store() -> Store {
+        Arc::new(RwLock::new(Keyspace::default()))
+    }
+
+    fn new_stats() -> Arc<LockStats> {
+        Arc::new(LockStats::default())
+    }
+
+    fn new_slowlog() -> Arc<SlowLog> {
+        Arc::new(SlowLog::new(None, SLOWLOG_MAX_LEN))
+    }
+
+    #[test]
+    fn test_latency_samples_match_commands() {
+        let store = new_store();
+        let stats = LockStats::default();
 
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--port" if i + 1 < args.len() => {
-                port = args[i + 1].parse().unwrap_or(7878);
-                i += 2;
+        let commands = ["SET a 1", "SET b 2", "GET a", "DEL b", "KEYS"];
+        for cmd in commands {
+            execute_command(cmd, &store, &stats);
+        }
+
+        let report = execute_command("LATENCY", &store, &stats);
+        assert!(
+            report.starts_with(&format!("LATENCY samples={} ", commands.len())),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn test_latency_empty() {
+        let stats = LockStats::default();
+        let report = execute_command("LATENCY", &new_store(), &stats);
+        assert_eq!(report, "LATENCY samples=0 avg_us=0.000 max_us=0.000\n");
+    }
+
+    #[test]
+    fn test_latency_max_tracks_slowest() {
+        let stats = LockStats::default();
+        stats.record(Duration::from_micros(10));
+        stats.record(Duration::from_micros(30));
+
+        assert_eq!(stats.samples.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.max_nanos.load(Ordering::Relaxed), 30_000);
+        assert_eq!(stats.total_nanos.load(Ordering::Relaxed), 40_000);
+    }
+
+    #[test]
+    fn test_configure_stream_sets_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        configure_stream(&server, Some(Duration::from_secs(5))).unwrap();
+
+        assert!(server.nodelay().unwrap());
+        assert_eq!(server.read_timeout().unwrap(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_idle_connection_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let config = Config {
+                port: 0,
+                threads: 1,
+                idle_timeout: Some(Duration::from_millis(100)),
+                max_session: None,
+                slow_threshold: None,
+                snapshot: None,
+            };
+            handle_client(stream, new_store(), new_stats(), new_slowlog(), &config);
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        // 兜底：服务端没关闭连接时测试不会永远挂住
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let start = Instant::now();
+        let mut buf = [0u8; 16];
+        // 什么都不发送，服务端超时后关闭连接，read 返回 0（EOF）
+        let n = client.read(&mut buf).unwrap();
+
+        assert_eq!(n, 0);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_session_limit_closes_active_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let config = Config {
+                port: 0,
+                threads: 1,
+                idle_timeout: Some(Duration::from_secs(10)),
+                max_session: Some(Duration::from_millis(300)),
+                slow_threshold: None,
+                snapshot: None,
+            };
+            handle_client(stream, new_store(), new_stats(), new_slowlog(), &config);
+        });
+
+        let client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut writer = client.try_clone().unwrap();
+        let mut reader = BufReader::new(client);
+
+        // 连接一直活跃（远低于空闲超时），但到了会话上限仍会被关闭
+        let start = Instant::now();
+        let mut replies = 0;
+        loop {
+            if writer.write_all(b"SET a 1\n").is_err() {
+                break;
             }
-            "--threads" if i + 1 < args.len() => {
-                threads = args[i + 1].parse().unwrap_or(4);
-                i += 2;
+            let mut reply = String::new();
+            match reader.read_line(&mut reply) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    // 每条已读取的命令都得到了完整回复
+                    assert_eq!(reply, "OK\n");
+                    replies += 1;
+                }
             }
-            _ => i += 1,
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        let elapsed = start.elapsed();
+        assert!(replies > 0);
+        assert!(elapsed >= Duration::from_millis(300), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(5), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_read_timeout_takes_smaller() {
+        let secs = Duration::from_secs;
+        assert_eq!(read_timeout(Some(secs(10)), Some(secs(3))), Some(secs(3)));
+        assert_eq!(read_timeout(Some(secs(2)), Some(secs(3))), Some(secs(2)));
+        assert_eq!(read_timeout(None, Some(secs(3))), Some(secs(3)));
+        assert_eq!(read_timeout(Some(secs(2)), None), Some(secs(2)));
+        assert_eq!(read_timeout(None, None), None);
+    }
+
+    #[test]
+    fn test_dump_then_load_round_trip() {
+        let stats = LockStats::default();
+        let store = new_store();
+        execute_command("SET name Alice", &store, &stats);
+        execute_command("SET msg hello world", &store, &stats);
+
+        let dump = execute_command("DUMP", &store, &stats);
+        let json = dump.strip_prefix("DUMP ").unwrap().trim_end();
+
+        // 载入到另一个已有数据的 store，原有内容被整体替换
+        let restored = new_store();
+        execute_command("SET old value", &restored, &stats);
+        let reply = execute_command(&format!("LOAD {}", json), &restored, &stats);
+        assert_eq!(reply, "OK 2 keys loaded\n");
+
+        assert_eq!(restored.read().unwrap().data, store.read().unwrap().data);
+        assert_eq!(
+            execute_command("GET msg", &restored, &stats),
+            "VALUE hello world\n"
+        );
+        assert_eq!(execute_command("GET old", &restored, &stats), "NOT_FOUND\n");
+    }
+
+    #[test]
+    fn test_load_malformed_json_keeps_store() {
+        let stats = LockStats::default();
+        let store = new_store();
+        execute_command("SET name Alice", &store, &stats);
+
+        let reply = execute_command(r#"LOAD {"name": "Bob""#, &store, &stats);
+        assert!(reply.starts_with("ERROR invalid JSON"));
+
+        // 值不是字符串也算格式错误
+        let reply = execute_command(r#"LOAD {"name": 1}"#, &store, &stats);
+        assert!(reply.starts_with("ERROR invalid JSON"));
+
+        assert_eq!(execute_command("GET name", &store, &stats), "VALUE Alice\n");
+    }
+
+    #[test]
+    fn test_expire_command() {
+        let stats = LockStats::default();
+        let store = new_store();
+        execute_command("SET name Alice", &store, &stats);
+
+        assert_eq!(execute_command("EXPIRE name 60", &store, &stats), "OK\n");
+        assert!(store.read().unwrap().expires.contains_key("name"));
+        assert_eq!(
+            execute_command("EXPIRE missing 60", &store, &stats),
+            "NOT_FOUND\n"
+        );
+        assert_eq!(
+            execute_command("EXPIRE name soon", &store, &stats),
+            "ERROR invalid seconds\n"
+        );
+
+        // EXPIRE 0 立即过期，读不到了
+        assert_eq!(execute_command("EXPIRE name 0", &store, &stats), "OK\n");
+        assert_eq!(execute_command("GET name", &store, &stats), "NOT_FOUND\n");
+        assert_eq!(execute_command("KEYS", &store, &stats), "KEYS (empty)\n");
+
+        // 重新 SET 清除过期时间
+        execute_command("SET name Bob", &store, &stats);
+        assert!(store.read().unwrap().expires.is_empty());
+        assert_eq!(execute_command("GET name", &store, &stats), "VALUE Bob\n");
+    }
+
+    #[test]
+    fn test_sweeper_removes_unread_expired_key() {
+        let stats = LockStats::default();
+        let store = new_store();
+        execute_command("SET temp 1", &store, &stats);
+        execute_command("SET keep 2", &store, &stats);
+        execute_command("EXPIRE temp 0", &store, &stats);
+
+        let sweeper = spawn_sweeper(&store, Duration::from_millis(20));
+
+        // 不读取 temp，只观察底层数据，等待清理线程删除它
+        let start = Instant::now();
+        while store.read().unwrap().data.contains_key("temp") {
+            assert!(
+                start.elapsed() < Duration::from_secs(2),
+                "过期的键没有被清理"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        {
+            let ks = store.read().unwrap();
+            assert!(ks.expires.is_empty());
+            assert_eq!(ks.data.get("keep").map(String::as_str), Some("2"));
+        }
+
+        // store 被释放后清理线程自己退出
+        drop(store);
+        sweeper.join().unwrap();
+    }
+
+    #[test]
+    fn test_slow_command_in_slowlog() {
+        let stats = LockStats::default();
+        let store = new_store();
+        let slowlog = SlowLog::new(Some(Duration::from_millis(50)), SLOWLOG_MAX_LEN);
+
+        execute_timed("SET fast 1", &store, &stats, &slowlog);
+        assert_eq!(
+            execute_timed("SLOWLOG", &store, &stats, &slowlog),
+            "SLOWLOG 0\n"
+        );
+
+        // 另一个线程持有写锁 200ms，这期间的 GET 只能等待，成为慢命令
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let holder = {
+            let store = Arc::clone(&store);
+            thread::spawn(move || {
+                let _guard = store.write().unwrap();
+                locked_tx.send(()).unwrap();
+                thread::sleep(Duration::from_millis(200));
+            })
+        };
+        locked_rx.recv().unwrap();
+        assert_eq!(
+            execute_timed("GET fast", &store, &stats, &slowlog),
+            "VALUE 1\n"
+        );
+        holder.join().unwrap();
+
+        let report = execute_timed("SLOWLOG", &store, &stats, &slowlog);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", report);
+        assert_eq!(lines[0], "SLOWLOG 1");
+        assert!(lines[1].ends_with("us GET fast"), "{}", report);
+    }
+
+    #[test]
+    fn test_slowlog_evicts_oldest() {
+        let slowlog = SlowLog::new(Some(Duration::ZERO), 2);
+        let now = SystemTime::now();
+        for cmd in ["GET a", "GET b", "GET c"] {
+            slowlog.record(cmd, now, Duration::from_millis(1));
+        }
+
+        let commands: Vec<String> = slowlog
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.command.clone())
+            .collect();
+        assert_eq!(commands, ["GET b", "GET c"]);
+    }
+
+    #[test]
+    fn test_slowlog_disabled_records_nothing() {
+        let slowlog = SlowLog::new(None, SLOWLOG_MAX_LEN);
+        slowlog.record("GET a", SystemTime::now(), Duration::from_secs(10));
+        assert_eq!(slowlog.report(), "SLOWLOG 0\n");
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.json");
+        let stats = LockStats::default();
+        let store = new_store();
+        execute_command("SET name Alice", &store, &stats);
+        execute_command("SET msg hello world", &store, &stats);
+        execute_command("SET temp gone", &store, &stats);
+        execute_command("EXPIRE temp 0", &store, &stats);
+
+        save_snapshot(&store, &path).unwrap();
+        assert!(!dir.path().join("dump.json.tmp").exists());
+
+        // 已过期的键不会写进快照
+        let loaded = load_snapshot(&path).unwrap().unwrap();
+        let mut expected = store.read().unwrap().data.clone();
+        expected.remove("temp");
+        assert_eq!(loaded.data, expected);
+        assert!(loaded.expires.is_empty());
+    }
+
+    #[test]
+    fn test_snapshotter_saves_periodically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.json");
+        let stats = LockStats::default();
+        let store = new_store();
+        execute_command("SET name Alice", &store, &stats);
+
+        let snapshotter = spawn_snapshotter(&store, path.clone(), Duration::from_millis(20));
+
+        // 等待后台线程写出第一份快照
+        let start = Instant::now();
+        while !path.exists() {
+            assert!(start.elapsed() < Duration::from_secs(2), "快照没有被保存");
+            thread::sleep(Duration::from_millis(10));
         }
+        let loaded = load_snapshot(&path).unwrap().unwrap();
+        assert_eq!(loaded.data.get("name").map(String::as_str), Some("Alice"));
+
+        // store 被释放后快照线程自己退出
+        drop(store);
+        snapshotter.join().unwrap();
     }
 
-    (port, threads)
+    #[test]
+    fn test_load_snapshot_missing_or_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.json");
+        assert!(load_snapshot(&path).unwrap().is_none());
+
+        fs::write(&path, "{not json").unwrap();
+        assert!(load_snapshot(&path).is_err());
+    }
+
+    #[test]
+    fn test_save_snapshot_failure_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("no_such_dir").join("dump.json");
+        assert!(save_snapshot(&new_store(), &path).is_err());
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args_both_syntaxes() {
+        let config = parse_args(&args(&["--port", "8080", "--threads", "8"])).unwrap();
+        assert_eq!((config.port, config.threads), (8080, 8));
+
+        let config = parse_args(&args(&[
+            "--port=9090",
+            "--threads=2",
+            "--idle-timeout=30",
+            "--snapshot=data/dump.json",
+        ]))
+        .unwrap();
+        assert_eq!((config.port, config.threads), (9090, 2));
+        assert_eq!(config.idle_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(config.snapshot, Some(PathBuf::from("data/dump.json")));
+
+        // 两种写法可以混用，没有给出的参数保持默认值
+        let config = parse_args(&args(&["--slow-ms=0", "--max-session-secs", "0"])).unwrap();
+        assert_eq!((config.port, config.threads), (7878, 4));
+        assert_eq!(config.slow_threshold, Some(Duration::ZERO));
+        assert_eq!(config.max_session, None);
+    }
+
+    #[test]
+    fn test_parse_args_invalid_value() {
+        assert_eq!(
+            parse_args(&args(&["--port", "http"])).err().unwrap(),
+            "--port 的值无效: http"
+        );
+        assert_eq!(
+            parse_args(&args(&["--port=70000"])).err().unwrap(),
+            "--port 的值无效: 70000"
+        );
+        assert!(parse_args(&args(&["--threads", "0"])).is_err());
+        assert!(parse_args(&args(&["--idle-timeout=-1"])).is_err());
+        assert_eq!(
+            parse_args(&args(&["--port"])).err().unwrap(),
+            "--port 缺少参数值"
+        );
+    }
+
+    #[test]
+    fn test_parse_args_unknown_flag() {
+        assert_eq!(
+            parse_args(&args(&["--port", "8080", "--verbose"]))
+                .err()
+                .unwrap(),
+            "未知参数: --verbose"
+        );
+        assert_eq!(
+            parse_args(&args(&["--color=auto"])).err().unwrap(),
+            "未知参数: --color"
+        );
+        assert!(parse_args(&args(&["8080"])).is_err());
+    }
 }
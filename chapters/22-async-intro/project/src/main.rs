@@ -1,24 +1,132 @@
 // async-kv: 异步键值存储服务器
 // 使用 Tokio 运行时
+// 用法: async-kv [--unix <socket 路径>] [--max-conns N]
 //
 // 特性:
 // - 异步 I/O，少量线程处理大量连接
 // - tokio::spawn 并发处理请求
 // - 使用 tokio::sync::RwLock 代替 std::sync::RwLock
+// - 每个连接分配递增的 id，日志带 [conn N] 前缀
+// - --unix 改为监听 UNIX 域套接字，本机进程间通信省去 TCP 协议栈开销（仅 Unix 系统）
+// - EXPIRE / TTL 设置和查询过期时间，后台任务定期清理过期的键
+// - --max-conns 用 Semaphore 限制同时处理的连接数，超出的连接收到 BUSY 后被关闭
+// - INFO 报告运行时间、键的个数和当前连接数
 
 use std::collections::HashMap;
+use std::env;
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::{RwLock, Semaphore};
+use tokio::time::Instant;
 
 // 异步版本的 Store
 // 注意：tokio::sync::RwLock 而不是 std::sync::RwLock
 // tokio 的锁是异步感知的，可以跨 await 点持有
-type Store = Arc<RwLock<HashMap<String, String>>>;
+//
+// 数据和过期时间各用一把锁，两个字段都是 Arc，clone 只增加引用计数。
+// 需要同时持有两把锁时，总是先锁 data 再锁 expires，避免死锁
+#[derive(Clone, Default)]
+struct Store {
+    data: Arc<RwLock<HashMap<String, String>>>,
+    /// 设置了过期时间的键 -> 过期时刻
+    expires: Arc<RwLock<HashMap<String, Instant>>>,
+    /// 所有连接共享的运行统计
+    metrics: Arc<Metrics>,
+}
+
+/// 服务器运行统计，INFO 命令读取
+struct Metrics {
+    /// 启动时刻：main 里创建 Store 时记录
+    started: Instant,
+    /// 正在处理的连接数（即正在运行的客户端任务数）
+    connections: AtomicUsize,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics {
+            started: Instant::now(),
+            connections: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// 后台清理过期键的间隔
+const REAP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 下一个连接 id
+///
+/// 多个任务并发 accept 也不会拿到重复的 id：fetch_add 是原子的「读取并加一」
+static NEXT_CONN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 分配一个新的连接 id
+fn next_conn_id() -> u64 {
+    NEXT_CONN_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 #[tokio::main]
 async fn main() {
+    let store = Store::default();
+
+    // 过期的键在 GET 时已经不可见，后台任务负责真正释放内存
+    tokio::spawn(reap_loop(store.clone(), REAP_INTERVAL));
+
+    let args: Vec<String> = env::args().collect();
+
+    // 每个连接占用一个许可，许可用完时新连接直接被拒绝
+    let max_conns = match parse_max_conns(&args) {
+        Some(n) => n,
+        None => {
+            eprintln!("用法: async-kv [--unix <socket 路径>] [--max-conns N]");
+            std::process::exit(1);
+        }
+    };
+    let limiter = Arc::new(Semaphore::new(max_conns));
+
+    // UNIX 域套接字只有 Unix 系统才有，其他平台只能用 TCP
+    #[cfg(not(unix))]
+    if args.iter().any(|a| a == "--unix") {
+        eprintln!("--unix 只在 Unix 系统上可用");
+        std::process::exit(1);
+    }
+
+    #[cfg(unix)]
+    if let Some(i) = args.iter().position(|a| a == "--unix") {
+        let path = match args.get(i + 1) {
+            Some(path) => path,
+            None => {
+                eprintln!("用法: async-kv [--unix <socket 路径>] [--max-conns N]");
+                std::process::exit(1);
+            }
+        };
+
+        let listener = match bind_unix(Path::new(path)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("无法绑定到 {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+
+        println!("async-kv 启动，监听 unix:{}", path);
+        println!("使用 Tokio 异步运行时\n");
+        serve_unix(listener, store, limiter).await;
+        return;
+    }
+
     let addr = "127.0.0.1:7878";
 
     // TcpListener::bind 是异步的，返回 Future
@@ -28,30 +136,100 @@ async fn main() {
     println!("async-kv 启动，监听 {}", addr);
     println!("使用 Tokio 异步运行时\n");
 
-    let store: Store = Arc::new(RwLock::new(HashMap::new()));
-
     loop {
         // accept() 异步等待新连接
         let (socket, peer) = listener.accept().await.unwrap();
+        let conn_id = next_conn_id();
+
+        println!("[conn {}] 客户端连接: {}", conn_id, peer);
+
+        spawn_client(socket, store.clone(), conn_id, &limiter);
+    }
+}
+
+/// 解析 --max-conns，没有该参数或为 0 时不限制；参数格式错误返回 None
+fn parse_max_conns(args: &[String]) -> Option<usize> {
+    match args.iter().position(|a| a == "--max-conns") {
+        Some(i) => match args.get(i + 1)?.parse().ok()? {
+            0 => Some(Semaphore::MAX_PERMITS),
+            n => Some(n.min(Semaphore::MAX_PERMITS)),
+        },
+        None => Some(Semaphore::MAX_PERMITS),
+    }
+}
+
+/// 为新连接创建处理任务，连接数已满时回复 BUSY 并关闭
+///
+/// try_acquire_owned 不等待：拿不到许可说明已满，立即拒绝，
+/// 而不是让客户端排队（这就是「甩掉负载」）。
+/// 许可随任务移动，任务结束时被 drop，名额自动归还
+fn spawn_client<S>(socket: S, store: Store, conn_id: u64, limiter: &Arc<Semaphore>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let permit = match Arc::clone(limiter).try_acquire_owned() {
+        Ok(permit) => permit,
+        Err(_) => {
+            println!("[conn {}] 连接数已满，拒绝", conn_id);
+            // 在单独的任务里回复，慢客户端不会拖住 accept 循环
+            tokio::spawn(async move {
+                let mut socket = socket;
+                let _ = socket.write_all(b"BUSY\n").await;
+                let _ = socket.shutdown().await;
+            });
+            return;
+        }
+    };
 
-        println!("[{:?}] 客户端连接", peer);
+    // tokio::spawn 创建异步任务
+    // 类似 thread::spawn，但是是轻量级的绿色线程
+    let metrics = Arc::clone(&store.metrics);
+    metrics.connections.fetch_add(1, Ordering::Relaxed);
+    tokio::spawn(async move {
+        handle_client(socket, store, conn_id).await;
+        println!("[conn {}] 客户端断开", conn_id);
+        metrics.connections.fetch_sub(1, Ordering::Relaxed);
+        drop(permit);
+    });
+}
 
-        // 克隆共享状态
-        let store = Arc::clone(&store);
+/// 绑定 UNIX 域套接字
+///
+/// 上次运行异常退出时 socket 文件会残留，直接 bind 会报 AddrInUse，
+/// 所以先删掉它。只删除 socket 类型的文件，避免误删普通文件
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            fs::remove_file(path)?;
+        }
+    }
+    UnixListener::bind(path)
+}
 
-        // tokio::spawn 创建异步任务
-        // 类似 thread::spawn，但是是轻量级的绿色线程
-        tokio::spawn(async move {
-            handle_client(socket, store).await;
-            println!("[{:?}] 客户端断开", peer);
-        });
+/// UNIX 域套接字的 accept 循环，与 main 中的 TCP 循环相同
+#[cfg(unix)]
+async fn serve_unix(listener: UnixListener, store: Store, limiter: Arc<Semaphore>) {
+    loop {
+        // UNIX 套接字的客户端通常没有地址，日志里不打印 peer
+        let (socket, _) = listener.accept().await.unwrap();
+        let conn_id = next_conn_id();
+
+        println!("[conn {}] 客户端连接: unix socket", conn_id);
+
+        spawn_client(socket, store.clone(), conn_id, &limiter);
     }
 }
 
 /// 处理单个客户端（异步版本）
-async fn handle_client(mut socket: TcpStream, store: Store) {
-    // split 将 socket 分成读写两半
-    let (reader, mut writer) = socket.split();
+///
+/// 对连接类型是泛型的：TcpStream 和 UnixStream 都实现了 AsyncRead + AsyncWrite
+async fn handle_client<S>(socket: S, store: Store, conn_id: u64)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // tokio::io::split 将 socket 分成读写两半，适用于任何 AsyncRead + AsyncWrite
+    let (reader, mut writer) = tokio::io::split(socket);
 
     // 使用异步 BufReader
     let mut reader = BufReader::new(reader);
@@ -72,7 +250,11 @@ async fn handle_client(mut socket: TcpStream, store: Store) {
             continue;
         }
 
-        let response = execute_command(line, &store).await;
+        println!("[conn {}] 收到: {}", conn_id, line);
+
+        let response = execute_command(line, &store).await.encode();
+
+        println!("[conn {}] 响应: {}", conn_id, response.trim());
 
         // write_all 也是异步的
         if writer.write_all(response.as_bytes()).await.is_err() {
@@ -85,51 +267,490 @@ async fn handle_client(mut socket: TcpStream, store: Store) {
     }
 }
 
+/// 键是否已经过期（没有设置过期时间的键永不过期）
+fn is_expired(expires: &HashMap<String, Instant>, key: &str) -> bool {
+    expires
+        .get(key)
+        .is_some_and(|&deadline| deadline <= Instant::now())
+}
+
+/// 删除所有已过期的键，返回删除的个数
+async fn reap_expired(store: &Store) -> usize {
+    let mut data = store.data.write().await;
+    let mut expires = store.expires.write().await;
+
+    let now = Instant::now();
+    let expired: Vec<String> = expires
+        .iter()
+        .filter(|(_, &deadline)| deadline <= now)
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    for key in &expired {
+        data.remove(key);
+        expires.remove(key);
+    }
+    expired.len()
+}
+
+/// 后台清理任务：每隔 period 清理一次过期键
+async fn reap_loop(store: Store, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        // interval.tick() 异步等待下一个周期，不占用线程
+        interval.tick().await;
+        let count = reap_expired(&store).await;
+        if count > 0 {
+            println!("[reaper] 清理了 {} 个过期键", count);
+        }
+    }
+}
+
+/// 命令的执行结果
+///
+/// execute_command 只决定「回复什么」，「怎样写到连接上」由 encode 负责，
+/// 测试可以直接比较枚举值，不用解析字符串
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Ok,
+    Value(String),
+    NotFound,
+    Keys(Vec<String>),
+    /// 剩余秒数，-1 表示没有过期时间
+    Ttl(i64),
+    /// INFO 的统计结果
+    Info {
+        uptime_secs: u64,
+        keys: usize,
+        connections: usize,
+    },
+    Bye,
+    Error(String),
+}
+
+impl Reply {
+    /// 编码成协议格式，每个回复占一行
+    fn encode(&self) -> String {
+        match self {
+            Reply::Ok => "OK\n".to_string(),
+            Reply::Value(value) => format!("VALUE {}\n", value),
+            Reply::NotFound => "NOT_FOUND\n".to_string(),
+            Reply::Keys(keys) if keys.is_empty() => "KEYS (empty)\n".to_string(),
+            Reply::Keys(keys) => format!("KEYS {}\n", keys.join(" ")),
+            Reply::Ttl(secs) => format!("TTL {}\n", secs),
+            Reply::Info {
+                uptime_secs,
+                keys,
+                connections,
+            } => format!(
+                "INFO uptime_secs={} keys={} connections={}\n",
+                uptime_secs, keys, connections
+            ),
+            Reply::Bye => "BYE\n".to_string(),
+            Reply::Error(message) => format!("ERROR {}\n", message),
+        }
+    }
+}
+
 /// 执行命令（异步版本）
-async fn execute_command(line: &str, store: &Store) -> String {
+async fn execute_command(line: &str, store: &Store) -> Reply {
     let parts: Vec<&str> = line.splitn(3, ' ').collect();
 
     match parts.as_slice() {
         ["SET", key, value] | ["set", key, value] => {
             // .await 获取写锁
-            let mut store = store.write().await;
-            store.insert(key.to_string(), value.to_string());
-            "OK\n".to_string()
+            let mut data = store.data.write().await;
+            data.insert(key.to_string(), value.to_string());
+            // 重新 SET 的键不再继承之前的过期时间
+            store.expires.write().await.remove(*key);
+            Reply::Ok
         }
 
         ["GET", key] | ["get", key] => {
             // .await 获取读锁
-            let store = store.read().await;
-            match store.get(*key) {
-                Some(value) => format!("VALUE {}\n", value),
-                None => "NOT_FOUND\n".to_string(),
+            let data = store.data.read().await;
+            let expires = store.expires.read().await;
+            // 惰性检查：过期但还没被后台任务清理的键视为不存在
+            match data.get(*key) {
+                Some(value) if !is_expired(&expires, key) => Reply::Value(value.clone()),
+                _ => Reply::NotFound,
             }
         }
 
         ["DEL", key] | ["del", key] => {
-            let mut store = store.write().await;
-            store.remove(*key);
-            "OK\n".to_string()
+            let mut data = store.data.write().await;
+            data.remove(*key);
+            store.expires.write().await.remove(*key);
+            Reply::Ok
+        }
+
+        ["EXPIRE", key, seconds] | ["expire", key, seconds] => {
+            let seconds: u64 = match seconds.parse() {
+                Ok(n) => n,
+                Err(_) => return Reply::Error("invalid seconds".to_string()),
+            };
+            // 秒数大到 Instant 放不下时（比如 u64::MAX），直接用 `+` 会 panic
+            let deadline = match Instant::now().checked_add(Duration::from_secs(seconds)) {
+                Some(deadline) => deadline,
+                None => return Reply::Error("invalid seconds".to_string()),
+            };
+
+            let data = store.data.read().await;
+            let mut expires = store.expires.write().await;
+            if !data.contains_key(*key) || is_expired(&expires, key) {
+                return Reply::NotFound;
+            }
+            expires.insert(key.to_string(), deadline);
+            Reply::Ok
+        }
+
+        ["TTL", key] | ["ttl", key] => {
+            let data = store.data.read().await;
+            let expires = store.expires.read().await;
+            if !data.contains_key(*key) || is_expired(&expires, key) {
+                return Reply::NotFound;
+            }
+            match expires.get(*key) {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    // 四舍五入到秒
+                    Reply::Ttl(((remaining.as_millis() + 500) / 1000) as i64)
+                }
+                // 与 Redis 一致，没有过期时间时返回 -1
+                None => Reply::Ttl(-1),
+            }
         }
 
         ["KEYS"] | ["keys"] => {
-            let store = store.read().await;
-            let keys: Vec<&String> = store.keys().collect();
-            if keys.is_empty() {
-                "KEYS (empty)\n".to_string()
-            } else {
-                format!(
-                    "KEYS {}\n",
-                    keys.iter()
-                        .map(|k| k.as_str())
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                )
+            let data = store.data.read().await;
+            let expires = store.expires.read().await;
+            let keys = data
+                .keys()
+                .filter(|k| !is_expired(&expires, k))
+                .cloned()
+                .collect();
+            Reply::Keys(keys)
+        }
+
+        ["INFO"] | ["info"] => {
+            let data = store.data.read().await;
+            let expires = store.expires.read().await;
+            // 与 KEYS 一致，已过期但还没清理的键不计入
+            let keys = data.keys().filter(|k| !is_expired(&expires, k)).count();
+            Reply::Info {
+                uptime_secs: store.metrics.started.elapsed().as_secs(),
+                keys,
+                connections: store.metrics.connections.load(Ordering::Relaxed),
             }
         }
 
-        ["QUIT"] | ["quit"] => "BYE\n".to_string(),
+        ["QUIT"] | ["quit"] => Reply::Bye,
+
+        _ => Reply::Error("unknown command".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conn_ids_increase() {
+        let a = next_conn_id();
+        let b = next_conn_id();
+        let c = next_conn_id();
+
+        assert!(a < b && b < c);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_conn_ids_unique_across_tasks() {
+        let handles: Vec<_> = (0..8)
+            .map(|_| tokio::spawn(async { (0..100).map(|_| next_conn_id()).collect::<Vec<_>>() }))
+            .collect();
+
+        let mut ids = Vec::new();
+        for handle in handles {
+            let task_ids = handle.await.unwrap();
+            // 同一个任务内拿到的 id 严格递增
+            assert!(task_ids.windows(2).all(|w| w[0] < w[1]));
+            ids.extend(task_ids);
+        }
+
+        let total = ids.len();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), total);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_set_get() {
+        use tokio::net::UnixStream;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kv.sock");
+
+        // 模拟上次运行残留的 socket 文件
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let listener = bind_unix(&path).unwrap();
+        let limiter = Arc::new(Semaphore::new(Semaphore::MAX_PERMITS));
+        tokio::spawn(serve_unix(listener, Store::default(), limiter));
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        writer.write_all(b"SET name Alice\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "OK");
+
+        writer.write_all(b"GET name\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "VALUE Alice");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_max_conns_rejects_excess() {
+        use tokio::io::AsyncReadExt;
+        use tokio::net::UnixStream;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kv.sock");
+        let listener = bind_unix(&path).unwrap();
+        let limiter = Arc::new(Semaphore::new(2));
+        tokio::spawn(serve_unix(listener, Store::default(), Arc::clone(&limiter)));
+
+        // 收到命令的回复，说明连接已经拿到许可、正在被处理
+        async fn admitted(path: &Path) -> UnixStream {
+            let mut stream = UnixStream::connect(path).await.unwrap();
+            stream.write_all(b"GET x\n").await.unwrap();
+            let mut buf = [0u8; 16];
+            let n = stream.read(&mut buf).await.unwrap();
+            assert_eq!(&buf[..n], b"NOT_FOUND\n");
+            stream
+        }
+
+        let first = admitted(&path).await;
+        let _second = admitted(&path).await;
+        assert_eq!(limiter.available_permits(), 0);
+
+        // 第三个连接超出上限：收到 BUSY 后被关闭
+        let mut third = UnixStream::connect(&path).await.unwrap();
+        let mut reply = String::new();
+        third.read_to_string(&mut reply).await.unwrap();
+        assert_eq!(reply, "BUSY\n");
+
+        // 断开一个连接后名额归还，新连接可以进入
+        drop(first);
+        while limiter.available_permits() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        admitted(&path).await;
+    }
+
+    #[test]
+    fn test_parse_max_conns() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
 
-        _ => "ERROR unknown command\n".to_string(),
+        assert_eq!(
+            parse_max_conns(&args(&["async-kv", "--max-conns", "8"])),
+            Some(8)
+        );
+        assert_eq!(
+            parse_max_conns(&args(&["async-kv"])),
+            Some(Semaphore::MAX_PERMITS)
+        );
+        assert_eq!(
+            parse_max_conns(&args(&["async-kv", "--max-conns", "0"])),
+            Some(Semaphore::MAX_PERMITS)
+        );
+        assert_eq!(
+            parse_max_conns(&args(&["async-kv", "--max-conns", "x"])),
+            None
+        );
+        assert_eq!(parse_max_conns(&args(&["async-kv", "--max-conns"])), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bind_unix_keeps_regular_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.txt");
+        fs::write(&path, "important").unwrap();
+
+        // 不是 socket 的文件不删除，bind 报错
+        assert!(bind_unix(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "important");
+    }
+
+    // start_paused: 测试中的时间由 tokio 控制，advance 会立即推进时钟
+    #[tokio::test(start_paused = true)]
+    async fn test_expire_then_gone() {
+        let store = Store::default();
+        execute_command("SET session abc", &store).await;
+
+        assert_eq!(execute_command("TTL session", &store).await, Reply::Ttl(-1));
+        assert_eq!(execute_command("EXPIRE session 2", &store).await, Reply::Ok);
+        assert_eq!(execute_command("TTL session", &store).await, Reply::Ttl(2));
+
+        tokio::time::advance(Duration::from_millis(1500)).await;
+        assert_eq!(
+            execute_command("GET session", &store).await,
+            Reply::Value("abc".to_string())
+        );
+
+        tokio::time::advance(Duration::from_millis(600)).await;
+        assert_eq!(
+            execute_command("GET session", &store).await,
+            Reply::NotFound
+        );
+        assert_eq!(
+            execute_command("TTL session", &store).await,
+            Reply::NotFound
+        );
+        assert_eq!(execute_command("KEYS", &store).await, Reply::Keys(vec![]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_clears_expiry() {
+        let store = Store::default();
+        execute_command("SET k v1", &store).await;
+        execute_command("EXPIRE k 1", &store).await;
+        execute_command("SET k v2", &store).await;
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(
+            execute_command("GET k", &store).await,
+            Reply::Value("v2".to_string())
+        );
+        assert_eq!(execute_command("TTL k", &store).await, Reply::Ttl(-1));
+
+        assert_eq!(
+            execute_command("EXPIRE missing 10", &store).await,
+            Reply::NotFound
+        );
+        assert_eq!(
+            execute_command("EXPIRE k soon", &store).await,
+            Reply::Error("invalid seconds".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expire_huge_seconds_rejected() {
+        let store = Store::default();
+        execute_command("SET k v", &store).await;
+
+        // 过期时刻超出 Instant 的范围，回复错误而不是 panic，键保持不过期
+        assert_eq!(
+            execute_command("EXPIRE k 18446744073709551615", &store).await,
+            Reply::Error("invalid seconds".to_string())
+        );
+        assert_eq!(execute_command("TTL k", &store).await, Reply::Ttl(-1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reaper_removes_expired_keys() {
+        let store = Store::default();
+        execute_command("SET a 1", &store).await;
+        execute_command("SET b 2", &store).await;
+        execute_command("EXPIRE a 1", &store).await;
+
+        tokio::spawn(reap_loop(store.clone(), Duration::from_millis(100)));
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        // 后台任务已经把过期键从两个表里都删掉了
+        assert!(!store.data.read().await.contains_key("a"));
+        assert!(store.expires.read().await.is_empty());
+        assert_eq!(
+            execute_command("GET b", &store).await,
+            Reply::Value("2".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reply_variants() {
+        let store = Store::default();
+
+        assert_eq!(execute_command("SET name Alice", &store).await, Reply::Ok);
+        assert_eq!(
+            execute_command("GET name", &store).await,
+            Reply::Value("Alice".to_string())
+        );
+        assert_eq!(
+            execute_command("KEYS", &store).await,
+            Reply::Keys(vec!["name".to_string()])
+        );
+        assert_eq!(execute_command("TTL name", &store).await, Reply::Ttl(-1));
+        assert_eq!(execute_command("EXPIRE name 10", &store).await, Reply::Ok);
+        assert_eq!(execute_command("TTL name", &store).await, Reply::Ttl(10));
+        assert_eq!(execute_command("DEL name", &store).await, Reply::Ok);
+        assert_eq!(execute_command("GET name", &store).await, Reply::NotFound);
+        assert_eq!(execute_command("TTL name", &store).await, Reply::NotFound);
+        assert_eq!(execute_command("quit", &store).await, Reply::Bye);
+        assert_eq!(
+            execute_command("FLUSH", &store).await,
+            Reply::Error("unknown command".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reply_encode() {
+        assert_eq!(Reply::Ok.encode(), "OK\n");
+        assert_eq!(Reply::Value("a b".to_string()).encode(), "VALUE a b\n");
+        assert_eq!(Reply::NotFound.encode(), "NOT_FOUND\n");
+        assert_eq!(Reply::Keys(vec![]).encode(), "KEYS (empty)\n");
+        assert_eq!(
+            Reply::Keys(vec!["a".to_string(), "b".to_string()]).encode(),
+            "KEYS a b\n"
+        );
+        assert_eq!(Reply::Ttl(-1).encode(), "TTL -1\n");
+        assert_eq!(Reply::Bye.encode(), "BYE\n");
+        assert_eq!(
+            Reply::Error("invalid seconds".to_string()).encode(),
+            "ERROR invalid seconds\n"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_info_reports_metrics() {
+        let store = Store::default();
+        execute_command("SET a 1", &store).await;
+        execute_command("SET b 2", &store).await;
+        execute_command("SET c 3", &store).await;
+        execute_command("DEL c", &store).await;
+        execute_command("EXPIRE b 1", &store).await;
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        store.metrics.connections.fetch_add(2, Ordering::Relaxed);
+
+        // b 已过期，c 被删除，只剩 a
+        let reply = execute_command("INFO", &store).await;
+        assert_eq!(
+            reply,
+            Reply::Info {
+                uptime_secs: 5,
+                keys: 1,
+                connections: 2,
+            }
+        );
+        assert_eq!(reply.encode(), "INFO uptime_secs=5 keys=1 connections=2\n");
+    }
+
+    #[tokio::test]
+    async fn test_info_counts_live_connections() {
+        let store = Store::default();
+        let limiter = Arc::new(Semaphore::new(Semaphore::MAX_PERMITS));
+
+        let (client, server) = tokio::io::duplex(1024);
+        spawn_client(server, store.clone(), next_conn_id(), &limiter);
+        assert_eq!(store.metrics.connections.load(Ordering::Relaxed), 1);
+
+        // 客户端断开后任务结束，计数归零
+        drop(client);
+        while store.metrics.connections.load(Ordering::Relaxed) > 0 {
+            tokio::task::yield_now().await;
+        }
     }
 }
@@ -2,8 +2,8 @@
 // 使用 Axum 框架构建 REST API
 //
 // API:
-//   POST /links          创建短链接
-//   GET /:code           重定向到原始 URL
+//   POST /links          创建短链接（可选 ttl_secs 设置有效期）
+//   GET /:code           重定向到原始 URL，过期后返回 410 Gone
 //   GET /links/:code/stats  查看统计
 
 use axum::{
@@ -17,14 +17,27 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+/// 后台清理过期链接的间隔
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
 
 // 短链接记录
 #[derive(Clone)]
 struct LinkRecord {
     url: String,
     clicks: u64,
+    /// 过期时刻，None 表示永不过期
+    expires_at: Option<Instant>,
+}
+
+impl LinkRecord {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|t| t <= now)
+    }
 }
 
 // 应用状态
@@ -37,6 +50,9 @@ struct AppState {
 #[derive(Deserialize)]
 struct CreateLinkRequest {
     url: String,
+    /// 有效期（秒），不填表示永久有效
+    #[serde(default)]
+    ttl_secs: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -72,7 +88,10 @@ async fn main() {
         .route("/links", post(create_link))
         .route("/:code", get(redirect_link))
         .route("/links/:code/stats", get(get_stats))
-        .with_state(state);
+        .with_state(Arc::clone(&state));
+
+    // 后台定期删除过期链接，避免它们一直占用内存
+    tokio::spawn(prune_loop(state, PRUNE_INTERVAL));
 
     let addr = "0.0.0.0:3000";
     println!("link-short 启动，监听 {}", addr);
@@ -94,6 +113,32 @@ async fn create_link(
     State(state): State<Arc<AppState>>,
     Json(req): Json<CreateLinkRequest>,
 ) -> impl IntoResponse {
+    // ttl_secs 为 0 的链接一创建就过期，没有意义，直接拒绝
+    if req.ttl_secs == Some(0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "ttl_secs must be positive".to_string(),
+            }),
+        ));
+    }
+
+    // ttl_secs 大到 Instant 放不下时（比如接近 u64::MAX），直接用 `+` 会 panic
+    let expires_at = match req.ttl_secs {
+        Some(secs) => match Instant::now().checked_add(Duration::from_secs(secs)) {
+            Some(at) => Some(at),
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: "ttl_secs is too large".to_string(),
+                    }),
+                ));
+            }
+        },
+        None => None,
+    };
+
     // 生成随机短码
     let code = generate_code();
 
@@ -101,6 +146,7 @@ async fn create_link(
     let record = LinkRecord {
         url: req.url,
         clicks: 0,
+        expires_at,
     };
 
     // 存储
@@ -113,7 +159,7 @@ async fn create_link(
         code,
     };
 
-    (StatusCode::CREATED, Json(response))
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 /// 重定向到原始 URL
@@ -126,6 +172,20 @@ async fn redirect_link(
     // 先尝试获取写锁来更新点击数
     let mut links = state.links.write().await;
 
+    // 已过期但还没被后台任务清理的链接返回 410 Gone；
+    // 清理之后就和不存在的短码一样返回 404
+    if links
+        .get(&code)
+        .is_some_and(|r| r.is_expired(Instant::now()))
+    {
+        return Err((
+            StatusCode::GONE,
+            Json(ErrorResponse {
+                error: "Link expired".to_string(),
+            }),
+        ));
+    }
+
     if let Some(record) = links.get_mut(&code) {
         record.clicks += 1;
         let url = record.url.clone();
@@ -166,6 +226,24 @@ async fn get_stats(
     }
 }
 
+/// 删除所有已过期的链接，返回删除的个数
+async fn prune_expired(state: &AppState) -> usize {
+    let now = Instant::now();
+    let mut links = state.links.write().await;
+    let before = links.len();
+    links.retain(|_, record| !record.is_expired(now));
+    before - links.len()
+}
+
+/// 每隔 period 清理一次过期链接
+async fn prune_loop(state: Arc<AppState>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        prune_expired(&state).await;
+    }
+}
+
 /// 生成 6 位随机短码
 fn generate_code() -> String {
     const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
@@ -178,3 +256,93 @@ fn generate_code() -> String {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state() -> Arc<AppState> {
+        Arc::new(AppState {
+            links: RwLock::new(HashMap::new()),
+            base_url: "http://localhost:3000".to_string(),
+        })
+    }
+
+    /// 调用 create_link，返回生成的短码
+    async fn create(state: &Arc<AppState>, ttl_secs: Option<u64>) -> String {
+        let req = CreateLinkRequest {
+            url: "https://example.com".to_string(),
+            ttl_secs,
+        };
+        let response = create_link(State(Arc::clone(state)), Json(req))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let links = state.links.read().await;
+        links.keys().next().unwrap().clone()
+    }
+
+    async fn visit(state: &Arc<AppState>, code: &str) -> StatusCode {
+        redirect_link(State(Arc::clone(state)), Path(code.to_string()))
+            .await
+            .into_response()
+            .status()
+    }
+
+    // start_paused：tokio 的时钟由测试控制，sleep 会立即推进时间
+    #[tokio::test(start_paused = true)]
+    async fn test_expired_link_is_gone() {
+        let state = test_state();
+        let code = create(&state, Some(5)).await;
+
+        assert_eq!(visit(&state, &code).await, StatusCode::TEMPORARY_REDIRECT);
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(visit(&state, &code).await, StatusCode::GONE);
+
+        // 后台清理之后，短码不再存在
+        assert_eq!(prune_expired(&state).await, 1);
+        assert_eq!(visit(&state, &code).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_link_without_ttl_never_expires() {
+        let state = test_state();
+        let code = create(&state, None).await;
+
+        tokio::time::sleep(Duration::from_secs(365 * 24 * 3600)).await;
+        assert_eq!(prune_expired(&state).await, 0);
+        assert_eq!(visit(&state, &code).await, StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_rejected() {
+        let state = test_state();
+        let req = CreateLinkRequest {
+            url: "https://example.com".to_string(),
+            ttl_secs: Some(0),
+        };
+        let response = create_link(State(Arc::clone(&state)), Json(req))
+            .await
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(state.links.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_huge_ttl_rejected() {
+        let state = test_state();
+        let req = CreateLinkRequest {
+            url: "https://example.com".to_string(),
+            ttl_secs: Some(u64::MAX),
+        };
+        let response = create_link(State(Arc::clone(&state)), Json(req))
+            .await
+            .into_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(state.links.read().await.is_empty());
+    }
+}
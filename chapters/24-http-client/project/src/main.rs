@@ -5,11 +5,25 @@
 //   api-cli get <URL>
 //   api-cli post <URL> --json '{"key": "value"}'
 //   api-cli get <URL> -H "Authorization: Bearer token"
+//   api-cli get <URL> --bearer token
+//   api-cli get <URL> --basic user:pass
+//   api-cli request HEAD <URL>
+//   api-cli get <URL> --repeat 20
+//   api-cli post <URL>/login --json '{...}' --cookie-jar cookies.json
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use clap::{Parser, Subcommand};
-use reqwest::Client;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, ClientBuilder, Method, RequestBuilder};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "api-cli")]
@@ -17,6 +31,37 @@ use std::collections::HashMap;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// HTTP Basic 认证 (格式: user:pass)
+    #[arg(long, global = true, conflicts_with = "bearer")]
+    basic: Option<String>,
+
+    /// Bearer Token 认证
+    #[arg(long, global = true)]
+    bearer: Option<String>,
+
+    /// 跳过 TLS 证书校验（仅用于测试自签名证书的服务）
+    #[arg(long, global = true)]
+    insecure: bool,
+
+    /// 最多读取多少字节的响应体，超出部分截断
+    #[arg(long, global = true)]
+    max_bytes: Option<usize>,
+
+    /// 顺序发送 N 次相同的请求，输出延迟和状态码统计而不是响应体
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    repeat: Option<u64>,
+
+    /// 从文件载入 Cookie，请求结束后把收到的 Cookie 写回，多次调用之间保持登录状态
+    #[arg(long, global = true, value_name = "FILE")]
+    cookie_jar: Option<PathBuf>,
+}
+
+/// 发送请求时与具体方法无关的选项
+struct SendOptions {
+    max_bytes: Option<usize>,
+    /// Some(n) 时进入压测模式
+    repeat: Option<u64>,
 }
 
 #[derive(Subcommand)]
@@ -29,6 +74,10 @@ enum Commands {
         /// 自定义请求头 (格式: "Name: Value")
         #[arg(short = 'H', long = "header")]
         headers: Vec<String>,
+
+        /// 查询参数 (格式: key=value，可重复)
+        #[arg(short, long)]
+        query: Vec<String>,
     },
 
     /// 发送 POST 请求
@@ -43,6 +92,10 @@ enum Commands {
         /// 自定义请求头
         #[arg(short = 'H', long = "header")]
         headers: Vec<String>,
+
+        /// 查询参数 (格式: key=value，可重复)
+        #[arg(short, long)]
+        query: Vec<String>,
     },
 
     /// 发送 PUT 请求
@@ -57,6 +110,10 @@ enum Commands {
         /// 自定义请求头
         #[arg(short = 'H', long = "header")]
         headers: Vec<String>,
+
+        /// 查询参数 (格式: key=value，可重复)
+        #[arg(short, long)]
+        query: Vec<String>,
     },
 
     /// 发送 DELETE 请求
@@ -67,6 +124,31 @@ enum Commands {
         /// 自定义请求头
         #[arg(short = 'H', long = "header")]
         headers: Vec<String>,
+
+        /// 查询参数 (格式: key=value，可重复)
+        #[arg(short, long)]
+        query: Vec<String>,
+    },
+
+    /// 使用任意 HTTP 方法发送请求 (HEAD、OPTIONS 或自定义方法)
+    Request {
+        /// HTTP 方法，区分大小写，例如 HEAD、OPTIONS、PURGE
+        method: String,
+
+        /// 请求 URL
+        url: String,
+
+        /// JSON 请求体
+        #[arg(long)]
+        json: Option<String>,
+
+        /// 自定义请求头
+        #[arg(short = 'H', long = "header")]
+        headers: Vec<String>,
+
+        /// 查询参数 (格式: key=value，可重复)
+        #[arg(short, long)]
+        query: Vec<String>,
     },
 }
 
@@ -74,38 +156,147 @@ enum Commands {
 async fn main() {
     let cli = Cli::parse();
 
+    if cli.insecure {
+        eprintln!("警告: 已禁用 TLS 证书校验 (--insecure)，连接可能被中间人窃听或篡改");
+    }
+
+    let jar = match cli.cookie_jar.as_deref().map(load_cookie_jar).transpose() {
+        Ok(jar) => jar.map(Arc::new),
+        Err(e) => {
+            eprintln!("读取 Cookie 文件失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // reqwest::Client 是可复用的，内部维护连接池
-    let client = Client::new();
+    let client = match client_builder(cli.insecure, jar.as_ref()).build() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("创建 HTTP 客户端失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // --basic / --bearer 转换成一个 Authorization 请求头，和 -H 合并
+    let auth = auth_header(cli.basic.as_deref(), cli.bearer.as_deref());
+    let opts = SendOptions {
+        max_bytes: cli.max_bytes,
+        repeat: cli.repeat,
+    };
 
     let result = match cli.command {
-        Commands::Get { url, headers } => do_get(&client, &url, &headers).await,
+        Commands::Get {
+            url,
+            headers,
+            query,
+        } => do_get(&client, &url, &with_auth(headers, &auth), &query, &opts).await,
+
+        Commands::Post {
+            url,
+            json,
+            headers,
+            query,
+        } => {
+            let headers = with_auth(headers, &auth);
+            do_post(&client, &url, json, &headers, &query, &opts).await
+        }
 
-        Commands::Post { url, json, headers } => do_post(&client, &url, json, &headers).await,
+        Commands::Put {
+            url,
+            json,
+            headers,
+            query,
+        } => {
+            let headers = with_auth(headers, &auth);
+            do_put(&client, &url, json, &headers, &query, &opts).await
+        }
 
-        Commands::Put { url, json, headers } => do_put(&client, &url, json, &headers).await,
+        Commands::Delete {
+            url,
+            headers,
+            query,
+        } => do_delete(&client, &url, &with_auth(headers, &auth), &query, &opts).await,
 
-        Commands::Delete { url, headers } => do_delete(&client, &url, &headers).await,
+        Commands::Request {
+            method,
+            url,
+            json,
+            headers,
+            query,
+        } => {
+            let headers = with_auth(headers, &auth);
+            do_request(&client, &method, &url, json, &headers, &query, &opts).await
+        }
     };
 
+    // 请求失败也保存：重定向途中收到的 Cookie 同样有效
+    if let (Some(jar), Some(path)) = (&jar, &cli.cookie_jar) {
+        if let Err(e) = save_cookie_jar(jar, path) {
+            eprintln!("保存 Cookie 文件失败: {}", e);
+        }
+    }
+
     if let Err(e) = result {
         eprintln!("请求失败: {}", e);
         std::process::exit(1);
     }
 }
 
+/// 载入 Cookie 文件，文件不存在时返回空的 Cookie 存储
+///
+/// 文件每行一个 JSON 格式的 Cookie，已经过期的在载入时丢弃
+fn load_cookie_jar(path: &Path) -> Result<CookieStoreMutex, Box<dyn std::error::Error>> {
+    let store = match File::open(path) {
+        Ok(file) => CookieStore::load_json(BufReader::new(file))
+            .map_err(|e| e as Box<dyn std::error::Error>)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => CookieStore::default(),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(CookieStoreMutex::new(store))
+}
+
+/// 把 Cookie 写回文件
+///
+/// 没有过期时间的会话 Cookie 也要保存，登录接口返回的通常就是这种
+fn save_cookie_jar(jar: &CookieStoreMutex, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = File::create(path)?;
+    let store = jar.lock().map_err(|_| "Cookie 存储的锁已损坏")?;
+    store
+        .save_incl_expired_and_nonpersistent_json(&mut file)
+        .map_err(|e| e as Box<dyn std::error::Error>)?;
+    Ok(())
+}
+
+/// 根据命令行选项配置 HTTP 客户端
+///
+/// `insecure` 为 true 时接受无效证书（过期、自签名、域名不匹配），
+/// 方便调试本地的 HTTPS 服务。
+/// 传入 `jar` 时客户端自动保存响应中的 Set-Cookie，并在之后的请求中带上
+fn client_builder(insecure: bool, jar: Option<&Arc<CookieStoreMutex>>) -> ClientBuilder {
+    let builder = Client::builder().danger_accept_invalid_certs(insecure);
+    match jar {
+        Some(jar) => builder.cookie_provider(Arc::clone(jar)),
+        None => builder,
+    }
+}
+
 /// 发送 GET 请求
-async fn do_get(client: &Client, url: &str, headers: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+async fn do_get(
+    client: &Client,
+    url: &str,
+    headers: &[String],
+    query: &[String],
+    opts: &SendOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
     let parsed_headers = parse_headers(headers);
 
-    let mut req = client.get(url);
+    let mut req = apply_query(client.get(url), query)?;
 
     for (name, value) in &parsed_headers {
         req = req.header(name.as_str(), value.as_str());
     }
 
-    let response = req.send().await?;
-
-    print_response(response).await
+    send(req, opts).await
 }
 
 /// 发送 POST 请求
@@ -114,10 +305,12 @@ async fn do_post(
     url: &str,
     json: Option<String>,
     headers: &[String],
+    query: &[String],
+    opts: &SendOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let parsed_headers = parse_headers(headers);
 
-    let mut req = client.post(url);
+    let mut req = apply_query(client.post(url), query)?;
 
     for (name, value) in &parsed_headers {
         req = req.header(name.as_str(), value.as_str());
@@ -128,9 +321,7 @@ async fn do_post(
         req = req.json(&value);
     }
 
-    let response = req.send().await?;
-
-    print_response(response).await
+    send(req, opts).await
 }
 
 /// 发送 PUT 请求
@@ -139,10 +330,12 @@ async fn do_put(
     url: &str,
     json: Option<String>,
     headers: &[String],
+    query: &[String],
+    opts: &SendOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let parsed_headers = parse_headers(headers);
 
-    let mut req = client.put(url);
+    let mut req = apply_query(client.put(url), query)?;
 
     for (name, value) in &parsed_headers {
         req = req.header(name.as_str(), value.as_str());
@@ -153,24 +346,168 @@ async fn do_put(
         req = req.json(&value);
     }
 
-    let response = req.send().await?;
-
-    print_response(response).await
+    send(req, opts).await
 }
 
 /// 发送 DELETE 请求
-async fn do_delete(client: &Client, url: &str, headers: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+async fn do_delete(
+    client: &Client,
+    url: &str,
+    headers: &[String],
+    query: &[String],
+    opts: &SendOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
     let parsed_headers = parse_headers(headers);
 
-    let mut req = client.delete(url);
+    let mut req = apply_query(client.delete(url), query)?;
 
     for (name, value) in &parsed_headers {
         req = req.header(name.as_str(), value.as_str());
     }
 
-    let response = req.send().await?;
+    send(req, opts).await
+}
+
+/// 使用任意方法发送请求
+async fn do_request(
+    client: &Client,
+    method: &str,
+    url: &str,
+    json: Option<String>,
+    headers: &[String],
+    query: &[String],
+    opts: &SendOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let req = build_request(client, method, url, json, headers, query)?;
 
-    print_response(response).await
+    send(req, opts).await
+}
+
+/// 发送构造好的请求：普通模式打印响应，--repeat 模式打印统计
+async fn send(req: RequestBuilder, opts: &SendOptions) -> Result<(), Box<dyn std::error::Error>> {
+    match opts.repeat {
+        Some(n) => {
+            let stats = run_repeat(req, n).await?;
+            print!("{}", stats.summary());
+            Ok(())
+        }
+        None => {
+            let response = req.send().await?;
+            print_response(response, opts.max_bytes).await
+        }
+    }
+}
+
+/// --repeat 模式的统计结果
+#[derive(Debug, Default)]
+struct BenchStats {
+    /// 每个成功请求从发送到读完响应体的耗时
+    latencies: Vec<Duration>,
+    /// 状态码 -> 次数，BTreeMap 让输出按状态码排序
+    statuses: BTreeMap<u16, usize>,
+    /// 连接失败、读取响应失败等没有拿到完整响应的次数
+    failures: usize,
+}
+
+impl BenchStats {
+    fn summary(&self) -> String {
+        let total = self.latencies.len() + self.failures;
+        let mut out = format!(
+            "请求次数: {} (成功 {}, 失败 {})\n",
+            total,
+            self.latencies.len(),
+            self.failures
+        );
+
+        if let (Some(min), Some(max)) = (self.latencies.iter().min(), self.latencies.iter().max()) {
+            let avg = self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32;
+            out.push_str(&format!(
+                "延迟: min {:.2}ms / avg {:.2}ms / max {:.2}ms\n",
+                millis(*min),
+                millis(avg),
+                millis(*max)
+            ));
+        }
+
+        if !self.statuses.is_empty() {
+            out.push_str("状态码:\n");
+            for (status, count) in &self.statuses {
+                out.push_str(&format!("  {}: {}\n", status, count));
+            }
+        }
+        out
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// 顺序发送 n 次请求并统计
+///
+/// 单次请求失败只记一次失败，继续发送下一次，不中断整个压测
+async fn run_repeat(req: RequestBuilder, n: u64) -> Result<BenchStats, Box<dyn std::error::Error>> {
+    let mut stats = BenchStats::default();
+
+    for _ in 0..n {
+        // send() 会消耗 RequestBuilder，每次发送前复制一份；
+        // 只有流式请求体无法复制，这里的 JSON 请求体都可以
+        let req = req.try_clone().ok_or("请求体无法重复发送")?;
+
+        let start = Instant::now();
+        // 响应体也要读完，耗时才包含完整的传输时间
+        let result = match req.send().await {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                response.bytes().await.map(|_| status)
+            }
+            Err(e) => Err(e),
+        };
+        let elapsed = start.elapsed();
+
+        match result {
+            Ok(status) => {
+                stats.latencies.push(elapsed);
+                *stats.statuses.entry(status).or_insert(0) += 1;
+            }
+            Err(e) => {
+                eprintln!("请求失败: {}", e);
+                stats.failures += 1;
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+/// 按方法名构造请求
+///
+/// Method::from_bytes 接受任何合法的 token，GET、HEAD 这类标准方法得到对应的常量，
+/// 其他名字（如 PURGE）作为扩展方法原样发送；含空格等非法字符时返回错误
+fn build_request(
+    client: &Client,
+    method: &str,
+    url: &str,
+    json: Option<String>,
+    headers: &[String],
+    query: &[String],
+) -> Result<RequestBuilder, Box<dyn std::error::Error>> {
+    let method = Method::from_bytes(method.as_bytes())
+        .map_err(|_| format!("无效的 HTTP 方法 '{}'", method))?;
+    let parsed_headers = parse_headers(headers);
+
+    let mut req = apply_query(client.request(method, url), query)?;
+
+    for (name, value) in &parsed_headers {
+        req = req.header(name.as_str(), value.as_str());
+    }
+
+    if let Some(body) = json {
+        let value: Value = serde_json::from_str(&body)?;
+        req = req.json(&value);
+    }
+
+    Ok(req)
 }
 
 /// 解析请求头
@@ -188,21 +525,669 @@ fn parse_headers(headers: &[String]) -> HashMap<String, String> {
         .collect()
 }
 
+/// 解析查询参数，每一项形如 key=value
+fn parse_query(query: &[String]) -> Result<Vec<(String, String)>, String> {
+    query
+        .iter()
+        .map(|q| match q.split_once('=') {
+            Some((key, value)) => Ok((key.to_string(), value.to_string())),
+            None => Err(format!("无效的查询参数 '{}'，格式应为 key=value", q)),
+        })
+        .collect()
+}
+
+/// 把查询参数追加到 URL
+///
+/// RequestBuilder::query 会负责百分号编码，空格、& 等特殊字符不需要手动处理
+fn apply_query(req: RequestBuilder, query: &[String]) -> Result<RequestBuilder, String> {
+    let pairs = parse_query(query)?;
+    Ok(req.query(&pairs))
+}
+
+/// 根据 --basic / --bearer 生成 Authorization 头的值
+///
+/// Basic 认证的格式是 `Basic base64(user:pass)`，这里只做编码，并不加密
+fn auth_header(basic: Option<&str>, bearer: Option<&str>) -> Option<String> {
+    if let Some(credentials) = basic {
+        return Some(format!("Basic {}", BASE64.encode(credentials)));
+    }
+    bearer.map(|token| format!("Bearer {}", token))
+}
+
+/// 把认证头追加到请求头列表
+///
+/// 用户用 -H 显式指定的 Authorization 优先，此时忽略 --basic / --bearer
+fn with_auth(mut headers: Vec<String>, auth: &Option<String>) -> Vec<String> {
+    let has_explicit = parse_headers(&headers)
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("authorization"));
+
+    if let Some(value) = auth {
+        if !has_explicit {
+            headers.push(format!("Authorization: {}", value));
+        }
+    }
+    headers
+}
+
+/// 读取响应体，最多读取 `max_bytes` 字节
+///
+/// `response.text()` 会把整个响应体读进内存，这里改成逐块读取，
+/// 超过上限就停止，不再接收剩余数据。返回读到的内容以及是否被截断
+async fn read_body_limited(
+    response: &mut reqwest::Response,
+    max_bytes: Option<usize>,
+) -> reqwest::Result<(Vec<u8>, bool)> {
+    let mut body = Vec::new();
+
+    // chunk() 每次返回下一块数据，读完时返回 None
+    while let Some(chunk) = response.chunk().await? {
+        if let Some(max) = max_bytes {
+            let room = max - body.len();
+            if chunk.len() > room {
+                body.extend_from_slice(&chunk[..room]);
+                return Ok((body, true));
+            }
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok((body, false))
+}
+
 /// 打印响应
-async fn print_response(response: reqwest::Response) -> Result<(), Box<dyn std::error::Error>> {
+async fn print_response(
+    mut response: reqwest::Response,
+    max_bytes: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let status = response.status();
+    // 读取响应体之前先取出 Content-Type，决定怎样格式化
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
 
     println!("Status: {}", status);
     println!();
 
-    // 尝试解析为 JSON 并美化输出
-    let text = response.text().await?;
+    let (body, truncated) = read_body_limited(&mut response, max_bytes).await?;
+    let text = String::from_utf8_lossy(&body);
 
-    if let Ok(json) = serde_json::from_str::<Value>(&text) {
-        println!("{}", serde_json::to_string_pretty(&json)?);
-    } else {
+    // 截断的内容不是完整的 JSON，原样输出并提示
+    if truncated {
         println!("{}", text);
+        println!();
+        println!("... (响应体超过 {} 字节，已截断)", body.len());
+        return Ok(());
     }
 
+    println!("{}", format_body(content_type.as_deref(), &text));
+
     Ok(())
 }
+
+/// 按 Content-Type 格式化响应体
+///
+/// - XML（application/xml、text/xml、*+xml）: 缩进输出，格式不对时原样输出
+/// - 其他类型: 能解析为 JSON 就美化（有些 API 把 JSON 标成 text/plain），否则原样输出
+fn format_body(content_type: Option<&str>, text: &str) -> String {
+    if content_type.is_some_and(is_xml) {
+        return pretty_xml(text).unwrap_or_else(|| text.to_string());
+    }
+
+    match serde_json::from_str::<Value>(text) {
+        // Value 序列化不会失败
+        Ok(json) => serde_json::to_string_pretty(&json).unwrap_or_else(|_| text.to_string()),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// Content-Type 是否为 XML，忽略 `; charset=...` 等参数
+fn is_xml(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    mime == "application/xml" || mime == "text/xml" || mime.ends_with("+xml")
+}
+
+/// XML 中的一个片段
+#[derive(Debug, PartialEq)]
+enum XmlToken<'a> {
+    /// `<name ...>`，保存标签名和完整标签
+    Open(&'a str, &'a str),
+    /// `</name>`
+    Close(&'a str, &'a str),
+    /// 自闭合标签、声明、注释等不影响层级的片段
+    Single(&'a str),
+    /// 标签之间的文本（已去掉首尾空白）
+    Text(&'a str),
+}
+
+/// 把 XML 切分成片段，遇到没有结束的标签返回 None
+fn xml_tokens(xml: &str) -> Option<Vec<XmlToken<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = xml;
+
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            // 文本一直到下一个 '<'
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = rest[..end].trim();
+            if !text.is_empty() {
+                tokens.push(XmlToken::Text(text));
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        // 注释、CDATA、声明有各自的结束标记，内容里可能出现 '<' 或 '>'
+        let special = [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>")];
+        if let Some((_, close)) = special.iter().find(|(open, _)| rest.starts_with(open)) {
+            let end = rest.find(close)? + close.len();
+            let token = &rest[..end];
+            if token.starts_with("<![CDATA[") {
+                tokens.push(XmlToken::Text(token));
+            } else {
+                tokens.push(XmlToken::Single(token));
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        // 普通标签：找到不在引号里的 '>'，属性值里允许出现 '>'
+        let mut quote = None;
+        let end = rest.char_indices().find_map(|(i, c)| {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), c) if c == q => quote = None,
+                (None, '>') => return Some(i),
+                _ => {}
+            }
+            None
+        })?;
+        let tag = &rest[..=end];
+        let inner = &rest[1..end];
+
+        if let Some(name) = inner.strip_prefix('/') {
+            tokens.push(XmlToken::Close(name.trim(), tag));
+        } else if inner.ends_with('/') || inner.starts_with('!') {
+            // 自闭合标签，或 <!DOCTYPE ...>
+            tokens.push(XmlToken::Single(tag));
+        } else {
+            let name = inner.split_whitespace().next()?;
+            tokens.push(XmlToken::Open(name, tag));
+        }
+        rest = &rest[end + 1..];
+    }
+
+    Some(tokens)
+}
+
+/// 简单的 XML 缩进：每层缩进两个空格，只包含文本的元素保持在一行
+///
+/// 标签不匹配、没有结束、或根本没有元素时返回 None，由调用方原样输出
+fn pretty_xml(xml: &str) -> Option<String> {
+    let tokens = xml_tokens(xml)?;
+    if !tokens
+        .iter()
+        .any(|t| matches!(t, XmlToken::Open(..) | XmlToken::Single(_)))
+    {
+        return None;
+    }
+
+    let mut out = Vec::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let indent = "  ".repeat(stack.len());
+        match tokens[i] {
+            XmlToken::Open(name, tag) => {
+                // <a>text</a> 和 <a></a> 合并成一行
+                match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Some(XmlToken::Text(text)), Some(XmlToken::Close(close, end)))
+                        if *close == name =>
+                    {
+                        out.push(format!("{}{}{}{}", indent, tag, text, end));
+                        i += 3;
+                        continue;
+                    }
+                    (Some(XmlToken::Close(close, end)), _) if *close == name => {
+                        out.push(format!("{}{}{}", indent, tag, end));
+                        i += 2;
+                        continue;
+                    }
+                    _ => {}
+                }
+                out.push(format!("{}{}", indent, tag));
+                stack.push(name);
+            }
+            XmlToken::Close(name, tag) => {
+                // 结束标签必须与最近打开的标签同名
+                if stack.pop()? != name {
+                    return None;
+                }
+                out.push(format!("{}{}", "  ".repeat(stack.len()), tag));
+            }
+            XmlToken::Single(text) | XmlToken::Text(text) => {
+                out.push(format!("{}{}", indent, text));
+            }
+        }
+        i += 1;
+    }
+
+    if !stack.is_empty() {
+        return None;
+    }
+    Some(out.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insecure_client_builder() {
+        // ClientBuilder 的 Debug 输出只在关闭证书校验时包含该字段
+        let insecure = format!("{:?}", client_builder(true, None));
+        assert!(insecure.contains("danger_accept_invalid_certs"));
+
+        let secure = format!("{:?}", client_builder(false, None));
+        assert!(!secure.contains("danger_accept_invalid_certs"));
+
+        assert!(client_builder(true, None).build().is_ok());
+    }
+
+    #[test]
+    fn test_insecure_flag_parses() {
+        let cli = Cli::try_parse_from(["api-cli", "get", "https://localhost:8443", "--insecure"])
+            .unwrap();
+        assert!(cli.insecure);
+
+        let cli = Cli::try_parse_from(["api-cli", "get", "https://localhost:8443"]).unwrap();
+        assert!(!cli.insecure);
+    }
+
+    /// 启动一个测试用 HTTP 服务，按顺序为每个连接返回一个响应；None 表示不响应直接断开
+    ///
+    /// 返回服务的根 URL，以及收到的第一个请求（请求行和头部）
+    async fn serve_responses(
+        responses: Vec<Option<Vec<u8>>>,
+    ) -> (String, tokio::sync::oneshot::Receiver<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        tokio::spawn(async move {
+            let mut tx = Some(tx);
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 1024];
+                let n = socket.read(&mut request).await.unwrap_or(0);
+                if let Some(tx) = tx.take() {
+                    let _ = tx.send(String::from_utf8_lossy(&request[..n]).into_owned());
+                }
+                if let Some(response) = response {
+                    let _ = socket.write_all(&response).await;
+                }
+            }
+        });
+
+        (format!("http://{}/", addr), rx)
+    }
+
+    /// 只响应一次的 HTTP 服务
+    async fn serve_once(
+        response: impl Into<Vec<u8>>,
+    ) -> (String, tokio::sync::oneshot::Receiver<String>) {
+        serve_responses(vec![Some(response.into())]).await
+    }
+
+    /// 拼出完整的 HTTP 响应；headers 的每一行以 `\r\n` 结尾
+    ///
+    /// 都带 Connection: close，下一次请求必然使用新连接
+    fn http_response(status: u16, headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 {} X\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            headers,
+            body.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    /// 响应体为 `body_len` 个 'x' 的服务
+    async fn serve_large_body(body_len: usize) -> String {
+        serve_once(http_response(200, "", &vec![b'x'; body_len]))
+            .await
+            .0
+    }
+
+    #[tokio::test]
+    async fn test_max_bytes_truncates_large_body() {
+        let url = serve_large_body(100_000).await;
+        let mut response = Client::new().get(&url).send().await.unwrap();
+
+        let (body, truncated) = read_body_limited(&mut response, Some(1000)).await.unwrap();
+        assert!(truncated);
+        assert_eq!(body.len(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_body_under_limit_is_complete() {
+        let url = serve_large_body(500).await;
+        let mut response = Client::new().get(&url).send().await.unwrap();
+
+        let (body, truncated) = read_body_limited(&mut response, Some(1000)).await.unwrap();
+        assert!(!truncated);
+        assert_eq!(body.len(), 500);
+
+        let url = serve_large_body(5000).await;
+        let mut response = Client::new().get(&url).send().await.unwrap();
+        let (body, truncated) = read_body_limited(&mut response, None).await.unwrap();
+        assert!(!truncated);
+        assert_eq!(body.len(), 5000);
+    }
+
+    #[test]
+    fn test_xml_indented() {
+        let xml = r#"<?xml version="1.0"?><user id="1"><name>Alice</name><tags><tag>a</tag><tag/></tags><note a=">"></note></user>"#;
+        let expected = r#"<?xml version="1.0"?>
+<user id="1">
+  <name>Alice</name>
+  <tags>
+    <tag>a</tag>
+    <tag/>
+  </tags>
+  <note a=">"></note>
+</user>"#;
+
+        assert_eq!(
+            format_body(Some("application/xml; charset=utf-8"), xml),
+            expected
+        );
+        assert_eq!(format_body(Some("application/atom+xml"), xml), expected);
+        assert_eq!(format_body(Some("text/xml"), xml), expected);
+    }
+
+    #[test]
+    fn test_malformed_xml_printed_raw() {
+        for xml in [
+            "<a><b></a></b>",
+            "<a><b>text</b>",
+            "<a attr=\"x",
+            "plain text",
+        ] {
+            assert_eq!(format_body(Some("text/xml"), xml), xml);
+        }
+    }
+
+    #[test]
+    fn test_other_content_types() {
+        // JSON 仍然美化，不论 Content-Type
+        assert_eq!(
+            format_body(Some("application/json"), r#"{"a":1}"#),
+            "{\n  \"a\": 1\n}"
+        );
+        assert_eq!(format_body(None, r#"{"a":1}"#), "{\n  \"a\": 1\n}");
+
+        // 非 XML 类型即使内容像 XML 也原样输出
+        let html = "<html><body>hi</body></html>";
+        assert_eq!(format_body(Some("text/html"), html), html);
+    }
+
+    #[test]
+    fn test_basic_auth_header() {
+        let header = auth_header(Some("user:pass"), None);
+        assert_eq!(header.as_deref(), Some("Basic dXNlcjpwYXNz"));
+    }
+
+    #[test]
+    fn test_bearer_auth_header() {
+        let header = auth_header(None, Some("abc123"));
+        assert_eq!(header.as_deref(), Some("Bearer abc123"));
+
+        assert_eq!(auth_header(None, None), None);
+    }
+
+    #[test]
+    fn test_with_auth_appends_header() {
+        let auth = auth_header(None, Some("abc123"));
+        let headers = with_auth(vec!["Accept: application/json".to_string()], &auth);
+
+        let parsed = parse_headers(&headers);
+        assert_eq!(parsed["Authorization"], "Bearer abc123");
+        assert_eq!(parsed["Accept"], "application/json");
+    }
+
+    #[test]
+    fn test_explicit_authorization_wins() {
+        let auth = auth_header(Some("user:pass"), None);
+        let headers = with_auth(vec!["authorization: Token xyz".to_string()], &auth);
+
+        assert_eq!(headers, vec!["authorization: Token xyz"]);
+    }
+
+    #[test]
+    fn test_query_params_encoded() {
+        let client = Client::new();
+        let query = vec!["q=rust lang".to_string(), "filter=a&b=c".to_string()];
+
+        let req = apply_query(client.get("http://example.com/search"), &query)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            req.url().as_str(),
+            "http://example.com/search?q=rust+lang&filter=a%26b%3Dc"
+        );
+    }
+
+    #[test]
+    fn test_query_appends_to_existing() {
+        let client = Client::new();
+        let query = vec!["page=2".to_string()];
+
+        let req = apply_query(client.get("http://example.com/items?sort=asc"), &query)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(req.url().query(), Some("sort=asc&page=2"));
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        assert!(parse_query(&["novalue".to_string()]).is_err());
+        assert_eq!(
+            parse_query(&["empty=".to_string()]).unwrap(),
+            vec![("empty".to_string(), String::new())]
+        );
+    }
+
+    /// 返回 URL 和收到的请求头部分
+    async fn serve_capture() -> (String, tokio::sync::oneshot::Receiver<String>) {
+        // HEAD 的响应可以带 Content-Length，但不能有响应体
+        let (url, rx) =
+            serve_once("HTTP/1.1 200 OK\r\nContent-Length: 42\r\nConnection: close\r\n\r\n").await;
+        (format!("{}ping", url), rx)
+    }
+
+    #[tokio::test]
+    async fn test_request_head() {
+        let (url, received) = serve_capture().await;
+        let headers = vec!["X-Trace: 1".to_string()];
+
+        let req = build_request(&Client::new(), "HEAD", &url, None, &headers, &[]).unwrap();
+        let mut response = req.send().await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let request = received.await.unwrap();
+        assert!(
+            request.starts_with("HEAD /ping HTTP/1.1\r\n"),
+            "{}",
+            request
+        );
+        assert!(request.to_lowercase().contains("x-trace: 1"), "{}", request);
+
+        let (body, truncated) = read_body_limited(&mut response, None).await.unwrap();
+        assert!(body.is_empty());
+        assert!(!truncated);
+    }
+
+    #[tokio::test]
+    async fn test_request_custom_method() {
+        let (url, received) = serve_capture().await;
+
+        let req = build_request(&Client::new(), "PURGE", &url, None, &[], &[]).unwrap();
+        req.send().await.unwrap();
+
+        let request = received.await.unwrap();
+        assert!(request.starts_with("PURGE /ping "), "{}", request);
+    }
+
+    #[test]
+    fn test_invalid_method() {
+        let result = build_request(
+            &Client::new(),
+            "BAD METHOD",
+            "http://localhost/",
+            None,
+            &[],
+            &[],
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_request_subcommand_parses() {
+        let cli = Cli::try_parse_from([
+            "api-cli",
+            "request",
+            "OPTIONS",
+            "http://localhost/",
+            "-H",
+            "A: b",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Request {
+                method,
+                url,
+                headers,
+                ..
+            } => {
+                assert_eq!(method, "OPTIONS");
+                assert_eq!(url, "http://localhost/");
+                assert_eq!(headers, ["A: b"]);
+            }
+            _ => panic!("应解析为 request 子命令"),
+        }
+    }
+
+    /// 按顺序为每个连接返回一个状态码；None 表示不响应直接断开
+    async fn serve_statuses(plan: Vec<Option<u16>>) -> String {
+        let responses = plan
+            .into_iter()
+            .map(|status| status.map(|status| http_response(status, "", b"ok")))
+            .collect();
+        serve_responses(responses).await.0
+    }
+
+    #[tokio::test]
+    async fn test_repeat_counts_statuses_and_failures() {
+        let url = serve_statuses(vec![Some(200), Some(404), None, Some(200), Some(500)]).await;
+        let req = build_request(&Client::new(), "GET", &url, None, &[], &[]).unwrap();
+
+        let stats = run_repeat(req, 5).await.unwrap();
+
+        // 中途断开的那一次记为失败，后面的请求照常发送
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.latencies.len(), 4);
+        assert_eq!(
+            stats.statuses.into_iter().collect::<Vec<_>>(),
+            vec![(200, 2), (404, 1), (500, 1)]
+        );
+    }
+
+    #[test]
+    fn test_bench_summary() {
+        let mut stats = BenchStats {
+            latencies: vec![Duration::from_millis(10), Duration::from_millis(30)],
+            failures: 1,
+            ..Default::default()
+        };
+        stats.statuses.insert(200, 2);
+
+        assert_eq!(
+            stats.summary(),
+            "请求次数: 3 (成功 2, 失败 1)\n\
+             延迟: min 10.00ms / avg 20.00ms / max 30.00ms\n\
+             状态码:\n  200: 2\n"
+        );
+
+        // 全部失败时没有延迟和状态码
+        let stats = BenchStats {
+            failures: 2,
+            ..Default::default()
+        };
+        assert_eq!(stats.summary(), "请求次数: 2 (成功 0, 失败 2)\n");
+    }
+
+    #[test]
+    fn test_repeat_flag() {
+        let cli =
+            Cli::try_parse_from(["api-cli", "get", "http://localhost/", "--repeat", "3"]).unwrap();
+        assert_eq!(cli.repeat, Some(3));
+
+        assert!(
+            Cli::try_parse_from(["api-cli", "get", "http://localhost/", "--repeat", "0"]).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cookie_jar_persists_set_cookie() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cookies.json");
+
+        // 第一次调用：文件还不存在，登录后把 Cookie 写入文件
+        let jar = Arc::new(load_cookie_jar(&path).unwrap());
+        let client = client_builder(false, Some(&jar)).build().unwrap();
+        let set_cookie = http_response(200, "Set-Cookie: session=abc123; Path=/\r\n", b"ok");
+        let (url, _) = serve_once(set_cookie).await;
+        client.get(format!("{}login", url)).send().await.unwrap();
+        save_cookie_jar(&jar, &path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            saved.contains("session") && saved.contains("abc123"),
+            "{}",
+            saved
+        );
+
+        // 第二次调用：从文件载入，请求自动带上 Cookie
+        let jar = Arc::new(load_cookie_jar(&path).unwrap());
+        let client = client_builder(false, Some(&jar)).build().unwrap();
+        let (url, received) = serve_capture().await;
+        client.get(&url).send().await.unwrap();
+
+        let request = received.await.unwrap().to_lowercase();
+        assert!(request.contains("cookie: session=abc123"), "{}", request);
+    }
+
+    #[test]
+    fn test_cookie_jar_flag() {
+        let cli =
+            Cli::try_parse_from(["api-cli", "get", "http://x", "--cookie-jar", "c.json"]).unwrap();
+        assert_eq!(cli.cookie_jar, Some(PathBuf::from("c.json")));
+    }
+}
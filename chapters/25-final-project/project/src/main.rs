@@ -23,35 +23,454 @@
 // - 统计分析（错误率、延迟分布等）
 // - 告警规则引擎
 
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::RwLock;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{Notify, RwLock};
 
-// 数据类型：支持字符串和列表
-#[derive(Clone)]
+// 数据类型：支持字符串、列表和哈希
+// 派生 Serialize/Deserialize 后可以直接写入快照文件
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum Value {
     String(String),
     List(Vec<String>),
+    /// 字段 -> 值
+    Hash(HashMap<String, String>),
 }
 
+/// 列表元素不超过这个数量时报告为 listpack，超过后报告为 quicklist
+const LIST_LISTPACK_MAX_ENTRIES: usize = 128;
+
+/// 哈希字段不超过这个数量时报告为 listpack，超过后报告为 hashtable
+const HASH_LISTPACK_MAX_ENTRIES: usize = 128;
+
+impl Value {
+    /// OBJECT ENCODING 报告的内部编码
+    ///
+    /// 这里的存储只有 String、Vec 和 HashMap 三种，编码名是仿照 Redis 按内容推算出来的：
+    /// 能解析成 i64 的字符串是 int，元素较少的列表是 listpack；
+    /// 哈希字段不超过 HASH_LISTPACK_MAX_ENTRIES 个时是 listpack，否则是 hashtable
+    fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(s) if s.parse::<i64>().is_ok() => "int",
+            Value::String(_) => "raw",
+            Value::List(items) if items.len() <= LIST_LISTPACK_MAX_ENTRIES => "listpack",
+            Value::List(_) => "quicklist",
+            Value::Hash(fields) if fields.len() <= HASH_LISTPACK_MAX_ENTRIES => "listpack",
+            Value::Hash(_) => "hashtable",
+        }
+    }
+}
+
+/// 列表超出长度上限时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum ListOverflow {
+    /// 拒绝整条 PUSH，列表保持不变
+    #[default]
+    Reject,
+    /// 照常写入，再从另一端删除多出来的元素
+    Trim,
+}
+
+impl ListOverflow {
+    fn parse(s: &str) -> Option<ListOverflow> {
+        match s {
+            "reject" => Some(ListOverflow::Reject),
+            "trim" => Some(ListOverflow::Trim),
+            _ => None,
+        }
+    }
+}
+
+/// 列表长度上限，由 --max-list-len 和 --list-overflow 决定
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ListLimit {
+    max_len: usize,
+    overflow: ListOverflow,
+}
+
+/// 数据库个数，与 Redis 默认配置一致（SELECT 0 ~ 15）
+const DB_COUNT: usize = 16;
+
+/// 字符串值的最大字节数，与 Redis 的 proto-max-bulk-len 默认值一致
+///
+/// SETRANGE 的偏移由客户端给出，不设上限的话一条命令就能让服务器分配几 TB 内存
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// 单个数据库的内容
+///
+/// 数据和过期时间放在同一把锁后面，SETEX 这类命令可以在一次加锁内同时更新两者
+#[derive(Default)]
+struct Keyspace {
+    data: HashMap<String, Value>,
+    /// 设置了过期时间的键 -> 过期时刻
+    expires: HashMap<String, Instant>,
+}
+
+impl Keyspace {
+    fn is_expired(&self, key: &str) -> bool {
+        self.expires
+            .get(key)
+            .is_some_and(|&deadline| deadline <= Instant::now())
+    }
+
+    /// 读取键，已过期的键视为不存在
+    ///
+    /// 读锁下不能删除，真正的清理留给下一次写操作（惰性删除）
+    fn get(&self, key: &str) -> Option<&Value> {
+        if self.is_expired(key) {
+            None
+        } else {
+            self.data.get(key)
+        }
+    }
+
+    /// 写操作前调用：如果键已过期，先把它真正删除
+    fn purge_expired(&mut self, key: &str) {
+        if self.is_expired(key) {
+            self.data.remove(key);
+            self.expires.remove(key);
+        }
+    }
+}
+
+/// 单个数据库
+type Db = RwLock<Keyspace>;
+
 struct Store {
-    data: RwLock<HashMap<String, Value>>,
-    // TODO: 添加过期时间管理
-    // expires: RwLock<HashMap<String, Instant>>,
+    // 每个数据库各自一把锁，不同库之间的读写互不阻塞
+    dbs: Vec<Db>,
+    /// 事务闸门：普通命令和保存快照持读锁，EXEC 持写锁
+    ///
+    /// EXEC 执行期间其他连接的命令都会在这里等待，事务里的命令不会和它们交错
+    exec_gate: RwLock<()>,
+    /// (库编号, 键) -> 正在 BLPOP 这个键的客户端共用的通知器
+    ///
+    /// 只在查找/插入时短暂持锁，不会跨 await，所以用 std 的 Mutex 即可
+    waiters: Mutex<HashMap<(usize, String), Arc<Notify>>>,
+    /// LPUSH / RPUSH 的列表长度上限，None 表示不限制
+    list_limit: Option<ListLimit>,
+    /// 订阅者 id -> 订阅者，PUBLISH 时遍历这里找到要推送的连接
+    subscribers: Mutex<HashMap<u64, Subscriber>>,
+    /// 下一个订阅者 id
+    next_subscriber: AtomicU64,
+}
+
+/// 一个订阅了频道或模式的连接
+struct Subscriber {
+    /// 推送消息的发送端，接收端在连接自己的 Session 里
+    tx: UnboundedSender<String>,
+    /// SUBSCRIBE 的精确频道名
+    channels: BTreeSet<String>,
+    /// PSUBSCRIBE 的 glob 模式；BTreeSet 保证多个模式命中时总是选同一个
+    patterns: BTreeSet<String>,
 }
 
 impl Store {
     fn new() -> Self {
         Store {
-            data: RwLock::new(HashMap::new()),
+            dbs: (0..DB_COUNT).map(|_| RwLock::default()).collect(),
+            exec_gate: RwLock::default(),
+            waiters: Mutex::default(),
+            list_limit: None,
+            subscribers: Mutex::default(),
+            next_subscriber: AtomicU64::new(0),
+        }
+    }
+
+    fn db(&self, index: usize) -> &Db {
+        &self.dbs[index]
+    }
+
+    /// 取得某个键的通知器，不存在时创建
+    fn waiter(&self, db: usize, key: &str) -> Arc<Notify> {
+        let mut waiters = self.waiters.lock().unwrap();
+        Arc::clone(waiters.entry((db, key.to_string())).or_default())
+    }
+
+    /// 唤醒所有正在等待这个键的 BLPOP
+    fn notify_waiters(&self, db: usize, key: &str) {
+        let waiters = self.waiters.lock().unwrap();
+        if let Some(notify) = waiters.get(&(db, key.to_string())) {
+            notify.notify_waiters();
+        }
+    }
+
+    /// 订阅一个频道（pattern 为 true 时是 glob 模式），返回这个连接当前的订阅总数
+    ///
+    /// 连接第一次订阅时创建消息通道，接收端放进 session.inbox
+    fn subscribe(&self, session: &mut Session, name: &str, pattern: bool) -> usize {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let id = match &session.inbox {
+            Some((id, _)) => *id,
+            None => {
+                let id = self.next_subscriber.fetch_add(1, Ordering::Relaxed);
+                let (tx, rx) = mpsc::unbounded_channel();
+                subscribers.insert(
+                    id,
+                    Subscriber {
+                        tx,
+                        channels: BTreeSet::new(),
+                        patterns: BTreeSet::new(),
+                    },
+                );
+                session.inbox = Some((id, rx));
+                id
+            }
+        };
+
+        let sub = subscribers
+            .get_mut(&id)
+            .expect("订阅者在连接存活期间不会被删除");
+        if pattern {
+            sub.patterns.insert(name.to_string());
+        } else {
+            sub.channels.insert(name.to_string());
+        }
+        sub.channels.len() + sub.patterns.len()
+    }
+
+    /// 向频道发布消息，返回收到消息的订阅者数量
+    ///
+    /// 每个订阅者最多收到一次：精确订阅优先，否则用第一个匹配的模式；
+    /// 连接已经断开（发送失败）的订阅者顺便删除
+    fn publish(&self, channel: &str, message: &str) -> usize {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let mut receivers = 0;
+        subscribers.retain(|_, sub| {
+            let push = if sub.channels.contains(channel) {
+                format!("*3\n$message\n${}\n${}\n", channel, message)
+            } else if let Some(pattern) = sub.patterns.iter().find(|p| glob_match(p, channel)) {
+                format!("*4\n$pmessage\n${}\n${}\n${}\n", pattern, channel, message)
+            } else {
+                return true;
+            };
+            let alive = sub.tx.send(push).is_ok();
+            if alive {
+                receivers += 1;
+            }
+            alive
+        });
+        receivers
+    }
+
+    /// 没有人再等待这个键时删除通知器，避免表无限增长
+    fn release_waiter(&self, db: usize, key: &str) {
+        let mut waiters = self.waiters.lock().unwrap();
+        let id = (db, key.to_string());
+        if waiters.get(&id).is_some_and(|n| Arc::strong_count(n) == 1) {
+            waiters.remove(&id);
+        }
+    }
+
+    /// 复制所有数据库的内容，用于写快照
+    ///
+    /// 先按编号顺序拿到全部读锁再复制，得到的是同一时刻的一致视图；
+    /// 写命令只会锁一个库，所以按固定顺序加锁不会死锁
+    async fn snapshot(&self) -> Vec<DbSnapshot> {
+        let mut guards = Vec::with_capacity(self.dbs.len());
+        for db in &self.dbs {
+            guards.push(db.read().await);
+        }
+
+        let now = Instant::now();
+        let unix_now = unix_millis(SystemTime::now());
+
+        guards
+            .iter()
+            .map(|ks| {
+                // Instant 只在本进程内有意义，换算成 Unix 毫秒时间戳再保存
+                let expires: HashMap<String, u64> = ks
+                    .expires
+                    .iter()
+                    .filter(|(_, &deadline)| deadline > now)
+                    .map(|(key, &deadline)| {
+                        let remaining = deadline.duration_since(now).as_millis() as u64;
+                        (key.clone(), unix_now + remaining)
+                    })
+                    .collect();
+
+                let data = ks
+                    .data
+                    .iter()
+                    .filter(|(key, _)| !ks.is_expired(key))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+
+                DbSnapshot { data, expires }
+            })
+            .collect()
+    }
+
+    /// 从快照恢复，快照中已经过期的键直接丢弃
+    fn from_snapshot(snapshot: Vec<DbSnapshot>) -> Store {
+        let store = Store::new();
+        let now = Instant::now();
+        let unix_now = unix_millis(SystemTime::now());
+
+        for (db, snap) in store.dbs.iter().zip(snapshot) {
+            // 还没有其他任务访问 store，try_write 一定成功
+            let mut ks = db.try_write().expect("新建的 store 不应被锁住");
+            for (key, value) in snap.data {
+                match snap.expires.get(&key) {
+                    Some(&at) if at <= unix_now => continue,
+                    Some(&at) => {
+                        let remaining = Duration::from_millis(at - unix_now);
+                        ks.expires.insert(key.clone(), now + remaining);
+                    }
+                    None => {}
+                }
+                ks.data.insert(key, value);
+            }
         }
+
+        store
+    }
+}
+
+/// 单个数据库的快照
+#[derive(Default, Serialize, Deserialize)]
+struct DbSnapshot {
+    data: HashMap<String, Value>,
+    /// 键 -> 过期时刻（Unix 毫秒时间戳）
+    expires: HashMap<String, u64>,
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// 把整个 store 以 JSON 写入快照文件
+///
+/// 先写临时文件再 rename，进程中途崩溃也不会留下写了一半的快照
+async fn save_snapshot(store: &Store, path: &Path) -> io::Result<()> {
+    let snapshot = {
+        // 和普通命令一样持闸门读锁，不会保存到只执行了一半的事务
+        let _gate = store.exec_gate.read().await;
+        store.snapshot().await
+    };
+    // 序列化和写文件都在锁外进行
+    let json = serde_json::to_vec(&snapshot)?;
+
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, json).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+/// 读取快照文件，文件不存在时返回 None
+fn load_snapshot(path: &Path) -> io::Result<Option<Store>> {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let snapshot: Vec<DbSnapshot> = serde_json::from_slice(&json)?;
+    Ok(Some(Store::from_snapshot(snapshot)))
+}
+
+/// 快照文件的默认路径
+const SNAPSHOT_FILE: &str = "dump.json";
+
+/// 单个连接的状态
+///
+/// 订阅、事务等连接级状态都放在这里，RESET 会把它们一起恢复成默认值
+#[derive(Default)]
+struct Session {
+    /// 当前选中的数据库编号
+    db: usize,
+    /// MULTI 之后排队等待 EXEC 的命令；None 表示不在事务中
+    queue: Option<Vec<String>>,
+    /// (订阅者 id, 推送消息的接收端)；第一次 SUBSCRIBE / PSUBSCRIBE 之后才有
+    inbox: Option<(u64, UnboundedReceiver<String>)>,
+}
+
+/// 等待下一条推送给这个连接的消息；没有订阅时永远不返回
+async fn next_push(session: &mut Session) -> Option<String> {
+    match session.inbox.as_mut() {
+        Some((_, rx)) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 启动参数
+#[derive(Default)]
+struct Config {
+    /// 把每条命令及其响应打印到 stderr，用于调试
+    trace: bool,
+    /// 每隔多久把数据写入快照文件，None 表示不保存
+    save_interval: Option<Duration>,
+    /// 单个连接最多执行多少条命令，None 表示不限制
+    max_commands: Option<u64>,
+    /// 列表最多容纳多少个元素，None 表示不限制
+    max_list_len: Option<usize>,
+    /// 超出 max_list_len 时拒绝还是裁剪
+    list_overflow: ListOverflow,
+}
+
+impl Config {
+    fn from_args(args: &[String]) -> Config {
+        let mut config = Config::default();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--trace" => config.trace = true,
+                "--save" => match iter.next().and_then(|s| s.parse::<u64>().ok()) {
+                    Some(secs) if secs > 0 => {
+                        config.save_interval = Some(Duration::from_secs(secs))
+                    }
+                    _ => eprintln!("--save 需要一个正整数秒数，已忽略"),
+                },
+                // 0 表示不限制
+                "--max-commands-per-conn" => {
+                    match iter.next().and_then(|s| s.parse::<u64>().ok()) {
+                        Some(n) => config.max_commands = Some(n).filter(|&n| n > 0),
+                        None => eprintln!("--max-commands-per-conn 需要一个整数，已忽略"),
+                    }
+                }
+                // 0 表示不限制
+                "--max-list-len" => match iter.next().and_then(|s| s.parse::<usize>().ok()) {
+                    Some(n) => config.max_list_len = Some(n).filter(|&n| n > 0),
+                    None => eprintln!("--max-list-len 需要一个整数，已忽略"),
+                },
+                "--list-overflow" => match iter.next().and_then(|s| ListOverflow::parse(s)) {
+                    Some(overflow) => config.list_overflow = overflow,
+                    None => eprintln!("--list-overflow 只能是 reject 或 trim，已忽略"),
+                },
+                other => eprintln!("忽略未知参数: {}", other),
+            }
+        }
+
+        config
+    }
+
+    /// 没有设置 --max-list-len 时返回 None
+    fn list_limit(&self) -> Option<ListLimit> {
+        self.max_list_len.map(|max_len| ListLimit {
+            max_len,
+            overflow: self.list_overflow,
+        })
     }
 }
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = Arc::new(Config::from_args(&args));
+
     let addr = "127.0.0.1:6379";
     let listener = TcpListener::bind(addr).await.unwrap();
 
@@ -59,37 +478,155 @@ async fn main() {
     println!("\n已实现的命令:");
     println!("  SET key value");
     println!("  GET key");
-    println!("  DEL key");
+    println!("  STRLEN key");
+    println!("  APPEND key value");
+    println!("  DEL key [key ...]");
+    println!("  UNLINK key [key ...]");
+    println!("  KEYS pattern");
+    println!("  RANDOMKEY");
     println!("  LPUSH key value [value ...]");
+    println!("  RPUSH key value [value ...]");
     println!("  LRANGE key start stop");
-    println!("\n待实现:");
-    println!("  EXPIRE, HSET, HGET, PUBLISH, SUBSCRIBE...\n");
+    println!("  LINDEX key index");
+    println!("  LSET key index value");
+    println!("  LINSERT key BEFORE|AFTER pivot value");
+    println!("  SORT key [ALPHA] [DESC]");
+    println!("  BLPOP key timeout");
+    println!("  SELECT index");
+    println!("  RESET");
+    println!("  MULTI / EXEC / DISCARD");
+    println!("  INCR key");
+    println!("  HSET key field value [field value ...]");
+    println!("  HGET key field");
+    println!("  HINCRBY key field increment");
+    println!("  SETEX key seconds value");
+    println!("  EXPIRE key seconds");
+    println!("  EXPIREAT key unix-timestamp");
+    println!("  PERSIST key");
+    println!("  TTL key");
+    println!("  OBJECT ENCODING key");
+    println!("  SUBSCRIBE channel [channel ...]");
+    println!("  PSUBSCRIBE pattern [pattern ...]");
+    println!("  PUBLISH channel message\n");
+
+    let snapshot_path = PathBuf::from(SNAPSHOT_FILE);
+    let mut store = match load_snapshot(&snapshot_path) {
+        Ok(Some(store)) => {
+            println!("已从 {} 恢复数据", snapshot_path.display());
+            store
+        }
+        Ok(None) => Store::new(),
+        Err(e) => {
+            eprintln!("无法读取快照 {}: {}", snapshot_path.display(), e);
+            std::process::exit(1);
+        }
+    };
+    store.list_limit = config.list_limit();
+    let store = Arc::new(store);
 
-    let store = Arc::new(Store::new());
+    if let Some(interval) = config.save_interval {
+        println!(
+            "每 {} 秒保存快照到 {}",
+            interval.as_secs(),
+            snapshot_path.display()
+        );
+        let store = Arc::clone(&store);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // interval 的第一次 tick 立即完成，跳过它
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = save_snapshot(&store, &snapshot_path).await {
+                    eprintln!("保存快照失败: {}", e);
+                }
+            }
+        });
+    }
 
     loop {
-        let (socket, _) = listener.accept().await.unwrap();
+        let (socket, peer) = listener.accept().await.unwrap();
         let store = Arc::clone(&store);
+        let config = Arc::clone(&config);
 
         tokio::spawn(async move {
-            handle_client(socket, store).await;
+            handle_client(socket, peer, store, config).await;
         });
     }
 }
 
-async fn handle_client(mut socket: TcpStream, store: Arc<Store>) {
+async fn handle_client(
+    mut socket: TcpStream,
+    peer: SocketAddr,
+    store: Arc<Store>,
+    config: Arc<Config>,
+) {
     let (reader, mut writer) = socket.split();
     let mut reader = BufReader::new(reader);
-    let mut line = String::new();
+    let mut buf = Vec::new();
+    let mut session = Session::default();
+    let mut commands: u64 = 0;
 
     loop {
-        line.clear();
+        // 同时等待客户端的下一条命令和订阅推送的消息
+        //
+        // read_line 不是取消安全的：推送先到时，已读到一半的命令会被丢掉。
+        // read_until 被取消时已读的字节都留在 buf 里，下一轮接着读完这一行，
+        // 所以 buf 只在读到完整的一行之后才清空
+        let read = tokio::select! {
+            n = reader.read_until(b'\n', &mut buf) => n.unwrap_or(0),
+            Some(push) = next_push(&mut session) => {
+                if writer.write_all(push.as_bytes()).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        if read == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf).into_owned();
+        buf.clear();
 
-        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+        // 超过上限的那条命令不执行，回复错误后关闭连接
+        commands += 1;
+        if config.max_commands.is_some_and(|max| commands > max) {
+            let _ = writer
+                .write_all(b"-ERR max commands per connection exceeded\n")
+                .await;
             break;
         }
 
-        let response = execute_command(line.trim(), &store).await;
+        // 只有 BLPOP 会长时间等待，等待期间要留意客户端是否已经断开：
+        // 否则之后的 LPUSH 会唤醒它，元素被弹出却发不出去，就这样丢了。
+        // 其他命令很快完成，不去打断，免得 EXEC 之类的只执行一半
+        let blocking = session.queue.is_none()
+            && line
+                .split_whitespace()
+                .next()
+                .is_some_and(|name| name.eq_ignore_ascii_case("BLPOP"));
+        let command = async {
+            if config.trace {
+                execute_traced(
+                    line.trim(),
+                    &store,
+                    &mut session,
+                    peer,
+                    &mut std::io::stderr(),
+                )
+                .await
+            } else {
+                execute_command(line.trim(), &store, &mut session).await
+            }
+        };
+        let response = if blocking {
+            tokio::select! {
+                response = command => response,
+                _ = client_closed(&mut reader) => break,
+            }
+        } else {
+            command.await
+        };
 
         if writer.write_all(response.as_bytes()).await.is_err() {
             break;
@@ -97,91 +634,2469 @@ async fn handle_client(mut socket: TcpStream, store: Arc<Store>) {
     }
 }
 
-async fn execute_command(line: &str, store: &Store) -> String {
+/// 客户端关闭连接（读到 EOF 或出错）时返回
+///
+/// 只看缓冲区、不消费数据：客户端在等待期间又发来了命令时一直等待，
+/// 这些命令留给下一轮循环处理
+async fn client_closed<R: AsyncBufRead + Unpin>(reader: &mut R) {
+    match reader.fill_buf().await {
+        Ok(data) if !data.is_empty() => std::future::pending().await,
+        _ => {}
+    }
+}
+
+/// 执行命令，并把「时间戳 客户端地址 命令 -> 响应」写入 log
+///
+/// 只是在 execute_command 外面包一层，不影响响应内容
+async fn execute_traced(
+    line: &str,
+    store: &Store,
+    session: &mut Session,
+    peer: SocketAddr,
+    log: &mut impl Write,
+) -> String {
+    let response = execute_command(line, store, session).await;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    // {:?} 会给字符串加引号并转义换行，多行响应也只占一行日志
+    let _ = writeln!(
+        log,
+        "[{}.{:03}] {} {:?} -> {:?}",
+        now.as_secs(),
+        now.subsec_millis(),
+        peer,
+        line,
+        response
+    );
+
+    response
+}
+
+async fn execute_command(line: &str, store: &Store, session: &mut Session) -> String {
     let parts: Vec<&str> = line.split_whitespace().collect();
 
     if parts.is_empty() {
         return "ERROR empty command\n".to_string();
     }
 
-    match parts[0].to_uppercase().as_str() {
-        "SET" if parts.len() >= 3 => {
+    // 先查命令表：未知命令和参数个数不对的命令在这里统一拒绝，
+    // 下面的各个分支可以放心按下标取参数
+    let name = parts[0].to_uppercase();
+    let spec = match find_command(&name) {
+        Some(spec) => spec,
+        None => return "-ERROR unknown command\n".to_string(),
+    };
+    if !spec.accepts(parts.len()) {
+        return format!(
+            "-ERR wrong number of arguments for '{}' command\n",
+            spec.name.to_lowercase()
+        );
+    }
+
+    // 事务控制命令，以及 MULTI 之后的排队
+    match name.as_str() {
+        "MULTI" if session.queue.is_some() => {
+            return "-ERR MULTI calls can not be nested\n".to_string();
+        }
+        "MULTI" => {
+            session.queue = Some(Vec::new());
+            return "+OK\n".to_string();
+        }
+        "EXEC" => return exec_transaction(store, session).await,
+        "DISCARD" => {
+            return match session.queue.take() {
+                Some(_) => "+OK\n".to_string(),
+                None => "-ERR DISCARD without MULTI\n".to_string(),
+            };
+        }
+        // EXEC 持有闸门写锁，事务里的 BLPOP 一旦等待就没人能 LPUSH 唤醒它
+        "BLPOP" if session.queue.is_some() => {
+            return "-ERR BLPOP is not allowed in MULTI\n".to_string();
+        }
+        // RESET 照常立即执行，顺带丢弃排队的命令
+        "RESET" => {}
+        _ => {
+            if let Some(queue) = session.queue.as_mut() {
+                queue.push(line.to_string());
+                return "+QUEUED\n".to_string();
+            }
+        }
+    }
+
+    // BLPOP 可能长时间等待，这里不持有闸门，否则会一直挡住 EXEC；
+    // 它只在真正弹出元素时在 blpop 内部短暂持有
+    let _gate = match name.as_str() {
+        "BLPOP" => None,
+        _ => Some(store.exec_gate.read().await),
+    };
+    run_command(&name, &parts, store, session).await
+}
+
+/// 依次执行事务里排队的命令，返回由各自回复组成的数组
+///
+/// 整个过程持有闸门写锁，其他连接的命令要等事务全部执行完才能继续
+async fn exec_transaction(store: &Store, session: &mut Session) -> String {
+    let queue = match session.queue.take() {
+        Some(queue) => queue,
+        None => return "-ERR EXEC without MULTI\n".to_string(),
+    };
+
+    let _gate = store.exec_gate.write().await;
+    let mut replies = Vec::with_capacity(queue.len());
+    for line in &queue {
+        // 入队时已经校验过命令名和参数个数
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let name = parts[0].to_uppercase();
+        replies.push(run_command(&name, &parts, store, session).await);
+    }
+
+    // 每条回复本身已经是完整的一段，直接拼在数组头后面
+    format!("*{}\n{}", replies.len(), replies.concat())
+}
+
+/// 执行一条已经通过命令表校验的命令
+async fn run_command(name: &str, parts: &[&str], store: &Store, session: &mut Session) -> String {
+    // 所有数据命令都作用于当前连接选中的数据库
+    let db = store.db(session.db);
+
+    match name {
+        "SET" => {
             let key = parts[1].to_string();
             let value = parts[2..].join(" ");
-            store.data.write().await.insert(key, Value::String(value));
+            let mut ks = db.write().await;
+            // 与 Redis 一致：SET 会清除之前的过期时间
+            ks.expires.remove(&key);
+            ks.data.insert(key, Value::String(value));
             "+OK\n".to_string()
         }
 
-        "GET" if parts.len() == 2 => {
-            let data = store.data.read().await;
-            match data.get(parts[1]) {
+        "SETEX" => {
+            let seconds = match parts[2].parse::<i64>() {
+                // 与 Redis 一致，换算成毫秒后必须放得进 i64，快照里也按毫秒保存
+                Ok(n) if n > 0 && n.checked_mul(1000).is_some() => n as u64,
+                _ => return "-ERR invalid expire time in 'setex' command\n".to_string(),
+            };
+            // 秒数大到 Instant 放不下时，直接相加会 panic
+            let deadline = match Instant::now().checked_add(Duration::from_secs(seconds)) {
+                Some(deadline) => deadline,
+                None => return "-ERR invalid expire time in 'setex' command\n".to_string(),
+            };
+            let key = parts[1].to_string();
+            let value = parts[3..].join(" ");
+
+            // 一次写锁内同时写入值和过期时间，其他连接不会看到「有值但没有 TTL」的中间状态
+            let mut ks = db.write().await;
+            ks.expires.insert(key.clone(), deadline);
+            ks.data.insert(key, Value::String(value));
+            "+OK\n".to_string()
+        }
+
+        "EXPIRE" => {
+            let seconds: i64 = match parts[2].parse() {
+                Ok(n) => n,
+                Err(_) => return "-ERR value is not an integer\n".to_string(),
+            };
+            let key = parts[1];
+            // 过期时间不是正数时为 None，表示键立即删除
+            if seconds.checked_mul(1000).is_none() {
+                return "-ERR invalid expire time in 'expire' command\n".to_string();
+            }
+            let deadline = if seconds > 0 {
+                match Instant::now().checked_add(Duration::from_secs(seconds as u64)) {
+                    Some(deadline) => Some(deadline),
+                    None => return "-ERR invalid expire time in 'expire' command\n".to_string(),
+                }
+            } else {
+                None
+            };
+
+            let mut ks = db.write().await;
+            ks.purge_expired(key);
+            if !ks.data.contains_key(key) {
+                return ":0\n".to_string();
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    ks.expires.insert(key.to_string(), deadline);
+                }
+                None => {
+                    ks.data.remove(key);
+                    ks.expires.remove(key);
+                }
+            }
+            ":1\n".to_string()
+        }
+
+        "EXPIREAT" => {
+            let timestamp: i64 = match parts[2].parse() {
+                Ok(n) => n,
+                Err(_) => return "-ERR value is not an integer\n".to_string(),
+            };
+            let key = parts[1];
+            let invalid = || "-ERR invalid expire time in 'expireat' command\n".to_string();
+            // 与 Redis 一致，换算成毫秒后必须放得进 i64，快照里也按毫秒保存
+            if timestamp.checked_mul(1000).is_none() {
+                return invalid();
+            }
+
+            // 绝对时间是 SystemTime，过期表里存的是 Instant，按「距现在还有多久」换算；
+            // 时间点已经过去时为 None。两次加法都可能溢出，不能直接用 `+`
+            let at = match UNIX_EPOCH.checked_add(Duration::from_secs(timestamp.max(0) as u64)) {
+                Some(at) => at,
+                None => return invalid(),
+            };
+            let deadline = match at.duration_since(SystemTime::now()) {
+                Ok(remaining) if !remaining.is_zero() => {
+                    match Instant::now().checked_add(remaining) {
+                        Some(deadline) => Some(deadline),
+                        None => return invalid(),
+                    }
+                }
+                _ => None,
+            };
+
+            let mut ks = db.write().await;
+            ks.purge_expired(key);
+            if !ks.data.contains_key(key) {
+                return ":0\n".to_string();
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    ks.expires.insert(key.to_string(), deadline);
+                }
+                // 时间点已经过去，键立即删除
+                None => {
+                    ks.data.remove(key);
+                    ks.expires.remove(key);
+                }
+            }
+            ":1\n".to_string()
+        }
+
+        "PERSIST" => {
+            let key = parts[1];
+            let mut ks = db.write().await;
+            // 已经过期的键视为不存在
+            ks.purge_expired(key);
+            match ks.expires.remove(key) {
+                Some(_) => ":1\n".to_string(),
+                None => ":0\n".to_string(),
+            }
+        }
+
+        "TTL" => {
+            let key = parts[1];
+            let ks = db.read().await;
+            if ks.get(key).is_none() {
+                return ":-2\n".to_string();
+            }
+            match ks.expires.get(key) {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    // 四舍五入到秒
+                    format!(":{}\n", (remaining.as_millis() + 500) / 1000)
+                }
+                None => ":-1\n".to_string(),
+            }
+        }
+
+        "GET" => {
+            let ks = db.read().await;
+            match ks.get(parts[1]) {
                 Some(Value::String(s)) => format!("${}\n", s),
-                Some(Value::List(_)) => "-WRONGTYPE\n".to_string(),
+                Some(_) => "-WRONGTYPE\n".to_string(),
+                None => "$-1\n".to_string(),
+            }
+        }
+
+        "INCR" => {
+            let mut ks = db.write().await;
+            ks.purge_expired(parts[1]);
+            let current = match ks.data.get(parts[1]) {
+                Some(Value::String(s)) => match s.parse::<i64>() {
+                    Ok(n) => n,
+                    Err(_) => return "-ERR value is not an integer or out of range\n".to_string(),
+                },
+                Some(_) => return "-WRONGTYPE\n".to_string(),
+                None => 0,
+            };
+            let next = match current.checked_add(1) {
+                Some(n) => n,
+                None => return "-ERR increment or decrement would overflow\n".to_string(),
+            };
+            // 只改值，不动过期时间
+            ks.data
+                .insert(parts[1].to_string(), Value::String(next.to_string()));
+            format!(":{}\n", next)
+        }
+
+        "HSET" => {
+            // 字段和值必须成对出现
+            if parts.len() % 2 != 0 {
+                return "-ERR wrong number of arguments for 'hset' command\n".to_string();
+            }
+            let mut ks = db.write().await;
+            ks.purge_expired(parts[1]);
+            let fields = match ks
+                .data
+                .entry(parts[1].to_string())
+                .or_insert_with(|| Value::Hash(HashMap::new()))
+            {
+                Value::Hash(fields) => fields,
+                _ => return "-WRONGTYPE\n".to_string(),
+            };
+            // 返回新增的字段数，覆盖已有字段不计数
+            let added = parts[2..]
+                .chunks(2)
+                .filter(|pair| {
+                    fields
+                        .insert(pair[0].to_string(), pair[1].to_string())
+                        .is_none()
+                })
+                .count();
+            format!(":{}\n", added)
+        }
+
+        "HGET" => {
+            let ks = db.read().await;
+            match ks.get(parts[1]) {
+                Some(Value::Hash(fields)) => match fields.get(parts[2]) {
+                    Some(value) => format!("${}\n", value),
+                    None => "$-1\n".to_string(),
+                },
+                Some(_) => "-WRONGTYPE\n".to_string(),
+                None => "$-1\n".to_string(),
+            }
+        }
+
+        "HINCRBY" => {
+            let increment: i64 = match parts[3].parse() {
+                Ok(n) => n,
+                Err(_) => return "-ERR value is not an integer or out of range\n".to_string(),
+            };
+            let mut ks = db.write().await;
+            ks.purge_expired(parts[1]);
+            // 先检查类型，不存在的键才新建哈希；字符串等其他类型保持不变
+            let fields = match ks
+                .data
+                .entry(parts[1].to_string())
+                .or_insert_with(|| Value::Hash(HashMap::new()))
+            {
+                Value::Hash(fields) => fields,
+                _ => return "-WRONGTYPE\n".to_string(),
+            };
+            // 字段不存在时从 0 开始
+            let current = match fields.get(parts[2]) {
+                Some(value) => match value.parse::<i64>() {
+                    Ok(n) => n,
+                    Err(_) => return "-ERR hash value is not an integer\n".to_string(),
+                },
+                None => 0,
+            };
+            let next = match current.checked_add(increment) {
+                Some(n) => n,
+                None => return "-ERR increment or decrement would overflow\n".to_string(),
+            };
+            fields.insert(parts[2].to_string(), next.to_string());
+            format!(":{}\n", next)
+        }
+
+        "STRLEN" => {
+            let ks = db.read().await;
+            match ks.get(parts[1]) {
+                // String::len 就是 UTF-8 字节数，与 Redis 一致
+                Some(Value::String(s)) => format!(":{}\n", s.len()),
+                Some(_) => "-WRONGTYPE\n".to_string(),
+                None => ":0\n".to_string(),
+            }
+        }
+
+        "GETRANGE" => {
+            let (start, end) = match (parts[2].parse::<i64>(), parts[3].parse::<i64>()) {
+                (Ok(start), Ok(end)) => (start, end),
+                _ => return "-ERR value is not an integer\n".to_string(),
+            };
+
+            let ks = db.read().await;
+            match ks.get(parts[1]) {
+                Some(Value::String(s)) => {
+                    // 按字节截取，与 LRANGE 一样支持负数下标
+                    let bytes = s.as_bytes();
+                    let sub = match list_range(bytes.len(), start, end) {
+                        Some((start, end)) => String::from_utf8_lossy(&bytes[start..=end]),
+                        None => "".into(),
+                    };
+                    format!("${}\n", sub)
+                }
+                Some(_) => "-WRONGTYPE\n".to_string(),
+                None => "$\n".to_string(),
+            }
+        }
+
+        "SETRANGE" => {
+            let offset = match parts[2].parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => return "-ERR offset is out of range\n".to_string(),
+            };
+            let key = parts[1];
+            let value = parts[3..].join(" ");
+
+            let mut ks = db.write().await;
+            ks.purge_expired(key);
+            // 与 SET 不同，SETRANGE 修改已有的值，不会清除过期时间
+            let current = match ks.data.get(key) {
+                Some(Value::String(s)) => s.as_str(),
+                Some(_) => return "-WRONGTYPE\n".to_string(),
+                None => "",
+            };
+            let updated = match set_range(current, offset, &value) {
+                Some(s) => s,
+                None => return "-ERR string exceeds maximum allowed size\n".to_string(),
+            };
+            let len = updated.len();
+            ks.data.insert(key.to_string(), Value::String(updated));
+            format!(":{}\n", len)
+        }
+
+        "APPEND" => {
+            let value = parts[2..].join(" ");
+            let mut ks = db.write().await;
+            ks.purge_expired(parts[1]);
+
+            // entry: 键不存在时插入空字符串，再统一追加，效果与 SET 相同
+            let entry = ks
+                .data
+                .entry(parts[1].to_string())
+                .or_insert_with(|| Value::String(String::new()));
+            match entry {
+                Value::String(s) => {
+                    s.push_str(&value);
+                    format!(":{}\n", s.len())
+                }
+                _ => "-WRONGTYPE\n".to_string(),
+            }
+        }
+
+        // KEYS 要遍历整个库，复杂度是 O(n)，键很多时会长时间持有读锁、
+        // 阻塞所有写操作。生产环境的 Redis 也建议用 SCAN 代替
+        "KEYS" => {
+            let ks = db.read().await;
+            let mut keys: Vec<String> = ks
+                .data
+                .keys()
+                .filter(|k| ks.get(k).is_some() && glob_match(parts[1], k))
+                .cloned()
+                .collect();
+            // HashMap 的遍历顺序不固定，排序后输出可复现
+            keys.sort();
+            format_array(&keys)
+        }
+
+        "RANDOMKEY" => {
+            let ks = db.read().await;
+            match random_key(&ks, random_seed()) {
+                Some(key) => format!("${}\n", key),
                 None => "$-1\n".to_string(),
             }
         }
 
-        "DEL" if parts.len() >= 2 => {
-            let mut data = store.data.write().await;
+        // Redis 的 UNLINK 在后台线程释放内存；这里的值都很小，直接与 DEL 相同
+        "DEL" | "UNLINK" => {
+            let mut ks = db.write().await;
             let mut count = 0;
             for key in &parts[1..] {
-                if data.remove(*key).is_some() {
+                ks.purge_expired(key);
+                // 过期时间要一起删掉，否则之后 LPUSH 等命令重新创建这个键时
+                // 会继承旧的 TTL
+                ks.expires.remove(*key);
+                if ks.data.remove(*key).is_some() {
                     count += 1;
                 }
             }
             format!(":{}\n", count)
         }
 
-        "LPUSH" if parts.len() >= 3 => {
+        "LPUSH" | "RPUSH" => {
             let key = parts[1].to_string();
             let values: Vec<String> = parts[2..].iter().map(|s| s.to_string()).collect();
 
-            let mut data = store.data.write().await;
-            let list = data
+            let mut ks = db.write().await;
+            ks.purge_expired(&key);
+            let list = match ks
+                .data
                 .entry(key)
-                .or_insert_with(|| Value::List(Vec::new()));
+                .or_insert_with(|| Value::List(Vec::new()))
+            {
+                Value::List(list) => list,
+                _ => return "-WRONGTYPE\n".to_string(),
+            };
 
-            if let Value::List(ref mut vec) = list {
-                for v in values.into_iter().rev() {
-                    vec.insert(0, v);
+            match push_values(list, values, name == "LPUSH", store.list_limit) {
+                Ok(len) => {
+                    // 先释放写锁再唤醒，被唤醒的 BLPOP 可以立即拿到锁
+                    drop(ks);
+                    store.notify_waiters(session.db, parts[1]);
+                    format!(":{}\n", len)
+                }
+                Err(reply) => {
+                    // 被拒绝的 PUSH 不应留下一个新建的空列表
+                    if list.is_empty() {
+                        ks.data.remove(parts[1]);
+                    }
+                    reply
                 }
-                format!(":{}\n", vec.len())
-            } else {
-                "-WRONGTYPE\n".to_string()
             }
         }
 
-        "LRANGE" if parts.len() == 4 => {
+        "BLPOP" => {
+            // 超时时间单位是秒，可以是小数；0 表示一直等待
+            // 像 1e30 这样 Duration 放不下的秒数，from_secs_f64 会 panic，要用 try_ 版本
+            let timeout = match parts[2].parse::<f64>().map(Duration::try_from_secs_f64) {
+                Ok(Ok(t)) => t,
+                _ => return "-ERR timeout is not a float or out of range\n".to_string(),
+            };
+            let timeout = (!timeout.is_zero()).then_some(timeout);
+
+            let key = parts[1];
+            let reply = blpop(store, session.db, key, timeout).await;
+            store.release_waiter(session.db, key);
+            reply
+        }
+
+        "LRANGE" => {
             let key = parts[1];
             let start: i64 = parts[2].parse().unwrap_or(0);
             let stop: i64 = parts[3].parse().unwrap_or(-1);
 
-            let data = store.data.read().await;
-            match data.get(key) {
-                Some(Value::List(vec)) => {
-                    let len = vec.len() as i64;
-                    let start = if start < 0 { (len + start).max(0) } else { start.min(len) } as usize;
-                    let stop = if stop < 0 { (len + stop).max(0) } else { stop.min(len - 1) } as usize;
-
-                    if start > stop {
-                        "*0\n".to_string()
-                    } else {
-                        let items: Vec<String> = vec[start..=stop]
-                            .iter()
-                            .map(|s| format!("${}", s))
-                            .collect();
-                        format!("*{}\n{}\n", items.len(), items.join("\n"))
-                    }
+            // 持锁期间只复制需要的元素，拼接响应字符串时已经不持有锁，
+            // 这样大列表的格式化不会阻塞写者
+            match lrange_snapshot(db, key, start, stop).await {
+                Ok(items) => format_array(&items),
+                Err(e) => e.to_string(),
+            }
+        }
+
+        "SORT" => {
+            let mut alpha = false;
+            let mut desc = false;
+            for option in &parts[2..] {
+                match option.to_uppercase().as_str() {
+                    "ALPHA" => alpha = true,
+                    "DESC" => desc = true,
+                    _ => return "-ERR syntax error\n".to_string(),
                 }
-                Some(Value::String(_)) => "-WRONGTYPE\n".to_string(),
-                None => "*0\n".to_string(),
+            }
+
+            // 复制一份再排序，存储的列表保持原来的顺序；排序时也不持有锁
+            let items = match lrange_snapshot(db, parts[1], 0, -1).await {
+                Ok(items) => items,
+                Err(e) => return e.to_string(),
+            };
+            match sort_items(items, alpha, desc) {
+                Ok(sorted) => format_array(&sorted),
+                Err(e) => e.to_string(),
             }
         }
 
+        "LINDEX" => {
+            let index = match parts[2].parse::<i64>() {
+                Ok(i) => i,
+                Err(_) => return "-ERR value is not an integer\n".to_string(),
+            };
+
+            let ks = db.read().await;
+            match ks.get(parts[1]) {
+                Some(Value::List(items)) => match list_index(items.len(), index) {
+                    Some(i) => format!("${}\n", items[i]),
+                    None => "$-1\n".to_string(),
+                },
+                Some(_) => "-WRONGTYPE\n".to_string(),
+                None => "$-1\n".to_string(),
+            }
+        }
+
+        "LSET" => {
+            let index = match parts[2].parse::<i64>() {
+                Ok(i) => i,
+                Err(_) => return "-ERR value is not an integer\n".to_string(),
+            };
+
+            let mut ks = db.write().await;
+            ks.purge_expired(parts[1]);
+            match ks.data.get_mut(parts[1]) {
+                // 下标规则与 LINDEX 相同，但越界是错误而不是空回复
+                Some(Value::List(items)) => match list_index(items.len(), index) {
+                    Some(i) => {
+                        items[i] = parts[3].to_string();
+                        "+OK\n".to_string()
+                    }
+                    None => "-ERR index out of range\n".to_string(),
+                },
+                Some(_) => "-WRONGTYPE\n".to_string(),
+                None => "-ERR no such key\n".to_string(),
+            }
+        }
+
+        "LINSERT" => {
+            // BEFORE 插在基准元素的位置上，AFTER 插在它的下一个位置
+            let offset = match parts[2].to_uppercase().as_str() {
+                "BEFORE" => 0,
+                "AFTER" => 1,
+                _ => return "-ERR syntax error\n".to_string(),
+            };
+            let (pivot, value) = (parts[3], parts[4]);
+
+            let mut ks = db.write().await;
+            ks.purge_expired(parts[1]);
+            match ks.data.get_mut(parts[1]) {
+                // 只以第一个等于 pivot 的元素为基准
+                Some(Value::List(items)) => match items.iter().position(|item| item == pivot) {
+                    Some(i) => {
+                        items.insert(i + offset, value.to_string());
+                        format!(":{}\n", items.len())
+                    }
+                    None => ":-1\n".to_string(),
+                },
+                Some(_) => "-WRONGTYPE\n".to_string(),
+                // 与 Redis 一致：键不存在时不创建列表，返回 0
+                None => ":0\n".to_string(),
+            }
+        }
+
+        "SELECT" => match parts[1].parse::<usize>() {
+            Ok(index) if index < DB_COUNT => {
+                session.db = index;
+                "+OK\n".to_string()
+            }
+            _ => "-ERR DB index is out of range\n".to_string(),
+        },
+
+        "RESET" => {
+            *session = Session::default();
+            "+RESET\n".to_string()
+        }
+
+        "SUBSCRIBE" | "PSUBSCRIBE" => {
+            // 每个频道回复一条确认，带上订阅后的总数
+            let pattern = name == "PSUBSCRIBE";
+            let kind = name.to_lowercase();
+            parts[1..]
+                .iter()
+                .map(|channel| {
+                    let count = store.subscribe(session, channel, pattern);
+                    format!("*3\n${}\n${}\n:{}\n", kind, channel, count)
+                })
+                .collect()
+        }
+
+        "PUBLISH" => {
+            let message = parts[2..].join(" ");
+            format!(":{}\n", store.publish(parts[1], &message))
+        }
+
         "PING" => "+PONG\n".to_string(),
 
         "QUIT" => "+OK\n".to_string(),
 
+        "OBJECT" => match parts[1].to_uppercase().as_str() {
+            "ENCODING" if parts.len() == 3 => {
+                let ks = db.read().await;
+                match ks.get(parts[2]) {
+                    Some(value) => format!("+{}\n", value.encoding()),
+                    None => "-ERR no such key\n".to_string(),
+                }
+            }
+            "ENCODING" => {
+                "-ERR wrong number of arguments for 'object|encoding' command\n".to_string()
+            }
+            _ => "-ERR unknown subcommand\n".to_string(),
+        },
+
+        "COMMAND" => match parts.get(1).map(|s| s.to_uppercase()).as_deref() {
+            None => {
+                let items: Vec<String> = COMMANDS
+                    .iter()
+                    .map(|c| format!("{} {}", c.name.to_lowercase(), c.arity))
+                    .collect();
+                format_array(&items)
+            }
+            Some("COUNT") => format!(":{}\n", COMMANDS.len()),
+            Some(_) => "-ERR unknown subcommand\n".to_string(),
+        },
+
         _ => "-ERROR unknown command\n".to_string(),
     }
 }
+
+/// 命令的元信息
+struct CommandSpec {
+    name: &'static str,
+    /// 参数个数（包含命令名本身），与 Redis 的约定相同：
+    /// 正数表示必须正好这么多，负数 -N 表示至少 N 个
+    arity: i32,
+}
+
+impl CommandSpec {
+    fn accepts(&self, argc: usize) -> bool {
+        let argc = argc as i32;
+        if self.arity >= 0 {
+            argc == self.arity
+        } else {
+            argc >= -self.arity
+        }
+    }
+}
+
+/// 已实现的全部命令，新增命令时在这里登记
+const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "SET",
+        arity: -3,
+    },
+    CommandSpec {
+        name: "SETEX",
+        arity: -4,
+    },
+    CommandSpec {
+        name: "EXPIRE",
+        arity: 3,
+    },
+    CommandSpec {
+        name: "EXPIREAT",
+        arity: 3,
+    },
+    CommandSpec {
+        name: "PERSIST",
+        arity: 2,
+    },
+    CommandSpec {
+        name: "TTL",
+        arity: 2,
+    },
+    CommandSpec {
+        name: "GET",
+        arity: 2,
+    },
+    CommandSpec {
+        name: "STRLEN",
+        arity: 2,
+    },
+    CommandSpec {
+        name: "GETRANGE",
+        arity: 4,
+    },
+    CommandSpec {
+        name: "SETRANGE",
+        arity: -4,
+    },
+    CommandSpec {
+        name: "APPEND",
+        arity: -3,
+    },
+    CommandSpec {
+        name: "KEYS",
+        arity: 2,
+    },
+    CommandSpec {
+        name: "RANDOMKEY",
+        arity: 1,
+    },
+    CommandSpec {
+        name: "DEL",
+        arity: -2,
+    },
+    CommandSpec {
+        name: "UNLINK",
+        arity: -2,
+    },
+    CommandSpec {
+        name: "LPUSH",
+        arity: -3,
+    },
+    CommandSpec {
+        name: "RPUSH",
+        arity: -3,
+    },
+    CommandSpec {
+        name: "LRANGE",
+        arity: 4,
+    },
+    CommandSpec {
+        name: "LINDEX",
+        arity: 3,
+    },
+    CommandSpec {
+        name: "LSET",
+        arity: 4,
+    },
+    CommandSpec {
+        name: "LINSERT",
+        arity: 5,
+    },
+    CommandSpec {
+        name: "SORT",
+        arity: -2,
+    },
+    CommandSpec {
+        name: "BLPOP",
+        arity: 3,
+    },
+    CommandSpec {
+        name: "SELECT",
+        arity: 2,
+    },
+    CommandSpec {
+        name: "RESET",
+        arity: 1,
+    },
+    CommandSpec {
+        name: "MULTI",
+        arity: 1,
+    },
+    CommandSpec {
+        name: "EXEC",
+        arity: 1,
+    },
+    CommandSpec {
+        name: "DISCARD",
+        arity: 1,
+    },
+    CommandSpec {
+        name: "INCR",
+        arity: 2,
+    },
+    CommandSpec {
+        name: "HSET",
+        arity: -4,
+    },
+    CommandSpec {
+        name: "HGET",
+        arity: 3,
+    },
+    CommandSpec {
+        name: "HINCRBY",
+        arity: 4,
+    },
+    CommandSpec {
+        name: "SUBSCRIBE",
+        arity: -2,
+    },
+    CommandSpec {
+        name: "PSUBSCRIBE",
+        arity: -2,
+    },
+    CommandSpec {
+        name: "PUBLISH",
+        arity: -3,
+    },
+    CommandSpec {
+        name: "PING",
+        arity: -1,
+    },
+    CommandSpec {
+        name: "QUIT",
+        arity: -1,
+    },
+    CommandSpec {
+        name: "OBJECT",
+        arity: -2,
+    },
+    CommandSpec {
+        name: "COMMAND",
+        arity: -1,
+    },
+];
+
+/// 按大写命令名查找命令表
+fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+/// 弹出列表的第一个元素；列表为空时删除这个键（与 Redis 一致）
+///
+/// 返回 Ok(None) 表示键不存在或列表为空
+async fn pop_front(db: &Db, key: &str) -> Result<Option<String>, &'static str> {
+    let mut ks = db.write().await;
+    ks.purge_expired(key);
+
+    let (item, now_empty) = match ks.data.get_mut(key) {
+        Some(Value::List(vec)) if !vec.is_empty() => {
+            let item = vec.remove(0);
+            (Some(item), vec.is_empty())
+        }
+        Some(Value::List(_)) | None => (None, false),
+        Some(_) => return Err("-WRONGTYPE\n"),
+    };
+
+    if now_empty {
+        ks.data.remove(key);
+        ks.expires.remove(key);
+    }
+    Ok(item)
+}
+
+/// BLPOP：列表有元素时立即弹出，否则等待其他客户端 LPUSH，直到超时
+///
+/// 成功时返回 `*2 $key $value`，超时返回 `$-1`
+async fn blpop(store: &Store, db: usize, key: &str, timeout: Option<Duration>) -> String {
+    let notify = store.waiter(db, key);
+    // 超时远到 Instant 放不下时，和 0 一样一直等待
+    let deadline = timeout.and_then(|t| tokio::time::Instant::now().checked_add(t));
+
+    loop {
+        // 先登记等待，再检查列表：如果反过来，检查之后、开始等待之前的
+        // LPUSH 发出的通知就会丢失，客户端一直等到超时
+        let notified = notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        // 等待期间不持有闸门，只在真正弹出时短暂持读锁：
+        // 这样 EXEC 里 RPUSH 唤醒了它，也要等整个事务执行完才能弹出
+        let popped = {
+            let _gate = store.exec_gate.read().await;
+            pop_front(store.db(db), key).await
+        };
+        match popped {
+            Ok(Some(item)) => return format_array(&[key.to_string(), item]),
+            Ok(None) => {}
+            Err(e) => return e.to_string(),
+        }
+
+        match deadline {
+            Some(deadline) => {
+                if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                    return "$-1\n".to_string();
+                }
+            }
+            None => notified.await,
+        }
+        // 被唤醒后重新检查：同时等待的其他客户端可能已经抢先弹走了元素
+    }
+}
+
+/// Redis 风格的 glob 匹配
+///
+/// - `*` 匹配任意多个字符（包括 0 个）
+/// - `?` 匹配一个字符
+/// - `[abc]`、`[a-z]` 匹配集合中的一个字符，`[^a]` 取反
+/// - `\` 转义下一个字符
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 的位置，以及当时对应的文本位置；匹配失败时回到这里，让 `*` 多吞一个字符
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match_class(&pattern[p..], text[t]),
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(2),
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+
+        match (step, star) {
+            (Some(len), _) => {
+                p += len;
+                t += 1;
+            }
+            (None, Some((star_p, star_t))) => {
+                p = star_p + 1;
+                t = star_t + 1;
+                star = Some((star_p, star_t + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+
+    // 文本用完后，剩下的模式只能是 `*`
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 匹配 `[...]` 字符集合，成功时返回集合在模式中占的长度
+///
+/// 没有闭合的 `]` 时把 `[` 当作普通字符
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let end = match pattern.iter().skip(1).position(|&ch| ch == ']') {
+        // 集合至少有一个字符，所以从第 2 个字符之后开始找 ']'
+        Some(i) => i + 1,
+        None => return (c == '[').then_some(1),
+    };
+    let mut class = &pattern[1..end];
+    let negate = matches!(class.first(), Some('^'));
+    if negate {
+        class = &class[1..];
+    }
+
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            matched |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+
+    (matched != negate).then_some(end + 1)
+}
+
+/// 从未过期的键中按 seed 选出一个，没有可用的键时返回 None
+///
+/// seed 由调用方传入，测试时可以固定它；同一个 seed 选中哪个键还取决于 HashMap 的遍历顺序
+fn random_key(ks: &Keyspace, seed: u64) -> Option<&String> {
+    let live = || ks.data.keys().filter(|k| !ks.is_expired(k));
+    let count = live().count();
+    if count == 0 {
+        return None;
+    }
+    live().nth((seed % count as u64) as usize)
+}
+
+/// 生成一个随机数
+///
+/// 标准库没有随机数生成器，但 RandomState 每次创建都带随机的密钥（防 HashDoS 用），
+/// 对空输入求哈希就得到一个随机值，选随机键足够了，不需要为此引入 rand
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// 把 LINDEX 的下标（可为负数，-1 是最后一个）换算成实际下标，越界返回 None
+fn list_index(len: usize, index: i64) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+/// 把 LRANGE 的 start/stop（可为负数）换算成闭区间下标
+///
+/// 区间为空时返回 None
+fn list_range(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize))
+    }
+}
+
+/// 从字节偏移 `offset` 开始用 `value` 覆盖 `current`
+///
+/// `offset` 超过当前长度时，中间用 `\0` 补齐；结果会超过 MAX_STRING_LEN 时返回 None
+fn set_range(current: &str, offset: usize, value: &str) -> Option<String> {
+    let end = offset
+        .checked_add(value.len())
+        .filter(|&end| end <= MAX_STRING_LEN)?;
+    let mut bytes = current.as_bytes().to_vec();
+    if bytes.len() < end {
+        bytes.resize(end, 0);
+    }
+    bytes[offset..end].copy_from_slice(value.as_bytes());
+    // 覆盖位置可能落在多字节字符中间，无效的部分用替换字符表示
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// 在读锁内复制出 LRANGE 请求的元素
+///
+/// 返回的是拥有所有权的 Vec，函数返回时读锁已经释放
+async fn lrange_snapshot(
+    db: &Db,
+    key: &str,
+    start: i64,
+    stop: i64,
+) -> Result<Vec<String>, &'static str> {
+    let ks = db.read().await;
+    match ks.get(key) {
+        Some(Value::List(vec)) => Ok(match list_range(vec.len(), start, stop) {
+            Some((start, stop)) => vec[start..=stop].to_vec(),
+            None => Vec::new(),
+        }),
+        Some(_) => Err("-WRONGTYPE\n"),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// SORT 的排序：默认按数值，alpha 时按字符串
+///
+/// 数值排序时每个元素都必须能解析成数字，否则整个命令报错；
+/// 返回的仍是原始字符串，"1.50" 不会变成 "1.5"
+fn sort_items(
+    mut items: Vec<String>,
+    alpha: bool,
+    desc: bool,
+) -> Result<Vec<String>, &'static str> {
+    if alpha {
+        items.sort();
+    } else {
+        let mut keyed = Vec::with_capacity(items.len());
+        for item in items {
+            let n: f64 = item
+                .parse()
+                .map_err(|_| "-ERR One or more scores can't be converted into double\n")?;
+            keyed.push((n, item));
+        }
+        // total_cmp 给 f64 一个全序，NaN 也不会让排序出错
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        items = keyed.into_iter().map(|(_, item)| item).collect();
+    }
+
+    if desc {
+        items.reverse();
+    }
+    Ok(items)
+}
+
+/// 把 values 整体放到列表头部（front）或尾部，返回新的长度
+///
+/// 超过长度上限时要么全部写入，要么一个也不写：
+/// - Reject：只要写入后会超出上限，就拒绝整条命令，列表保持不变
+/// - Trim：全部写入后从另一端删除多出来的元素，刚写入的值优先保留
+fn push_values(
+    list: &mut Vec<String>,
+    values: Vec<String>,
+    front: bool,
+    limit: Option<ListLimit>,
+) -> Result<usize, String> {
+    if let Some(limit) = limit {
+        if limit.overflow == ListOverflow::Reject && list.len() + values.len() > limit.max_len {
+            return Err(format!(
+                "-ERR list would exceed max length of {}\n",
+                limit.max_len
+            ));
+        }
+    }
+
+    if front {
+        list.splice(0..0, values);
+    } else {
+        list.extend(values);
+    }
+
+    if let Some(limit) = limit {
+        if list.len() > limit.max_len {
+            let excess = list.len() - limit.max_len;
+            if front {
+                list.truncate(limit.max_len);
+            } else {
+                list.drain(..excess);
+            }
+        }
+    }
+    Ok(list.len())
+}
+
+/// 数组响应：`*N` 后面每个元素一行 `$elem`
+fn format_array(items: &[String]) -> String {
+    if items.is_empty() {
+        return "*0\n".to_string();
+    }
+    let lines: Vec<String> = items.iter().map(|s| format!("${}", s)).collect();
+    format!("*{}\n{}\n", items.len(), lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 用一个新会话（db 0）执行命令
+    async fn exec(store: &Store, line: &str) -> String {
+        execute_command(line, store, &mut Session::default()).await
+    }
+
+    #[tokio::test]
+    async fn test_trace_logs_command_and_reply() {
+        let store = Store::new();
+        let peer: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let mut log = Vec::new();
+
+        let response = execute_traced(
+            "SET name redis",
+            &store,
+            &mut Session::default(),
+            peer,
+            &mut log,
+        )
+        .await;
+        assert_eq!(response, "+OK\n");
+
+        let log = String::from_utf8(log).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains("127.0.0.1:50000"));
+        assert!(log.contains(r#""SET name redis" -> "+OK\n""#));
+    }
+
+    #[tokio::test]
+    async fn test_trace_does_not_change_reply() {
+        let store = Store::new();
+        let peer: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+
+        exec(&store, "SET k v").await;
+        let plain = exec(&store, "GET k").await;
+        let traced = execute_traced(
+            "GET k",
+            &store,
+            &mut Session::default(),
+            peer,
+            &mut Vec::new(),
+        )
+        .await;
+        assert_eq!(plain, traced);
+    }
+
+    async fn store_with_list() -> Store {
+        let store = Store::new();
+        exec(&store, "LPUSH list a b c d e").await;
+        store
+    }
+
+    #[tokio::test]
+    async fn test_lrange_output_unchanged() {
+        let store = store_with_list().await;
+
+        let cases = [
+            ("LRANGE list 0 -1", "*5\n$a\n$b\n$c\n$d\n$e\n"),
+            ("LRANGE list 1 2", "*2\n$b\n$c\n"),
+            ("LRANGE list -2 -1", "*2\n$d\n$e\n"),
+            ("LRANGE list 0 100", "*5\n$a\n$b\n$c\n$d\n$e\n"),
+            ("LRANGE list 3 1", "*0\n"),
+            ("LRANGE list 10 20", "*0\n"),
+            ("LRANGE missing 0 -1", "*0\n"),
+        ];
+        for (cmd, expected) in cases {
+            assert_eq!(exec(&store, cmd).await, expected, "{}", cmd);
+        }
+
+        exec(&store, "SET s v").await;
+        assert_eq!(exec(&store, "LRANGE s 0 -1").await, "-WRONGTYPE\n");
+    }
+
+    #[tokio::test]
+    async fn test_lrange_snapshot_releases_lock() {
+        let store = store_with_list().await;
+
+        let items = lrange_snapshot(store.db(0), "list", 0, -1).await.unwrap();
+
+        // 快照返回后读锁已释放，写者可以立即拿到写锁，
+        // 之后的格式化只使用复制出来的数据
+        assert!(store.db(0).try_write().is_ok());
+        assert_eq!(items, vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(format_array(&items[1..3]), "*2\n$b\n$c\n");
+    }
+
+    #[test]
+    fn test_list_range() {
+        assert_eq!(list_range(5, 0, -1), Some((0, 4)));
+        assert_eq!(list_range(5, -100, 1), Some((0, 1)));
+        assert_eq!(list_range(5, 4, 2), None);
+        assert_eq!(list_range(0, 0, -1), None);
+    }
+
+    #[tokio::test]
+    async fn test_select_isolates_databases() {
+        let store = Store::new();
+        let mut session = Session::default();
+
+        execute_command("SET name db0", &store, &mut session).await;
+        assert_eq!(
+            execute_command("SELECT 1", &store, &mut session).await,
+            "+OK\n"
+        );
+        assert_eq!(
+            execute_command("GET name", &store, &mut session).await,
+            "$-1\n"
+        );
+
+        execute_command("SET name db1", &store, &mut session).await;
+        execute_command("SELECT 0", &store, &mut session).await;
+        assert_eq!(
+            execute_command("GET name", &store, &mut session).await,
+            "$db0\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_select_out_of_range() {
+        let store = Store::new();
+        let mut session = Session::default();
+
+        let response = execute_command("SELECT 16", &store, &mut session).await;
+        assert!(response.starts_with("-ERR"));
+        let response = execute_command("SELECT abc", &store, &mut session).await;
+        assert!(response.starts_with("-ERR"));
+        assert_eq!(session.db, 0);
+    }
+
+    #[tokio::test]
+    async fn test_setex_expires() {
+        let store = Store::new();
+
+        assert_eq!(exec(&store, "SETEX session 1 token-abc").await, "+OK\n");
+        assert_eq!(exec(&store, "GET session").await, "$token-abc\n");
+        assert_eq!(exec(&store, "TTL session").await, ":1\n");
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        assert_eq!(exec(&store, "GET session").await, "$-1\n");
+        assert_eq!(exec(&store, "TTL session").await, ":-2\n");
+    }
+
+    #[tokio::test]
+    async fn test_setex_non_positive_seconds() {
+        let store = Store::new();
+
+        for cmd in ["SETEX k 0 v", "SETEX k -5 v", "SETEX k abc v"] {
+            let response = exec(&store, cmd).await;
+            assert!(response.starts_with("-ERR invalid expire time"), "{}", cmd);
+        }
+        assert_eq!(exec(&store, "GET k").await, "$-1\n");
+    }
+
+    #[tokio::test]
+    async fn test_huge_expire_rejected() {
+        let store = Store::new();
+
+        // Instant 放不下这么远的时刻，必须回复错误而不是 panic
+        assert_eq!(
+            exec(&store, "SETEX k 9223372036854775807 v").await,
+            "-ERR invalid expire time in 'setex' command\n"
+        );
+        assert_eq!(exec(&store, "GET k").await, "$-1\n");
+
+        exec(&store, "SET k v").await;
+        assert_eq!(
+            exec(&store, "EXPIRE k 9223372036854775807").await,
+            "-ERR invalid expire time in 'expire' command\n"
+        );
+        assert_eq!(exec(&store, "TTL k").await, ":-1\n");
+
+        // Instant 放得下、但换算成毫秒超出 i64 的秒数同样拒绝，否则快照会溢出
+        assert_eq!(
+            exec(&store, "EXPIRE k 4611686018427387903").await,
+            "-ERR invalid expire time in 'expire' command\n"
+        );
+        assert_eq!(exec(&store, "TTL k").await, ":-1\n");
+    }
+
+    #[tokio::test]
+    async fn test_set_clears_ttl() {
+        let store = Store::new();
+
+        exec(&store, "SETEX k 100 v").await;
+        assert_eq!(exec(&store, "TTL k").await, ":100\n");
+
+        exec(&store, "SET k v2").await;
+        assert_eq!(exec(&store, "TTL k").await, ":-1\n");
+        assert_eq!(exec(&store, "EXPIRE missing 10").await, ":0\n");
+    }
+
+    #[tokio::test]
+    async fn test_getrange() {
+        let store = Store::new();
+        exec(&store, "SET mykey This is a string").await;
+
+        assert_eq!(exec(&store, "GETRANGE mykey 0 3").await, "$This\n");
+        assert_eq!(exec(&store, "GETRANGE mykey -3 -1").await, "$ing\n");
+        assert_eq!(
+            exec(&store, "GETRANGE mykey 0 -1").await,
+            "$This is a string\n"
+        );
+        assert_eq!(exec(&store, "GETRANGE mykey 10 100").await, "$string\n");
+
+        // 区间为空或键不存在时返回空字符串
+        assert_eq!(exec(&store, "GETRANGE mykey 100 200").await, "$\n");
+        assert_eq!(exec(&store, "GETRANGE missing 0 -1").await, "$\n");
+    }
+
+    #[tokio::test]
+    async fn test_setrange() {
+        let store = Store::new();
+        exec(&store, "SET key1 Hello World").await;
+
+        assert_eq!(exec(&store, "SETRANGE key1 6 Redis").await, ":11\n");
+        assert_eq!(exec(&store, "GET key1").await, "$Hello Redis\n");
+
+        // 偏移超过长度（包括键不存在）时用 \0 补齐
+        assert_eq!(exec(&store, "SETRANGE key2 6 Redis").await, ":11\n");
+        assert_eq!(exec(&store, "GET key2").await, "$\0\0\0\0\0\0Redis\n");
+
+        assert_eq!(
+            exec(&store, "SETRANGE key1 -1 x").await,
+            "-ERR offset is out of range\n"
+        );
+
+        exec(&store, "LPUSH list a").await;
+        assert_eq!(exec(&store, "SETRANGE list 0 x").await, "-WRONGTYPE\n");
+        assert_eq!(exec(&store, "GETRANGE list 0 -1").await, "-WRONGTYPE\n");
+    }
+
+    #[tokio::test]
+    async fn test_setrange_rejects_huge_offset() {
+        let store = Store::new();
+        exec(&store, "SET key1 Hello").await;
+
+        // 超过上限、以及 offset + len 溢出 usize，都直接拒绝，原值不变
+        let too_big = format!("SETRANGE key1 {} x", MAX_STRING_LEN);
+        let overflow = format!("SETRANGE key1 {} xy", usize::MAX);
+        for cmd in [
+            too_big.as_str(),
+            "SETRANGE key1 4000000000000 x",
+            overflow.as_str(),
+        ] {
+            assert_eq!(
+                exec(&store, cmd).await,
+                "-ERR string exceeds maximum allowed size\n"
+            );
+        }
+        assert_eq!(exec(&store, "GET key1").await, "$Hello\n");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let store = Store::new();
+        exec(&store, "SET name redis").await;
+        exec(&store, "LPUSH list c b a").await;
+        exec(&store, "SETEX temp 100 soon").await;
+        exec(&store, "SET gone x").await;
+        exec(&store, "EXPIRE gone 0").await;
+
+        let mut db3 = Session {
+            db: 3,
+            ..Session::default()
+        };
+        execute_command("SET other db3", &store, &mut db3).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.json");
+        save_snapshot(&store, &path).await.unwrap();
+
+        let loaded = load_snapshot(&path).unwrap().unwrap();
+        assert_eq!(exec(&loaded, "GET name").await, "$redis\n");
+        assert_eq!(
+            exec(&loaded, "LRANGE list 0 -1").await,
+            exec(&store, "LRANGE list 0 -1").await
+        );
+        assert_eq!(exec(&loaded, "GET temp").await, "$soon\n");
+        assert_eq!(exec(&loaded, "TTL temp").await, ":100\n");
+        assert_eq!(exec(&loaded, "GET gone").await, "$-1\n");
+        assert_eq!(
+            execute_command(
+                "GET other",
+                &loaded,
+                &mut Session {
+                    db: 3,
+                    ..Session::default()
+                }
+            )
+            .await,
+            "$db3\n"
+        );
+        assert_eq!(exec(&loaded, "GET other").await, "$-1\n");
+    }
+
+    #[test]
+    fn test_load_missing_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = load_snapshot(&dir.path().join("missing.json")).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_save_interval_arg() {
+        let args: Vec<String> = ["--save", "60", "--trace"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = Config::from_args(&args);
+
+        assert_eq!(config.save_interval, Some(Duration::from_secs(60)));
+        assert!(config.trace);
+    }
+
+    #[tokio::test]
+    async fn test_max_commands_closes_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = Arc::new(Config {
+            max_commands: Some(2),
+            ..Config::default()
+        });
+
+        tokio::spawn(async move {
+            let (socket, peer) = listener.accept().await.unwrap();
+            handle_client(socket, peer, Arc::new(Store::new()), config).await;
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"PING\nPING\nPING\n").await.unwrap();
+
+        // 前 2 条正常执行，第 3 条收到错误，之后连接被关闭
+        let mut reply = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut client, &mut reply)
+            .await
+            .unwrap();
+        assert_eq!(
+            reply,
+            "+PONG\n+PONG\n-ERR max commands per connection exceeded\n"
+        );
+    }
+
+    #[test]
+    fn test_max_commands_zero_is_unlimited() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let config = Config::from_args(&args(&["--max-commands-per-conn", "0"]));
+        assert_eq!(config.max_commands, None);
+
+        let config = Config::from_args(&args(&["--max-commands-per-conn", "100"]));
+        assert_eq!(config.max_commands, Some(100));
+    }
+
+    #[tokio::test]
+    async fn test_command_count() {
+        let store = Store::new();
+
+        let reply = exec(&store, "COMMAND COUNT").await;
+        assert_eq!(reply, format!(":{}\n", COMMANDS.len()));
+
+        let reply = exec(&store, "command").await;
+        assert!(reply.starts_with(&format!("*{}\n", COMMANDS.len())));
+        assert!(reply.contains("$get 2\n"));
+        assert!(reply.contains("$set -3\n"));
+    }
+
+    #[tokio::test]
+    async fn test_arity_checked_from_table() {
+        let store = Store::new();
+
+        assert_eq!(
+            exec(&store, "GET").await,
+            "-ERR wrong number of arguments for 'get' command\n"
+        );
+        assert_eq!(
+            exec(&store, "get a b").await,
+            "-ERR wrong number of arguments for 'get' command\n"
+        );
+        assert_eq!(
+            exec(&store, "LPUSH list").await,
+            "-ERR wrong number of arguments for 'lpush' command\n"
+        );
+        assert_eq!(exec(&store, "NOPE").await, "-ERROR unknown command\n");
+
+        // 每个登记的命令都能被找到
+        for spec in COMMANDS {
+            assert!(find_command(spec.name).is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blpop_receives_push() {
+        let store = Arc::new(Store::new());
+
+        let waiter = {
+            let store = Arc::clone(&store);
+            tokio::spawn(async move { exec(&store, "BLPOP jobs 5").await })
+        };
+
+        // 让 BLPOP 先进入等待，再由另一个「客户端」推入元素
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+        assert_eq!(exec(&store, "LPUSH jobs job1").await, ":1\n");
+
+        let reply = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("BLPOP 没有被唤醒")
+            .unwrap();
+        assert_eq!(reply, "*2\n$jobs\n$job1\n");
+
+        // 弹出最后一个元素后键被删除，通知器也被清理
+        assert_eq!(exec(&store, "LRANGE jobs 0 -1").await, "*0\n");
+        assert!(store.waiters.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_blpop_stops_when_client_disconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store = Arc::new(Store::new());
+
+        let server_store = Arc::clone(&store);
+        tokio::spawn(async move {
+            let (socket, peer) = listener.accept().await.unwrap();
+            handle_client(socket, peer, server_store, Arc::new(Config::default())).await;
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"BLPOP jobs 0\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(client);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // 等待的客户端已经断开，推入的元素不能被它弹走
+        exec(&store, "LPUSH jobs job1").await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(exec(&store, "LRANGE jobs 0 -1").await, "*1\n$job1\n");
+    }
+
+    #[tokio::test]
+    async fn test_blpop_immediate_and_timeout() {
+        let store = Store::new();
+        exec(&store, "LPUSH list a").await;
+
+        // 已有元素时立即返回
+        assert_eq!(exec(&store, "BLPOP list 1").await, "*2\n$list\n$a\n");
+
+        // 列表为空，超时后返回空
+        let start = std::time::Instant::now();
+        assert_eq!(exec(&store, "BLPOP list 0.1").await, "$-1\n");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        exec(&store, "SET s v").await;
+        assert_eq!(exec(&store, "BLPOP s 1").await, "-WRONGTYPE\n");
+        assert!(exec(&store, "BLPOP list -1").await.starts_with("-ERR"));
+        assert!(exec(&store, "BLPOP list 1e30").await.starts_with("-ERR"));
+        assert!(exec(&store, "BLPOP list inf").await.starts_with("-ERR"));
+    }
+
+    #[tokio::test]
+    async fn test_blpop_huge_timeout_waits() {
+        let store = Arc::new(Store::new());
+
+        // Duration 放得下、但加到 Instant 上会溢出的超时，按一直等待处理
+        let waiter = {
+            let store = Arc::clone(&store);
+            tokio::spawn(async move { exec(&store, "BLPOP jobs 1.8e19").await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+        exec(&store, "LPUSH jobs job1").await;
+
+        let reply = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("BLPOP 没有被唤醒")
+            .unwrap();
+        assert_eq!(reply, "*2\n$jobs\n$job1\n");
+    }
+
+    #[tokio::test]
+    async fn test_object_encoding_strings() {
+        let store = Store::new();
+        exec(&store, "SET counter 42").await;
+        exec(&store, "SET negative -7").await;
+        exec(&store, "SET name Alice").await;
+
+        assert_eq!(exec(&store, "OBJECT ENCODING counter").await, "+int\n");
+        assert_eq!(exec(&store, "OBJECT ENCODING negative").await, "+int\n");
+        assert_eq!(exec(&store, "object encoding name").await, "+raw\n");
+
+        assert_eq!(
+            exec(&store, "OBJECT ENCODING missing").await,
+            "-ERR no such key\n"
+        );
+        assert!(exec(&store, "OBJECT ENCODING").await.starts_with("-ERR"));
+        assert!(exec(&store, "OBJECT FREQ name").await.starts_with("-ERR"));
+    }
+
+    #[tokio::test]
+    async fn test_object_encoding_list_threshold() {
+        let store = Store::new();
+
+        // 正好 LIST_LISTPACK_MAX_ENTRIES 个元素时仍是 listpack
+        let items = vec!["x"; LIST_LISTPACK_MAX_ENTRIES].join(" ");
+        exec(&store, &format!("LPUSH list {}", items)).await;
+        assert_eq!(exec(&store, "OBJECT ENCODING list").await, "+listpack\n");
+
+        // 再多一个就切换成 quicklist
+        exec(&store, "LPUSH list y").await;
+        assert_eq!(exec(&store, "OBJECT ENCODING list").await, "+quicklist\n");
+    }
+
+    #[tokio::test]
+    async fn test_object_encoding_hash_threshold() {
+        let store = Store::new();
+
+        // 正好 HASH_LISTPACK_MAX_ENTRIES 个字段时仍是 listpack
+        let fields: Vec<String> = (0..HASH_LISTPACK_MAX_ENTRIES)
+            .map(|i| format!("f{} v", i))
+            .collect();
+        exec(&store, &format!("HSET user {}", fields.join(" "))).await;
+        assert_eq!(exec(&store, "OBJECT ENCODING user").await, "+listpack\n");
+
+        // 覆盖已有字段不增加字段数，编码不变
+        exec(&store, "HSET user f0 w").await;
+        assert_eq!(exec(&store, "OBJECT ENCODING user").await, "+listpack\n");
+
+        // 再多一个字段就切换成 hashtable
+        exec(&store, "HSET user extra v").await;
+        assert_eq!(exec(&store, "OBJECT ENCODING user").await, "+hashtable\n");
+    }
+
+    #[tokio::test]
+    async fn test_strlen() {
+        let store = Store::new();
+        exec(&store, "SET name Alice").await;
+        exec(&store, "SET city 北京").await;
+        exec(&store, "LPUSH list a").await;
+
+        assert_eq!(exec(&store, "STRLEN name").await, ":5\n");
+        // 按字节计算，一个汉字占 3 字节
+        assert_eq!(exec(&store, "STRLEN city").await, ":6\n");
+        assert_eq!(exec(&store, "STRLEN missing").await, ":0\n");
+        assert_eq!(exec(&store, "STRLEN list").await, "-WRONGTYPE\n");
+    }
+
+    #[tokio::test]
+    async fn test_lindex() {
+        let store = Store::new();
+        // LPUSH list c b a 之后列表是 c b a
+        exec(&store, "LPUSH list c b a").await;
+        exec(&store, "SET name Alice").await;
+
+        assert_eq!(exec(&store, "LINDEX list 0").await, "$c\n");
+        assert_eq!(exec(&store, "LINDEX list 2").await, "$a\n");
+        assert_eq!(exec(&store, "LINDEX list -1").await, "$a\n");
+        assert_eq!(exec(&store, "LINDEX list -3").await, "$c\n");
+
+        // 越界和不存在的键都返回空
+        assert_eq!(exec(&store, "LINDEX list 3").await, "$-1\n");
+        assert_eq!(exec(&store, "LINDEX list -4").await, "$-1\n");
+        assert_eq!(exec(&store, "LINDEX missing 0").await, "$-1\n");
+
+        assert_eq!(exec(&store, "LINDEX name 0").await, "-WRONGTYPE\n");
+        assert!(exec(&store, "LINDEX list x").await.starts_with("-ERR"));
+    }
+
+    #[tokio::test]
+    async fn test_append() {
+        let store = Store::new();
+
+        // 不存在的键：相当于 SET
+        assert_eq!(exec(&store, "APPEND greeting Hello").await, ":5\n");
+        assert_eq!(exec(&store, "GET greeting").await, "$Hello\n");
+
+        // 已有的字符串：追加并返回新长度（字节数）
+        assert_eq!(exec(&store, "APPEND greeting , world").await, ":12\n");
+        assert_eq!(exec(&store, "GET greeting").await, "$Hello, world\n");
+
+        exec(&store, "LPUSH list a").await;
+        assert_eq!(exec(&store, "APPEND list b").await, "-WRONGTYPE\n");
+        assert_eq!(exec(&store, "LRANGE list 0 -1").await, "*1\n$a\n");
+    }
+
+    #[tokio::test]
+    async fn test_keys_pattern() {
+        let store = Store::new();
+        // 空库返回空数组
+        assert_eq!(exec(&store, "KEYS *").await, "*0\n");
+
+        exec(&store, "SET user:1 Alice").await;
+        exec(&store, "SET user:2 Bob").await;
+        exec(&store, "SET session:9 x").await;
+        exec(&store, "LPUSH queue a").await;
+
+        assert_eq!(
+            exec(&store, "KEYS *").await,
+            "*4\n$queue\n$session:9\n$user:1\n$user:2\n"
+        );
+        assert_eq!(exec(&store, "KEYS user:*").await, "*2\n$user:1\n$user:2\n");
+        assert_eq!(exec(&store, "KEYS nothing*").await, "*0\n");
+
+        // 过期的键不出现在结果中
+        exec(&store, "EXPIRE session:9 0").await;
+        assert_eq!(exec(&store, "KEYS s*").await, "*0\n");
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("h?llo", "hello"));
+        assert!(!glob_match("h?llo", "hllo"));
+        assert!(glob_match("h*llo", "heeeello"));
+        assert!(glob_match("*:*:end", "a:b:c:end"));
+        assert!(glob_match("h[ae]llo", "hallo"));
+        assert!(!glob_match("h[ae]llo", "hillo"));
+        assert!(glob_match("h[^e]llo", "hallo"));
+        assert!(!glob_match("h[^e]llo", "hello"));
+        assert!(glob_match("key[0-9]", "key7"));
+        assert!(!glob_match("key[0-9]", "keyx"));
+        assert!(glob_match("a\\*b", "a*b"));
+        assert!(!glob_match("a\\*b", "axb"));
+        assert!(!glob_match("abc", "abcd"));
+    }
+
+    #[tokio::test]
+    async fn test_reset_returns_to_db0() {
+        let store = Store::new();
+        let mut session = Session::default();
+
+        execute_command("SET name db0", &store, &mut session).await;
+        execute_command("SELECT 2", &store, &mut session).await;
+        execute_command("SET name db2", &store, &mut session).await;
+
+        assert_eq!(
+            execute_command("RESET", &store, &mut session).await,
+            "+RESET\n"
+        );
+        assert_eq!(session.db, 0);
+        assert_eq!(
+            execute_command("GET name", &store, &mut session).await,
+            "$db0\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_randomkey_empty() {
+        let store = Store::new();
+        assert_eq!(exec(&store, "RANDOMKEY").await, "$-1\n");
+
+        // 只剩已过期（但还没被惰性删除）的键时也视为空
+        exec(&store, "SETEX temp 10 x").await;
+        store
+            .db(0)
+            .write()
+            .await
+            .expires
+            .insert("temp".to_string(), Instant::now());
+        assert_eq!(exec(&store, "RANDOMKEY").await, "$-1\n");
+    }
+
+    #[tokio::test]
+    async fn test_randomkey_single_key() {
+        let store = Store::new();
+        exec(&store, "SET only 1").await;
+
+        for _ in 0..10 {
+            assert_eq!(exec(&store, "RANDOMKEY").await, "$only\n");
+        }
+    }
+
+    #[test]
+    fn test_random_key_skips_expired() {
+        let mut ks = Keyspace::default();
+        for key in ["a", "b", "c"] {
+            ks.data
+                .insert(key.to_string(), Value::String(key.to_string()));
+        }
+        ks.expires.insert("b".to_string(), Instant::now());
+
+        for seed in 0..20 {
+            let key = random_key(&ks, seed).unwrap();
+            assert_ne!(key, "b");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_del_clears_ttl() {
+        let store = Store::new();
+        exec(&store, "SETEX session 100 token").await;
+        assert_eq!(exec(&store, "DEL session").await, ":1\n");
+        assert!(store.db(0).read().await.expires.is_empty());
+
+        // LPUSH 不会清除过期时间，重新创建的键不应带着旧的 TTL
+        exec(&store, "LPUSH session a").await;
+        assert_eq!(exec(&store, "TTL session").await, ":-1\n");
+    }
+
+    #[tokio::test]
+    async fn test_unlink_same_as_del() {
+        let store = Store::new();
+        exec(&store, "SET a 1").await;
+        exec(&store, "SETEX b 100 2").await;
+
+        assert_eq!(exec(&store, "UNLINK a b missing").await, ":2\n");
+        assert_eq!(exec(&store, "GET a").await, "$-1\n");
+        assert_eq!(exec(&store, "TTL b").await, ":-2\n");
+        assert!(exec(&store, "UNLINK").await.starts_with("-ERR"));
+    }
+
+    #[tokio::test]
+    async fn test_lset() {
+        let store = Store::new();
+        exec(&store, "LPUSH list c b a").await;
+
+        assert_eq!(exec(&store, "LSET list 0 x").await, "+OK\n");
+        assert_eq!(exec(&store, "LSET list -1 z").await, "+OK\n");
+        assert_eq!(
+            exec(&store, "LRANGE list 0 -1").await,
+            format_array(&["x".to_string(), "b".to_string(), "z".to_string()])
+        );
+
+        assert_eq!(
+            exec(&store, "LSET list 3 y").await,
+            "-ERR index out of range\n"
+        );
+        assert_eq!(
+            exec(&store, "LSET list -4 y").await,
+            "-ERR index out of range\n"
+        );
+        assert_eq!(exec(&store, "LSET missing 0 y").await, "-ERR no such key\n");
+        assert!(exec(&store, "LSET list one y").await.starts_with("-ERR"));
+
+        exec(&store, "SET name Alice").await;
+        assert_eq!(exec(&store, "LSET name 0 y").await, "-WRONGTYPE\n");
+    }
+
+    #[tokio::test]
+    async fn test_linsert() {
+        let store = Store::new();
+        exec(&store, "LPUSH list c b a b").await;
+
+        // 以第一个 b 为基准
+        assert_eq!(exec(&store, "LINSERT list BEFORE b x").await, ":5\n");
+        assert_eq!(exec(&store, "LINSERT list after c y").await, ":6\n");
+        assert_eq!(
+            exec(&store, "LRANGE list 0 -1").await,
+            format_array(&["c", "y", "x", "b", "a", "b"].map(String::from))
+        );
+
+        assert_eq!(exec(&store, "LINSERT list BEFORE nope z").await, ":-1\n");
+        assert_eq!(exec(&store, "LINSERT missing BEFORE a z").await, ":0\n");
+        assert_eq!(exec(&store, "GET missing").await, "$-1\n");
+        assert_eq!(
+            exec(&store, "LINSERT list MIDDLE a z").await,
+            "-ERR syntax error\n"
+        );
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sort_numeric() {
+        let store = Store::new();
+        exec(&store, "LPUSH nums 10 2 -3 1.5").await;
+
+        assert_eq!(
+            exec(&store, "SORT nums").await,
+            format_array(&strings(&["-3", "1.5", "2", "10"]))
+        );
+        assert_eq!(
+            exec(&store, "SORT nums desc").await,
+            format_array(&strings(&["10", "2", "1.5", "-3"]))
+        );
+        // 存储的列表没有被修改
+        assert_eq!(
+            exec(&store, "LRANGE nums 0 -1").await,
+            format_array(&strings(&["10", "2", "-3", "1.5"]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sort_alpha() {
+        let store = Store::new();
+        exec(&store, "LPUSH words pear 10 apple 9").await;
+
+        // 按字符串比较时 "10" 排在 "9" 前面
+        assert_eq!(
+            exec(&store, "SORT words ALPHA").await,
+            format_array(&strings(&["10", "9", "apple", "pear"]))
+        );
+        assert_eq!(
+            exec(&store, "SORT words ALPHA DESC").await,
+            format_array(&strings(&["pear", "apple", "9", "10"]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sort_errors() {
+        let store = Store::new();
+        exec(&store, "LPUSH words pear 10").await;
+        exec(&store, "SET name Alice").await;
+
+        assert_eq!(
+            exec(&store, "SORT words").await,
+            "-ERR One or more scores can't be converted into double\n"
+        );
+        assert_eq!(exec(&store, "SORT words BY").await, "-ERR syntax error\n");
+        assert_eq!(exec(&store, "SORT name").await, "-WRONGTYPE\n");
+        assert_eq!(exec(&store, "SORT missing").await, "*0\n");
+    }
+
+    #[tokio::test]
+    async fn test_incr() {
+        let store = Store::new();
+        assert_eq!(exec(&store, "INCR counter").await, ":1\n");
+        assert_eq!(exec(&store, "INCR counter").await, ":2\n");
+
+        exec(&store, "SET name alice").await;
+        assert_eq!(
+            exec(&store, "INCR name").await,
+            "-ERR value is not an integer or out of range\n"
+        );
+
+        exec(&store, &format!("SET big {}", i64::MAX)).await;
+        assert_eq!(
+            exec(&store, "INCR big").await,
+            "-ERR increment or decrement would overflow\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multi_exec() {
+        let store = Store::new();
+        let mut session = Session::default();
+
+        assert_eq!(
+            execute_command("MULTI", &store, &mut session).await,
+            "+OK\n"
+        );
+        assert_eq!(
+            execute_command("SET counter 10", &store, &mut session).await,
+            "+QUEUED\n"
+        );
+        assert_eq!(
+            execute_command("INCR counter", &store, &mut session).await,
+            "+QUEUED\n"
+        );
+        assert_eq!(
+            execute_command("GET counter", &store, &mut session).await,
+            "+QUEUED\n"
+        );
+
+        // EXEC 之前命令还没有执行
+        assert_eq!(exec(&store, "GET counter").await, "$-1\n");
+
+        assert_eq!(
+            execute_command("EXEC", &store, &mut session).await,
+            "*3\n+OK\n:11\n$11\n"
+        );
+        assert_eq!(exec(&store, "GET counter").await, "$11\n");
+    }
+
+    #[tokio::test]
+    async fn test_exec_gate_blocks_blpop_and_snapshot() {
+        let store = Arc::new(Store::new());
+        let waiter = {
+            let store = Arc::clone(&store);
+            tokio::spawn(async move { exec(&store, "BLPOP jobs 5").await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // 模拟 EXEC：持有闸门写锁，在事务中推入元素
+        let gate = store.exec_gate.write().await;
+        let mut session = Session::default();
+        run_command("RPUSH", &["RPUSH", "jobs", "job1"], &store, &mut session).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.json");
+        let saver = {
+            let (store, path) = (Arc::clone(&store), path.clone());
+            tokio::spawn(async move { save_snapshot(&store, &path).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // 事务结束之前，BLPOP 不能弹出元素，快照也不会写出
+        assert_eq!(
+            run_command(
+                "LRANGE",
+                &["LRANGE", "jobs", "0", "-1"],
+                &store,
+                &mut session
+            )
+            .await,
+            "*1\n$job1\n"
+        );
+        assert!(!waiter.is_finished());
+        assert!(!saver.is_finished());
+
+        drop(gate);
+        assert_eq!(waiter.await.unwrap(), "*2\n$jobs\n$job1\n");
+        saver.await.unwrap().unwrap();
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_errors() {
+        let store = Store::new();
+        let mut session = Session::default();
+
+        assert_eq!(
+            execute_command("EXEC", &store, &mut session).await,
+            "-ERR EXEC without MULTI\n"
+        );
+        assert_eq!(
+            execute_command("DISCARD", &store, &mut session).await,
+            "-ERR DISCARD without MULTI\n"
+        );
+
+        execute_command("MULTI", &store, &mut session).await;
+        assert_eq!(
+            execute_command("MULTI", &store, &mut session).await,
+            "-ERR MULTI calls can not be nested\n"
+        );
+        execute_command("SET name alice", &store, &mut session).await;
+        assert_eq!(
+            execute_command("DISCARD", &store, &mut session).await,
+            "+OK\n"
+        );
+        assert_eq!(exec(&store, "GET name").await, "$-1\n");
+        assert_eq!(
+            execute_command("EXEC", &store, &mut session).await,
+            "-ERR EXEC without MULTI\n"
+        );
+    }
+
+    /// 带列表长度上限的 store
+    fn store_with_limit(max_len: usize, overflow: ListOverflow) -> Store {
+        let mut store = Store::new();
+        store.list_limit = Some(ListLimit { max_len, overflow });
+        store
+    }
+
+    #[tokio::test]
+    async fn test_rpush_appends() {
+        let store = Store::new();
+        assert_eq!(exec(&store, "RPUSH list a b").await, ":2\n");
+        assert_eq!(exec(&store, "LPUSH list x").await, ":3\n");
+        assert_eq!(exec(&store, "RPUSH list c").await, ":4\n");
+        assert_eq!(
+            exec(&store, "LRANGE list 0 -1").await,
+            "*4\n$x\n$a\n$b\n$c\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_list_len_rejects_whole_push() {
+        let store = store_with_limit(3, ListOverflow::Reject);
+        assert_eq!(exec(&store, "RPUSH list a b").await, ":2\n");
+
+        // 只放得下一个，两个都不写入
+        assert_eq!(
+            exec(&store, "LPUSH list x y").await,
+            "-ERR list would exceed max length of 3\n"
+        );
+        assert_eq!(exec(&store, "LRANGE list 0 -1").await, "*2\n$a\n$b\n");
+
+        assert_eq!(exec(&store, "RPUSH list c").await, ":3\n");
+        assert!(exec(&store, "RPUSH list d").await.starts_with("-ERR"));
+
+        // 新键一次就超出上限时，不会留下空列表
+        assert!(exec(&store, "RPUSH other 1 2 3 4")
+            .await
+            .starts_with("-ERR"));
+        assert_eq!(exec(&store, "KEYS *").await, "*1\n$list\n");
+    }
+
+    #[tokio::test]
+    async fn test_max_list_len_trims_far_end() {
+        let store = store_with_limit(3, ListOverflow::Trim);
+        assert_eq!(exec(&store, "RPUSH list a b c").await, ":3\n");
+
+        // RPUSH 写在尾部，从头部裁剪
+        assert_eq!(exec(&store, "RPUSH list d").await, ":3\n");
+        assert_eq!(exec(&store, "LRANGE list 0 -1").await, "*3\n$b\n$c\n$d\n");
+
+        // LPUSH 写在头部，从尾部裁剪
+        assert_eq!(exec(&store, "LPUSH list x y").await, ":3\n");
+        assert_eq!(exec(&store, "LRANGE list 0 -1").await, "*3\n$x\n$y\n$b\n");
+    }
+
+    #[test]
+    fn test_parse_list_limit() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(Config::from_args(&[]).list_limit(), None);
+        let config = Config::from_args(&args(&["--max-list-len", "100"]));
+        assert_eq!(
+            config.list_limit(),
+            Some(ListLimit {
+                max_len: 100,
+                overflow: ListOverflow::Reject,
+            })
+        );
+        let config = Config::from_args(&args(&["--max-list-len", "5", "--list-overflow", "trim"]));
+        assert_eq!(config.list_limit().unwrap().overflow, ListOverflow::Trim);
+        let config = Config::from_args(&args(&["--max-list-len", "0"]));
+        assert_eq!(config.list_limit(), None);
+    }
+
+    #[tokio::test]
+    async fn test_persist() {
+        let store = Store::new();
+        exec(&store, "SET session abc").await;
+        assert_eq!(exec(&store, "PERSIST session").await, ":0\n");
+
+        exec(&store, "EXPIRE session 100").await;
+        assert_eq!(exec(&store, "PERSIST session").await, ":1\n");
+        assert_eq!(exec(&store, "TTL session").await, ":-1\n");
+        assert_eq!(exec(&store, "PERSIST missing").await, ":0\n");
+    }
+
+    #[tokio::test]
+    async fn test_persist_after_expire() {
+        let store = Store::new();
+        exec(&store, "SET session abc").await;
+        // 直接把过期时刻设成过去，模拟 TTL 已到但还没被访问
+        store.db(0).write().await.expires.insert(
+            "session".to_string(),
+            Instant::now() - Duration::from_secs(1),
+        );
+
+        // 已过期的键不能再被 PERSIST 救回来
+        assert_eq!(exec(&store, "PERSIST session").await, ":0\n");
+        assert_eq!(exec(&store, "GET session").await, "$-1\n");
+    }
+
+    #[tokio::test]
+    async fn test_expireat() {
+        let store = Store::new();
+        exec(&store, "SET session abc").await;
+
+        let in_100s = unix_millis(SystemTime::now()) / 1000 + 100;
+        let reply = exec(&store, &format!("EXPIREAT session {}", in_100s)).await;
+        assert_eq!(reply, ":1\n");
+        let ttl = exec(&store, "TTL session").await;
+        assert!(ttl == ":100\n" || ttl == ":99\n", "TTL 为 {}", ttl);
+
+        assert_eq!(exec(&store, "EXPIREAT missing 1").await, ":0\n");
+        assert_eq!(
+            exec(&store, "EXPIREAT session soon").await,
+            "-ERR value is not an integer\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expireat_past_deletes() {
+        let store = Store::new();
+        exec(&store, "SET a 1").await;
+        exec(&store, "SET b 2").await;
+
+        assert_eq!(exec(&store, "EXPIREAT a 1000000000").await, ":1\n");
+        assert_eq!(exec(&store, "EXPIREAT b -5").await, ":1\n");
+        assert_eq!(exec(&store, "GET a").await, "$-1\n");
+        assert_eq!(exec(&store, "TTL b").await, ":-2\n");
+    }
+
+    #[tokio::test]
+    async fn test_expireat_far_future_rejected() {
+        let store = Store::new();
+        exec(&store, "SET a 1").await;
+
+        // 离现在太远的时间点换算成 Instant 会溢出，回复错误，键和 TTL 保持不变
+        assert_eq!(
+            exec(&store, "EXPIREAT a 9223372036854775807").await,
+            "-ERR invalid expire time in 'expireat' command\n"
+        );
+        assert_eq!(exec(&store, "TTL a").await, ":-1\n");
+    }
+
+    #[tokio::test]
+    async fn test_hincrby_creates_from_absent() {
+        let store = Store::new();
+        // 键和字段都不存在时从 0 开始
+        assert_eq!(exec(&store, "HINCRBY user:1 visits 5").await, ":5\n");
+        assert_eq!(exec(&store, "HINCRBY user:1 visits -2").await, ":3\n");
+        assert_eq!(exec(&store, "HGET user:1 visits").await, "$3\n");
+
+        // 已有哈希中新增字段
+        assert_eq!(exec(&store, "HSET user:1 name alice").await, ":1\n");
+        assert_eq!(exec(&store, "HINCRBY user:1 age 1").await, ":1\n");
+        assert_eq!(exec(&store, "HGET user:1 name").await, "$alice\n");
+        assert_eq!(exec(&store, "OBJECT ENCODING user:1").await, "+listpack\n");
+    }
+
+    #[tokio::test]
+    async fn test_hincrby_errors() {
+        let store = Store::new();
+        exec(&store, "SET name alice").await;
+        assert_eq!(exec(&store, "HINCRBY name visits 1").await, "-WRONGTYPE\n");
+        // 出错时不会把字符串改成哈希
+        assert_eq!(exec(&store, "GET name").await, "$alice\n");
+
+        exec(&store, "HSET user:1 name alice").await;
+        assert_eq!(
+            exec(&store, "HINCRBY user:1 name 1").await,
+            "-ERR hash value is not an integer\n"
+        );
+        assert_eq!(
+            exec(&store, "HINCRBY user:1 visits many").await,
+            "-ERR value is not an integer or out of range\n"
+        );
+        exec(&store, &format!("HSET user:1 big {}", i64::MAX)).await;
+        assert_eq!(
+            exec(&store, "HINCRBY user:1 big 1").await,
+            "-ERR increment or decrement would overflow\n"
+        );
+        assert_eq!(exec(&store, "GET user:1").await, "-WRONGTYPE\n");
+    }
+
+    #[tokio::test]
+    async fn test_hset_counts_new_fields() {
+        let store = Store::new();
+        assert_eq!(exec(&store, "HSET h a 1 b 2").await, ":2\n");
+        assert_eq!(exec(&store, "HSET h a 10 c 3").await, ":1\n");
+        assert_eq!(exec(&store, "HGET h a").await, "$10\n");
+        assert_eq!(exec(&store, "HGET h missing").await, "$-1\n");
+        assert_eq!(
+            exec(&store, "HSET h a").await,
+            "-ERR wrong number of arguments for 'hset' command\n"
+        );
+        assert_eq!(
+            exec(&store, "HSET h a 1 b").await,
+            "-ERR wrong number of arguments for 'hset' command\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_psubscribe_receives_matching_publish() {
+        let store = Store::new();
+        let mut subscriber = Session::default();
+        assert_eq!(
+            execute_command("PSUBSCRIBE news.*", &store, &mut subscriber).await,
+            "*3\n$psubscribe\n$news.*\n:1\n"
+        );
+
+        assert_eq!(exec(&store, "PUBLISH news.tech rust 2024").await, ":1\n");
+        let (_, inbox) = subscriber.inbox.as_mut().unwrap();
+        assert_eq!(
+            inbox.try_recv().unwrap(),
+            "*4\n$pmessage\n$news.*\n$news.tech\n$rust 2024\n"
+        );
+
+        // 不匹配的频道没有接收者
+        assert_eq!(exec(&store, "PUBLISH sports.ball goal").await, ":0\n");
+        assert!(inbox.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_publish_delivers_once_per_subscriber() {
+        let store = Store::new();
+        let mut subscriber = Session::default();
+        assert_eq!(
+            execute_command("PSUBSCRIBE news.* n*", &store, &mut subscriber).await,
+            "*3\n$psubscribe\n$news.*\n:1\n*3\n$psubscribe\n$n*\n:2\n"
+        );
+
+        // 两个模式都匹配，只推送一次，用排序后的第一个模式
+        assert_eq!(exec(&store, "PUBLISH news.tech hi").await, ":1\n");
+        let (_, inbox) = subscriber.inbox.as_mut().unwrap();
+        assert_eq!(
+            inbox.try_recv().unwrap(),
+            "*4\n$pmessage\n$n*\n$news.tech\n$hi\n"
+        );
+        assert!(inbox.try_recv().is_err());
+
+        // 同时有精确订阅时以 message 形式推送，仍然只有一次
+        execute_command("SUBSCRIBE news.tech", &store, &mut subscriber).await;
+        assert_eq!(exec(&store, "PUBLISH news.tech again").await, ":1\n");
+        let (_, inbox) = subscriber.inbox.as_mut().unwrap();
+        assert_eq!(
+            inbox.try_recv().unwrap(),
+            "*3\n$message\n$news.tech\n$again\n"
+        );
+        assert!(inbox.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reset_drops_subscriptions() {
+        let store = Store::new();
+        let mut subscriber = Session::default();
+        execute_command("PSUBSCRIBE *", &store, &mut subscriber).await;
+        execute_command("RESET", &store, &mut subscriber).await;
+
+        assert!(subscriber.inbox.is_none());
+        assert_eq!(exec(&store, "PUBLISH any msg").await, ":0\n");
+        assert!(store.subscribers.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_psubscribe_pushes_over_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store = Arc::new(Store::new());
+        let config = Arc::new(Config::default());
+
+        let server_store = Arc::clone(&store);
+        tokio::spawn(async move {
+            loop {
+                let (socket, peer) = listener.accept().await.unwrap();
+                let (store, config) = (Arc::clone(&server_store), Arc::clone(&config));
+                tokio::spawn(handle_client(socket, peer, store, config));
+            }
+        });
+
+        let subscriber = TcpStream::connect(addr).await.unwrap();
+        let mut subscriber = BufReader::new(subscriber);
+        subscriber.write_all(b"PSUBSCRIBE news.*\n").await.unwrap();
+        let mut reply = String::new();
+        for _ in 0..4 {
+            subscriber.read_line(&mut reply).await.unwrap();
+        }
+        assert_eq!(reply, "*3\n$psubscribe\n$news.*\n:1\n");
+
+        let mut publisher = TcpStream::connect(addr).await.unwrap();
+        publisher
+            .write_all(b"PUBLISH news.tech hello\n")
+            .await
+            .unwrap();
+
+        let mut push = String::new();
+        for _ in 0..5 {
+            subscriber.read_line(&mut push).await.unwrap();
+        }
+        assert_eq!(push, "*4\n$pmessage\n$news.*\n$news.tech\n$hello\n");
+    }
+
+    #[tokio::test]
+    async fn test_push_does_not_drop_partial_command() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store = Arc::new(Store::new());
+
+        let server_store = Arc::clone(&store);
+        tokio::spawn(async move {
+            let (socket, peer) = listener.accept().await.unwrap();
+            handle_client(socket, peer, server_store, Arc::new(Config::default())).await;
+        });
+
+        let mut subscriber = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        subscriber.write_all(b"PSUBSCRIBE news.*\n").await.unwrap();
+        let mut reply = String::new();
+        for _ in 0..4 {
+            subscriber.read_line(&mut reply).await.unwrap();
+        }
+
+        // 命令只发了一半，等服务端读到这一半后再推送消息
+        subscriber.write_all(b"SUBSCRIBE sp").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(store.publish("news.tech", "hello"), 1);
+
+        let mut push = String::new();
+        for _ in 0..5 {
+            subscriber.read_line(&mut push).await.unwrap();
+        }
+        assert_eq!(push, "*4\n$pmessage\n$news.*\n$news.tech\n$hello\n");
+
+        // 补上后半截，服务端看到的是完整的 SUBSCRIBE sports
+        subscriber.write_all(b"orts\n").await.unwrap();
+        let mut reply = String::new();
+        for _ in 0..4 {
+            subscriber.read_line(&mut reply).await.unwrap();
+        }
+        assert_eq!(reply, "*3\n$subscribe\n$sports\n:2\n");
+    }
+}